@@ -12,14 +12,20 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use autocxx_parser::file_locations::FileLocationStrategy;
-use proc_macro2::TokenStream;
+use autocxx_parser::{file_locations::FileLocationStrategy, DirectiveError};
 
-use crate::{strip_system_headers, CppCodegenOptions, ParseError, RebuildDependencyRecorder};
+use crate::cache::{BindingsCache, CachingDependencyRecorder, GeneratedFiles};
+use crate::{
+    rust_to_clang_target, strip_system_headers, CompileCommandFlags, CompileCommandsError,
+    CppCodegenOptions, CppStandard, FrozenBindings, ParseError, ParsedFile,
+    RebuildDependencyRecorder, CXXGEN_H,
+};
+use std::collections::HashSet;
 use std::ffi::OsString;
 use std::io::Write;
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::{ffi::OsStr, io, process};
 use std::{fmt::Display, fs::File};
 
@@ -39,6 +45,14 @@ pub enum BuilderError {
     NoIncludeCxxMacrosFound,
     /// Unable to create one of the directories to which we need to write
     UnableToCreateDirectory(std::io::Error, PathBuf),
+    /// A glob pattern given for the .rs files was invalid.
+    InvalidGlob(glob::PatternError),
+    /// A path matching a glob pattern couldn't be read.
+    GlobFailure(glob::GlobError),
+    /// Frozen bindings were requested, but were missing or out of date.
+    FrozenBindings(String),
+    /// The `compile_commands.json` couldn't be used.
+    CompileCommands(CompileCommandsError),
 }
 
 impl Display for BuilderError {
@@ -49,6 +63,10 @@ impl Display for BuilderError {
             BuilderError::FileWriteFail(ee, pb) => write!(f, "Unable to write to {}: {}", pb.to_string_lossy(), ee)?,
             BuilderError::NoIncludeCxxMacrosFound => write!(f, "No include_cpp! macro found")?,
             BuilderError::UnableToCreateDirectory(ee, pb) => write!(f, "Unable to create directory {}: {}", pb.to_string_lossy(), ee)?,
+            BuilderError::InvalidGlob(ee) => write!(f, "Invalid glob pattern for .rs files: {}", ee)?,
+            BuilderError::GlobFailure(ee) => write!(f, "Unable to read path matching glob pattern: {}", ee)?,
+            BuilderError::CompileCommands(ee) => write!(f, "{}", ee)?,
+            BuilderError::FrozenBindings(reason) => write!(f, "Unable to use frozen bindings: {}. Regenerate them using autocxx-gen --freeze.", reason)?,
         }
         Ok(())
     }
@@ -84,13 +102,22 @@ pub trait BuilderContext {
 /// `autocxx_gen` crates.
 #[cfg_attr(feature = "nightly", doc(cfg(feature = "build")))]
 pub struct Builder<BuilderContext> {
-    rs_file: PathBuf,
+    rs_files: Vec<PathBuf>,
+    rs_globs: Vec<String>,
     autocxx_incs: Vec<OsString>,
     extra_clang_args: Vec<String>,
-    dependency_recorder: Option<Box<dyn RebuildDependencyRecorder>>,
+    cpp_standard: Option<CppStandard>,
+    compile_commands: Option<(PathBuf, PathBuf)>,
+    target: Option<String>,
+    sysroot: Option<PathBuf>,
     custom_gendir: Option<PathBuf>,
     auto_allowlist: bool,
     cpp_codegen_options: CppCodegenOptions,
+    frozen_bindings: Option<PathBuf>,
+    regenerate_stale_frozen_bindings: bool,
+    cache_bindings: bool,
+    report: Option<PathBuf>,
+    strict: bool,
     // This member is to ensure that this type is parameterized
     // by a BuilderContext. The goal is to balance three needs:
     // (1) have most of the functionality over in autocxx_engine,
@@ -106,29 +133,104 @@ impl<CTX: BuilderContext> Builder<CTX> {
     pub fn new(
         rs_file: impl AsRef<Path>,
         autocxx_incs: impl IntoIterator<Item = impl AsRef<OsStr>>,
+    ) -> Self {
+        Self::new_multi(std::iter::once(rs_file), autocxx_incs)
+    }
+
+    /// Like `new`, but processes the `include_cpp!` macros in several .rs
+    /// files at once, producing a single [`cc::Build`] for all of them.
+    /// Each `include_cpp!` must have a distinct mod name. Since each file
+    /// has its own `cxxgen.h`, if there's more than one file, hand-written
+    /// C++ must include that of the `n`th file (counting from zero, in the
+    /// order given, followed by those matched by
+    /// [`Builder::rs_files_glob`]) as `file<n>/cxxgen.h`.
+    pub fn new_multi(
+        rs_files: impl IntoIterator<Item = impl AsRef<Path>>,
+        autocxx_incs: impl IntoIterator<Item = impl AsRef<OsStr>>,
     ) -> Self {
         CTX::setup();
         Self {
-            rs_file: rs_file.as_ref().to_path_buf(),
+            rs_files: rs_files
+                .into_iter()
+                .map(|f| f.as_ref().to_path_buf())
+                .collect(),
+            rs_globs: Vec::new(),
             autocxx_incs: autocxx_incs
                 .into_iter()
                 .map(|s| s.as_ref().to_os_string())
                 .collect(),
             extra_clang_args: Vec::new(),
-            dependency_recorder: CTX::get_dependency_recorder(),
+            cpp_standard: None,
+            compile_commands: None,
+            target: None,
+            sysroot: None,
             custom_gendir: None,
             auto_allowlist: false,
             cpp_codegen_options: CppCodegenOptions::default(),
+            frozen_bindings: None,
+            regenerate_stale_frozen_bindings: false,
+            cache_bindings: false,
+            report: None,
+            strict: false,
             ctx: PhantomData,
         }
     }
 
+    /// Also process all .rs files matching the given glob pattern,
+    /// e.g. `src/**/*.rs`. Files with no `include_cpp!` are ignored.
+    pub fn rs_files_glob(mut self, pattern: &str) -> Self {
+        self.rs_globs.push(pattern.to_string());
+        self
+    }
+
     /// Specify extra arguments for clang.
     pub fn extra_clang_args(mut self, extra_clang_args: &[&str]) -> Self {
         self.extra_clang_args = extra_clang_args.iter().map(|s| s.to_string()).collect();
         self
     }
 
+    /// Which C++ standard to use. This applies when parsing the C++
+    /// headers, and is also set on the returned [`cc::Build`] so that
+    /// C++ code built with it matches. If unspecified, headers are parsed
+    /// as C++14 and the [`cc::Build`] is left at the compiler's default.
+    pub fn cpp_standard(mut self, cpp_standard: CppStandard) -> Self {
+        self.cpp_standard = Some(cpp_standard);
+        self
+    }
+
+    /// Take include directories, defines and the C++ standard from the
+    /// entry for `for_file` in a `compile_commands.json`, such as that
+    /// produced by CMake. These are used both when parsing headers and on
+    /// the returned [`cc::Build`]. A standard given to
+    /// [`Builder::cpp_standard`] takes precedence.
+    pub fn compile_commands(
+        mut self,
+        compile_commands: impl AsRef<Path>,
+        for_file: impl AsRef<Path>,
+    ) -> Self {
+        self.compile_commands = Some((
+            compile_commands.as_ref().to_path_buf(),
+            for_file.as_ref().to_path_buf(),
+        ));
+        self
+    }
+
+    /// The Rust target triple for which to generate bindings. By default,
+    /// when cross-compiling, this is Cargo's `TARGET`, such that the layout
+    /// of C++ types matches that on the target rather than the host.
+    pub fn target(mut self, target: &str) -> Self {
+        self.target = Some(target.to_string());
+        self
+    }
+
+    /// The sysroot in which to find headers for the target. By default,
+    /// when cross-compiling, this is taken from a `SYSROOT_<target>` or
+    /// `SYSROOT` environment variable, if set.
+    pub fn sysroot(mut self, sysroot: impl AsRef<Path>) -> Self {
+        self.sysroot = Some(sysroot.as_ref().to_path_buf());
+        self
+    }
+
     /// Where to generate the code.
     pub fn custom_gendir(mut self, custom_gendir: PathBuf) -> Self {
         self.custom_gendir = Some(custom_gendir);
@@ -179,6 +281,59 @@ impl<CTX: BuilderContext> Builder<CTX> {
         self
     }
 
+    /// Use bindings previously written to this directory by
+    /// `autocxx-gen --freeze` instead of generating them, such that
+    /// libclang is not needed. The frozen bindings are only used if
+    /// the .rs file, headers, clang arguments and version of autocxx
+    /// all match those from which they were generated; otherwise the
+    /// build fails, unless [`Builder::regenerate_stale_frozen_bindings`]
+    /// is set. Only a single .rs file is supported. When cross-compiling,
+    /// bindings frozen with or without `--target` are accepted, unless
+    /// [`Builder::target`] or [`Builder::sysroot`] are given explicitly,
+    /// so take care that bindings frozen on the host suit the target.
+    pub fn frozen_bindings(mut self, dir: impl AsRef<Path>) -> Self {
+        self.frozen_bindings = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// If frozen bindings are out of date, generate fresh bindings
+    /// (which requires libclang) rather than failing.
+    pub fn regenerate_stale_frozen_bindings(mut self, do_it: bool) -> Self {
+        self.regenerate_stale_frozen_bindings = do_it;
+        self
+    }
+
+    /// Cache generated bindings on disk, keyed by the `include_cpp!`
+    /// configuration, preprocessed headers, clang arguments and version of
+    /// autocxx. If none of those have changed since the last build, bindgen
+    /// isn't run at all and output files are left untouched. Computing the
+    /// key needs the C++ preprocessor (see [`crate::get_clang_path`]); if
+    /// that can't be run, bindings are generated as normal.
+    pub fn cache_bindings(mut self, do_it: bool) -> Self {
+        self.cache_bindings = do_it;
+        self
+    }
+
+    /// Write a JSON report to this path, listing every API considered
+    /// with its C++ and Rust names, its kind, whether bindings were
+    /// generated, and if not, why not. The report is an array of objects
+    /// with keys `mod`, `cpp_name`, `rust_name`, `kind`, `generated`,
+    /// `reason`, `dependency_chain`, `type_kind`, `non_pod_reason` and
+    /// `synthesized`. No report is written when frozen bindings are used.
+    pub fn report(mut self, path: impl AsRef<Path>) -> Self {
+        self.report = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Fail the build if anything explicitly requested with `generate!` or
+    /// `generate_pod!`, or any method of such a type, can't be bound. This
+    /// is equivalent to adding `strict!()` to every `include_cpp!`;
+    /// `allow_skip!` may still be used to permit specific omissions.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
     /// Build autocxx C++ files and return a cc::Build you can use to build
     /// more from a build.rs file.
     pub fn build(self) -> Result<BuilderBuild, BuilderError> {
@@ -188,11 +343,56 @@ impl<CTX: BuilderContext> Builder<CTX> {
     /// For use in tests only, this does the build and returns additional information
     /// about the files generated which can subsequently be examined for correctness.
     pub fn build_listing_files(self) -> Result<BuilderSuccess, BuilderError> {
-        let clang_args = &self
-            .extra_clang_args
+        let compile_command_flags = match &self.compile_commands {
+            Some((compile_commands, for_file)) => {
+                CompileCommandFlags::from_compile_commands(compile_commands, for_file)
+                    .map_err(BuilderError::CompileCommands)?
+            }
+            None => CompileCommandFlags::default(),
+        };
+        // The standard goes first, such that extra_clang_args may still
+        // override it.
+        let cpp_standard = self.cpp_standard.or(compile_command_flags.cpp_standard);
+        let std_arg = cpp_standard.map(|std| std.clang_arg());
+        let target = self.target.clone().or_else(cross_compilation_target);
+        let sysroot = self.sysroot.clone().or_else(|| {
+            target
+                .as_deref()
+                .and_then(|target| sysroot_from_vars(target, |var| std::env::var_os(var)))
+        });
+        // Each with whether it was given explicitly, rather than worked
+        // out from the environment.
+        let target_args = target
             .iter()
-            .map(|s| &s[..])
+            .map(|target| {
+                (
+                    format!("--target={}", rust_to_clang_target(target)),
+                    self.target.is_some(),
+                )
+            })
+            .chain(sysroot.iter().map(|sysroot| {
+                (
+                    format!("--sysroot={}", sysroot.to_string_lossy()),
+                    self.sysroot.is_some(),
+                )
+            }))
             .collect::<Vec<_>>();
+        let define_args = compile_command_flags.define_args();
+        let make_clang_args = |include_automatic_target_args: bool| {
+            std_arg
+                .iter()
+                .chain(
+                    target_args
+                        .iter()
+                        .filter(|(_, explicit)| *explicit || include_automatic_target_args)
+                        .map(|(arg, _)| arg),
+                )
+                .chain(define_args.iter())
+                .chain(self.extra_clang_args.iter())
+                .map(|s| &s[..])
+                .collect::<Vec<_>>()
+        };
+        let clang_args = &make_clang_args(true);
         rust_version_check();
         let gen_location_strategy = match self.custom_gendir {
             None => FileLocationStrategy::new(),
@@ -215,51 +415,181 @@ impl<CTX: BuilderContext> Builder<CTX> {
             &Self::get_cxx_header_bytes(self.cpp_codegen_options.suppress_system_headers),
         )?;
 
-        let autocxx_inc = build_autocxx_inc(self.autocxx_incs, &incdir);
+        let autocxx_inc = build_autocxx_inc(
+            self.autocxx_incs.into_iter().chain(
+                compile_command_flags
+                    .include_dirs
+                    .iter()
+                    .map(|dir| dir.into()),
+            ),
+            &incdir,
+        );
         gen_location_strategy.set_cargo_env_vars_for_build();
 
-        let mut parsed_file = crate::parse_file(self.rs_file, self.auto_allowlist)
-            .map_err(BuilderError::ParseError)?;
-        parsed_file
-            .resolve_all(
-                autocxx_inc,
-                clang_args,
-                self.dependency_recorder,
-                &self.cpp_codegen_options,
-            )
-            .map_err(BuilderError::ParseError)?;
+        let mut rs_files = self.rs_files;
+        for pattern in &self.rs_globs {
+            for path in glob::glob(pattern).map_err(BuilderError::InvalidGlob)? {
+                rs_files.push(path.map_err(BuilderError::GlobFailure)?);
+            }
+        }
+        // Each file's code includes a "cxxgen.h", so if there are several
+        // files, each one's goes in its own subdirectory of the include
+        // directory and is included by that path.
+        let separate_cxxgen_h = rs_files.len() > 1;
         let mut counter = 0;
         let mut builder = cc::Build::new();
         builder.cpp(true);
+        if let Some(target) = &self.target {
+            builder.target(target);
+        }
+        let msvc = matches!(
+            target.clone().or_else(|| std::env::var("TARGET").ok()),
+            Some(target) if target.contains("msvc")
+        );
+        // This must be the same standard as was used to parse the headers.
+        if let Some(std) = cpp_standard {
+            if msvc {
+                builder.flag(std.msvc_arg());
+            } else {
+                builder.flag(std.clang_arg());
+            }
+        }
+        // MSVC has no equivalent of a sysroot.
+        match &sysroot {
+            Some(sysroot) if !msvc => {
+                builder.flag(format!("--sysroot={}", sysroot.to_string_lossy()).as_str());
+            }
+            _ => {}
+        }
+        builder.includes(&compile_command_flags.include_dirs);
+        for define in &compile_command_flags.defines {
+            match define.split_once('=') {
+                Some((name, value)) => builder.define(name, value),
+                None => builder.define(define, None),
+            };
+        }
+        if let Some(frozen) = self.frozen_bindings.map(FrozenBindings::new) {
+            let rs_file = match &rs_files[..] {
+                [rs_file] => rs_file,
+                _ => {
+                    return Err(BuilderError::FrozenBindings(
+                        "frozen bindings require exactly one .rs file".to_string(),
+                    ))
+                }
+            };
+            let fingerprint = frozen.read_fingerprint().and_then(|fingerprint| {
+                // Bindings frozen without the target arguments we add when
+                // cross-compiling, e.g. on the host, are good enough too.
+                fingerprint
+                    .check(rs_file, clang_args, &autocxx_inc)
+                    .or_else(|err| {
+                        let host_clang_args = make_clang_args(false);
+                        if host_clang_args.len() == clang_args.len() {
+                            return Err(err);
+                        }
+                        fingerprint
+                            .check(rs_file, &host_clang_args, &autocxx_inc)
+                            .map_err(|_| err)
+                    })?;
+                Ok(fingerprint)
+            });
+            match fingerprint {
+                Ok(fingerprint) => {
+                    if let Some(dep_recorder) = CTX::get_dependency_recorder() {
+                        for header in fingerprint.header_paths(&autocxx_inc) {
+                            dep_recorder.record_header_file_dependency(&header.to_string_lossy());
+                        }
+                    }
+                    let parsed_file = crate::parse_file(rs_file, self.auto_allowlist)
+                        .map_err(BuilderError::ParseError)?;
+                    builder.includes(parsed_file.include_dirs());
+                    let mut generated_cpp = copy_frozen_files(&frozen.include_dir(), &incdir)?;
+                    for gen_cxx_path in copy_frozen_files(&frozen.cxx_dir(), &cxxdir)? {
+                        builder.file(&gen_cxx_path);
+                        generated_cpp.push(gen_cxx_path);
+                    }
+                    let generated_rs = copy_frozen_files(&frozen.rs_dir(), &rsdir)?;
+                    for rs_file in &generated_rs {
+                        DirectiveError::remove(rs_file);
+                    }
+                    return Ok(BuilderSuccess(builder, generated_rs, generated_cpp));
+                }
+                Err(reason) if !self.regenerate_stale_frozen_bindings => {
+                    return Err(BuilderError::FrozenBindings(reason))
+                }
+                Err(_) => {} // generate afresh below
+            }
+        }
+        let cache = self
+            .cache_bindings
+            .then(|| BindingsCache::new(gen_location_strategy.get_cache_dir()));
         let mut generated_rs = Vec::new();
         let mut generated_cpp = Vec::new();
-        builder.includes(parsed_file.include_dirs());
-        for include_cpp in parsed_file.get_cpp_buildables() {
-            let generated_code = include_cpp
-                .generate_h_and_cxx(&self.cpp_codegen_options)
-                .map_err(BuilderError::InvalidCxx)?;
-            for filepair in generated_code.0 {
+        let mut mods_found = HashSet::new();
+        let mut report = Vec::new();
+        for (file_idx, rs_file) in rs_files.into_iter().enumerate() {
+            let mut parsed_file = crate::parse_file(rs_file, self.auto_allowlist)
+                .map_err(BuilderError::ParseError)?;
+            if self.strict {
+                parsed_file.set_strict();
+            }
+            for include_cpp in parsed_file.get_rs_buildables() {
+                // The generated .rs file is named after the mod, so mod
+                // names must be unique across all the files.
+                if !mods_found.insert(include_cpp.config.get_mod_name()) {
+                    return Err(BuilderError::ParseError(ParseError::ConflictingModNames));
+                }
+            }
+            let mut cpp_codegen_options = self.cpp_codegen_options.clone();
+            let cxxgen_h_dir = if separate_cxxgen_h {
+                let subdir = format!("file{}", file_idx);
+                cpp_codegen_options.path_to_cxxgen_h = Some(format!(
+                    "{}{}/",
+                    cpp_codegen_options.path_to_cxxgen_h.unwrap_or_default(),
+                    subdir
+                ));
+                let cxxgen_h_dir = incdir.join(subdir);
+                ensure_created(&cxxgen_h_dir)?;
+                cxxgen_h_dir
+            } else {
+                incdir.clone()
+            };
+            builder.includes(parsed_file.include_dirs());
+            let files = generate_files::<CTX>(
+                &mut parsed_file,
+                &autocxx_inc,
+                clang_args,
+                &cpp_codegen_options,
+                cache.as_ref(),
+            );
+            record_directive_errors(&parsed_file, &rsdir, &files);
+            let files = files?;
+            for implementation in &files.cxx {
                 let fname = format!("gen{}.cxx", counter);
                 counter += 1;
-                if let Some(implementation) = &filepair.implementation {
-                    let gen_cxx_path = write_to_file(&cxxdir, &fname, implementation)?;
-                    builder.file(&gen_cxx_path);
-                    generated_cpp.push(gen_cxx_path);
-                }
-                write_to_file(&incdir, &filepair.header_name, &filepair.header)?;
-                generated_cpp.push(incdir.join(filepair.header_name));
+                let gen_cxx_path = write_to_file(&cxxdir, &fname, implementation)?;
+                builder.file(&gen_cxx_path);
+                generated_cpp.push(gen_cxx_path);
             }
+            for (header_name, header) in &files.headers {
+                let dir = if header_name == CXXGEN_H {
+                    &cxxgen_h_dir
+                } else {
+                    &incdir
+                };
+                generated_cpp.push(write_to_file(dir, header_name, header)?);
+            }
+            for (rs_filename, rs) in &files.rs {
+                generated_rs.push(write_to_file(&rsdir, rs_filename, rs)?);
+            }
+            report.extend(files.report);
         }
-
-        for include_cpp in parsed_file.get_rs_buildables() {
-            let rs = include_cpp.generate_rs();
-            generated_rs.push(write_rs_to_file(
-                &rsdir,
-                &include_cpp.config.get_rs_filename(),
-                rs,
-            )?);
+        if let Some(report_path) = &self.report {
+            let report = serde_json::to_vec_pretty(&report).unwrap();
+            try_write_to_file(report_path, &report)
+                .map_err(|e| BuilderError::FileWriteFail(e, report_path.clone()))?;
         }
-        if counter == 0 {
+        if generated_cpp.is_empty() {
             Err(BuilderError::NoIncludeCxxMacrosFound)
         } else {
             Ok(BuilderSuccess(builder, generated_rs, generated_cpp))
@@ -280,11 +610,147 @@ impl<CTX: BuilderContext> Builder<CTX> {
     }
 }
 
+/// Generates all the code for one .rs file, or retrieves it from the
+/// cache if nothing has changed since it was last generated.
+fn generate_files<CTX: BuilderContext>(
+    parsed_file: &mut ParsedFile,
+    autocxx_inc: &[PathBuf],
+    clang_args: &[&str],
+    cpp_codegen_options: &CppCodegenOptions,
+    cache: Option<&BindingsCache>,
+) -> Result<GeneratedFiles, BuilderError> {
+    let cache_key =
+        cache.and_then(|_| parsed_file.cache_key(autocxx_inc, clang_args, cpp_codegen_options));
+    if let (Some(cache), Some(cache_key)) = (cache, cache_key) {
+        if let Some(files) = cache.get(cache_key) {
+            if let Some(dep_recorder) = CTX::get_dependency_recorder() {
+                for dependency in &files.dependencies {
+                    dep_recorder.record_header_file_dependency(dependency);
+                }
+            }
+            return Ok(files);
+        }
+    }
+    let recorded = Arc::new(Mutex::new(Vec::new()));
+    let dep_recorder = match cache_key {
+        None => CTX::get_dependency_recorder(),
+        Some(_) => Some(Box::new(CachingDependencyRecorder {
+            inner: CTX::get_dependency_recorder(),
+            recorded: recorded.clone(),
+        }) as Box<dyn RebuildDependencyRecorder>),
+    };
+    parsed_file
+        .resolve_all(
+            autocxx_inc.to_vec(),
+            clang_args,
+            dep_recorder,
+            cpp_codegen_options,
+        )
+        .map_err(BuilderError::ParseError)?;
+    let mut files = GeneratedFiles::default();
+    for include_cpp in parsed_file.get_cpp_buildables() {
+        let generated_code = include_cpp
+            .generate_h_and_cxx(cpp_codegen_options)
+            .map_err(BuilderError::InvalidCxx)?;
+        for filepair in generated_code.0 {
+            files.cxx.extend(filepair.implementation);
+            files.headers.push((filepair.header_name, filepair.header));
+        }
+    }
+    files.report = parsed_file.report();
+    for include_cpp in parsed_file.get_rs_buildables() {
+        files.rs.push((
+            include_cpp.get_rs_filename(),
+            include_cpp.generate_rs().to_string().into_bytes(),
+        ));
+    }
+    if let (Some(cache), Some(cache_key)) = (cache, cache_key) {
+        files.dependencies = recorded.lock().unwrap().clone();
+        // Failure to cache isn't fatal: we'll just generate again next time.
+        let _ = cache.put(cache_key, &files);
+    }
+    Ok(files)
+}
+
+/// Records any error concerning a specific directive alongside the
+/// generated Rust, such that the `include_cpp!` macro can report it at
+/// that directive, or removes any such record left over from previously.
+fn record_directive_errors(
+    parsed_file: &ParsedFile,
+    rsdir: &Path,
+    result: &Result<GeneratedFiles, BuilderError>,
+) {
+    let err = match result {
+        Err(BuilderError::ParseError(ParseError::AutocxxCodegenError(err))) => Some(err),
+        _ => None,
+    };
+    let directive = err.and_then(|err| err.directive());
+    for include_cpp in parsed_file.get_rs_buildables() {
+        let rs_file = rsdir.join(include_cpp.get_rs_filename());
+        match (err, &directive) {
+            (Some(err), Some(directive))
+                if include_cpp.config.get_directive_span(directive).is_some() =>
+            {
+                let directive_error = DirectiveError {
+                    item: directive.clone(),
+                    message: err.to_string(),
+                };
+                // Not fatal: the build will fail anyway, only less helpfully.
+                let _ = directive_error.write(&rs_file);
+            }
+            _ => DirectiveError::remove(&rs_file),
+        }
+    }
+}
+
+/// Cargo's `TARGET`, if it differs from the host.
+fn cross_compilation_target() -> Option<String> {
+    match (std::env::var("TARGET"), std::env::var("HOST")) {
+        (Ok(target), Ok(host)) if target != host => Some(target),
+        _ => None,
+    }
+}
+
+/// The sysroot for the given target, from `SYSROOT_<target>` (with `_`
+/// in place of `-`) or else `SYSROOT`, as looked up by `var`.
+fn sysroot_from_vars(target: &str, var: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    var(&format!("SYSROOT_{}", target.replace('-', "_")))
+        .or_else(|| var("SYSROOT"))
+        .map(PathBuf::from)
+}
+
 fn ensure_created(dir: &Path) -> Result<(), BuilderError> {
     std::fs::create_dir_all(dir)
         .map_err(|e| BuilderError::UnableToCreateDirectory(e, dir.to_path_buf()))
 }
 
+/// Copies each file in `from` into `to`, returning the new paths.
+fn copy_frozen_files(from: &Path, to: &Path) -> Result<Vec<PathBuf>, BuilderError> {
+    let mut entries = std::fs::read_dir(from)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| {
+            BuilderError::FrozenBindings(format!(
+                "unable to read {}: {}",
+                from.to_string_lossy(),
+                e
+            ))
+        })?;
+    entries.sort_by_key(|entry| entry.file_name());
+    entries
+        .into_iter()
+        .map(|entry| {
+            let content = std::fs::read(entry.path()).map_err(|e| {
+                BuilderError::FrozenBindings(format!(
+                    "unable to read {}: {}",
+                    entry.path().to_string_lossy(),
+                    e
+                ))
+            })?;
+            write_to_file(to, &entry.file_name().to_string_lossy(), &content)
+        })
+        .collect()
+}
+
 fn build_autocxx_inc<I, T>(paths: I, extra_path: &Path) -> Vec<PathBuf>
 where
     I: IntoIterator<Item = T>,
@@ -304,20 +770,60 @@ fn write_to_file(dir: &Path, filename: &str, content: &[u8]) -> Result<PathBuf,
 }
 
 fn try_write_to_file(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    if matches!(std::fs::read(path), Ok(existing) if existing == content) {
+        return Ok(()); // don't change timestamp on existing file unnecessarily
+    }
     let mut f = File::create(path)?;
     f.write_all(content)
 }
 
-fn write_rs_to_file(
-    dir: &Path,
-    filename: &str,
-    content: TokenStream,
-) -> Result<PathBuf, BuilderError> {
-    write_to_file(dir, filename, content.to_string().as_bytes())
-}
-
 fn rust_version_check() {
     if !version_check::is_min_version("1.54.0").unwrap_or(false) {
         panic!("Rust 1.54 or later is required.")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::sysroot_from_vars;
+    use crate::rust_to_clang_target;
+    use std::{collections::HashMap, ffi::OsString, path::PathBuf};
+
+    #[test]
+    fn test_rust_to_clang_target() {
+        for (rust, clang) in [
+            ("x86_64-unknown-linux-gnu", "x86_64-unknown-linux-gnu"),
+            ("x86_64-pc-windows-msvc", "x86_64-pc-windows-msvc"),
+            ("aarch64-apple-darwin", "arm64-apple-darwin"),
+            ("aarch64-apple-ios", "arm64-apple-ios"),
+            ("aarch64-apple-ios-sim", "arm64-apple-ios-simulator"),
+            ("x86_64-apple-ios", "x86_64-apple-ios-simulator"),
+            ("aarch64-linux-android", "aarch64-linux-android"),
+            ("riscv64gc-unknown-linux-gnu", "riscv64-unknown-linux-gnu"),
+            ("riscv32imac-unknown-none-elf", "riscv32-unknown-none-elf"),
+            ("thumbv7em-none-eabihf", "thumbv7em-none-eabihf"),
+        ] {
+            assert_eq!(rust_to_clang_target(rust), clang);
+        }
+    }
+
+    #[test]
+    fn test_sysroot_from_vars() {
+        let vars: HashMap<&str, OsString> = [
+            ("SYSROOT_aarch64_linux_android", "/ndk/sysroot".into()),
+            ("SYSROOT", "/sysroot".into()),
+        ]
+        .into_iter()
+        .collect();
+        let var = |name: &str| vars.get(name).cloned();
+        assert_eq!(
+            sysroot_from_vars("aarch64-linux-android", var),
+            Some(PathBuf::from("/ndk/sysroot"))
+        );
+        assert_eq!(
+            sysroot_from_vars("armv7-linux-androideabi", var),
+            Some(PathBuf::from("/sysroot"))
+        );
+        assert_eq!(sysroot_from_vars("armv7-linux-androideabi", |_| None), None);
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright 2021 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An on-disk cache of the code generated for each .rs file, keyed by
+//! [`crate::ParsedFile::cache_key`], such that the `Builder` can skip
+//! bindgen and conversion entirely if nothing has changed.
+
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::{FrozenBindings, RebuildDependencyRecorder};
+
+/// Marks an entry as complete, and lists the headers on which it depends.
+const DEPENDENCIES_FILE: &str = "dependencies.txt";
+const REPORT_FILE: &str = "report.json";
+
+/// How many entries are kept. Each .rs file needs its own entry, and
+/// each build configuration (e.g. debug and release) for it may need
+/// another, so this allows for a few of those before the least recently
+/// used entries are evicted.
+const MAX_ENTRIES: usize = 32;
+
+/// All the code generated from one .rs file.
+#[derive(Default)]
+pub(crate) struct GeneratedFiles {
+    pub(crate) rs: Vec<(String, Vec<u8>)>,
+    pub(crate) headers: Vec<(String, Vec<u8>)>,
+    pub(crate) cxx: Vec<Vec<u8>>,
+    /// Header files reported to the [`RebuildDependencyRecorder`]
+    /// while generating, so they can be reported again on a cache hit.
+    pub(crate) dependencies: Vec<String>,
+    /// See [`crate::ParsedFile::report`].
+    pub(crate) report: Vec<serde_json::Value>,
+}
+
+pub(crate) struct BindingsCache {
+    dir: PathBuf,
+    max_entries: usize,
+}
+
+impl BindingsCache {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            max_entries: MAX_ENTRIES,
+        }
+    }
+
+    /// Each entry has the same layout as a directory of frozen bindings.
+    fn entry(&self, key: u64) -> (PathBuf, FrozenBindings) {
+        let dir = self.dir.join(format!("{:016x}", key));
+        let entry = FrozenBindings::new(&dir);
+        (dir, entry)
+    }
+
+    pub(crate) fn get(&self, key: u64) -> Option<GeneratedFiles> {
+        let (dir, entry) = self.entry(key);
+        let dependencies =
+            String::from_utf8(std::fs::read(dir.join(DEPENDENCIES_FILE)).ok()?).ok()?;
+        let mut cxx = Vec::new();
+        while let Ok(implementation) = std::fs::read(entry.cxx_dir().join(cxx_name(cxx.len()))) {
+            cxx.push(implementation);
+        }
+        let report = serde_json::from_slice(&std::fs::read(dir.join(REPORT_FILE)).ok()?).ok()?;
+        // Rewritten to mark this entry as recently used.
+        write(&dir.join(DEPENDENCIES_FILE), dependencies.as_bytes()).ok()?;
+        Some(GeneratedFiles {
+            rs: read_dir(&entry.rs_dir()).ok()?,
+            headers: read_dir(&entry.include_dir()).ok()?,
+            cxx,
+            dependencies: dependencies.lines().map(|l| l.to_string()).collect(),
+            report,
+        })
+    }
+
+    pub(crate) fn put(&self, key: u64, files: &GeneratedFiles) -> std::io::Result<()> {
+        let (dir, entry) = self.entry(key);
+        for (subdir, contents) in [
+            (entry.rs_dir(), &files.rs),
+            (entry.include_dir(), &files.headers),
+        ] {
+            std::fs::create_dir_all(&subdir)?;
+            for (name, content) in contents {
+                write(&subdir.join(name), content)?;
+            }
+        }
+        std::fs::create_dir_all(entry.cxx_dir())?;
+        for (idx, implementation) in files.cxx.iter().enumerate() {
+            write(&entry.cxx_dir().join(cxx_name(idx)), implementation)?;
+        }
+        write(&dir.join(REPORT_FILE), &serde_json::to_vec(&files.report)?)?;
+        // Last, so that a partially written entry is never used.
+        let dependencies: String = files
+            .dependencies
+            .iter()
+            .map(|d| format!("{}\n", d))
+            .collect();
+        write(&dir.join(DEPENDENCIES_FILE), dependencies.as_bytes())?;
+        self.evict()
+    }
+
+    /// Removes all but the most recently used entries, judged by when
+    /// their dependencies file was last written.
+    fn evict(&self) -> std::io::Result<()> {
+        let mut entries: Vec<_> = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| {
+                let dir = entry.ok()?.path();
+                let last_used = std::fs::metadata(dir.join(DEPENDENCIES_FILE))
+                    .and_then(|metadata| metadata.modified())
+                    .ok();
+                Some((last_used, dir))
+            })
+            .collect();
+        // Most recent first. Incomplete entries, with no dependencies
+        // file, sort last.
+        entries.sort_by_key(|(last_used, _)| std::cmp::Reverse(*last_used));
+        for (_, dir) in entries.into_iter().skip(self.max_entries) {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
+
+fn cxx_name(idx: usize) -> String {
+    format!("gen{}.cxx", idx)
+}
+
+fn read_dir(dir: &Path) -> std::io::Result<Vec<(String, Vec<u8>)>> {
+    let mut files = std::fs::read_dir(dir)?
+        .map(|entry| {
+            let entry = entry?;
+            Ok((
+                entry.file_name().to_string_lossy().into_owned(),
+                std::fs::read(entry.path())?,
+            ))
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+    files.sort();
+    Ok(files)
+}
+
+fn write(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    File::create(path)?.write_all(content)
+}
+
+/// Passes header dependencies on to another recorder, if any, and also
+/// remembers them for storage in the cache.
+#[derive(Debug)]
+pub(crate) struct CachingDependencyRecorder {
+    pub(crate) inner: Option<Box<dyn RebuildDependencyRecorder>>,
+    pub(crate) recorded: Arc<Mutex<Vec<String>>>,
+}
+
+impl RebuildDependencyRecorder for CachingDependencyRecorder {
+    fn record_header_file_dependency(&self, filename: &str) {
+        if let Some(inner) = &self.inner {
+            inner.record_header_file_dependency(filename);
+        }
+        let mut recorded = self.recorded.lock().unwrap();
+        if !recorded.iter().any(|r| r == filename) {
+            recorded.push(filename.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BindingsCache, GeneratedFiles};
+
+    #[test]
+    fn test_cache_round_trip() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let cache = BindingsCache::new(tmp_dir.path().to_path_buf());
+        assert!(cache.get(1).is_none());
+        let files = GeneratedFiles {
+            rs: vec![("autocxx-ffi-default-gen.rs".into(), b"mod ffi {}".to_vec())],
+            headers: vec![
+                ("autocxxgen_ffi.h".into(), b"// a".to_vec()),
+                ("cxxgen.h".into(), b"// b".to_vec()),
+            ],
+            cxx: vec![b"// c".to_vec(), b"// d".to_vec()],
+            dependencies: vec!["/src/input.h".into()],
+            report: vec![serde_json::json!({"cpp_name": "A", "generated": true})],
+        };
+        cache.put(1, &files).unwrap();
+        assert!(cache.get(2).is_none());
+        let cached = cache.get(1).unwrap();
+        assert_eq!(cached.rs, files.rs);
+        assert_eq!(cached.headers, files.headers);
+        assert_eq!(cached.cxx, files.cxx);
+        assert_eq!(cached.dependencies, files.dependencies);
+        assert_eq!(cached.report, files.report);
+    }
+
+    #[test]
+    fn test_cache_eviction() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let cache = BindingsCache {
+            max_entries: 2,
+            ..BindingsCache::new(tmp_dir.path().to_path_buf())
+        };
+        // Such that each use gets a distinct modification time.
+        let pause = || std::thread::sleep(std::time::Duration::from_millis(20));
+        let files = GeneratedFiles::default();
+        cache.put(1, &files).unwrap();
+        pause();
+        cache.put(2, &files).unwrap();
+        pause();
+        assert!(cache.get(1).is_some());
+        pause();
+        cache.put(3, &files).unwrap();
+        assert!(cache.get(1).is_some());
+        assert!(cache.get(2).is_none());
+        assert!(cache.get(3).is_some());
+        assert_eq!(std::fs::read_dir(tmp_dir.path()).unwrap().count(), 2);
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright 2021 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Extraction of the flags which matter to autocxx from a
+//! `compile_commands.json` compilation database, as produced by CMake
+//! (`CMAKE_EXPORT_COMPILE_COMMANDS`) and others.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use crate::CppStandard;
+
+/// Errors which may occur reading a `compile_commands.json`.
+#[derive(Debug)]
+pub enum CompileCommandsError {
+    /// The file couldn't be read.
+    Io(std::io::Error, PathBuf),
+    /// The file wasn't valid JSON.
+    Json(serde_json::Error),
+    /// The file didn't have the expected structure.
+    Malformed(String),
+    /// There was no entry for the requested translation unit.
+    NoEntryForFile(PathBuf),
+}
+
+impl Display for CompileCommandsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompileCommandsError::Io(err, path) => write!(
+                f,
+                "Unable to read compile commands {}: {}",
+                path.to_string_lossy(),
+                err
+            )?,
+            CompileCommandsError::Json(err) => {
+                write!(f, "Compile commands weren't valid JSON: {}", err)?
+            }
+            CompileCommandsError::Malformed(msg) => {
+                write!(f, "Compile commands were malformed: {}", msg)?
+            }
+            CompileCommandsError::NoEntryForFile(path) => write!(
+                f,
+                "Compile commands had no entry for {}",
+                path.to_string_lossy()
+            )?,
+        }
+        Ok(())
+    }
+}
+
+/// The include directories, defines and language standard used to
+/// compile one translation unit, according to a `compile_commands.json`.
+#[derive(Debug, Default, PartialEq)]
+pub struct CompileCommandFlags {
+    /// Include directories (from `-I`, `-iquote` and `-isystem`), made
+    /// absolute relative to the entry's working directory.
+    pub include_dirs: Vec<PathBuf>,
+    /// Defines, either `NAME` or `NAME=VALUE`.
+    pub defines: Vec<String>,
+    /// The language standard, if one was given and we understand it.
+    pub cpp_standard: Option<CppStandard>,
+}
+
+impl CompileCommandFlags {
+    /// Reads the flags used to compile `for_file` from the given
+    /// `compile_commands.json`. `for_file` may be relative to the current
+    /// directory or absolute.
+    pub fn from_compile_commands(
+        compile_commands: &Path,
+        for_file: &Path,
+    ) -> Result<Self, CompileCommandsError> {
+        let json = std::fs::read_to_string(compile_commands)
+            .map_err(|e| CompileCommandsError::Io(e, compile_commands.to_path_buf()))?;
+        Self::from_json(&json, for_file)
+    }
+
+    fn from_json(json: &str, for_file: &Path) -> Result<Self, CompileCommandsError> {
+        let json: Value = serde_json::from_str(json).map_err(CompileCommandsError::Json)?;
+        let entries = json
+            .as_array()
+            .ok_or_else(|| CompileCommandsError::Malformed("expected an array".into()))?;
+        let for_file = normalize(for_file);
+        for entry in entries {
+            let field = |name: &str| entry.get(name).and_then(Value::as_str);
+            let directory = PathBuf::from(field("directory").ok_or_else(|| {
+                CompileCommandsError::Malformed("entry without a directory".into())
+            })?);
+            let file = field("file")
+                .ok_or_else(|| CompileCommandsError::Malformed("entry without a file".into()))?;
+            if normalize(&directory.join(file)) != for_file {
+                continue;
+            }
+            let args = match (entry.get("arguments"), field("command")) {
+                (Some(Value::Array(args)), _) => args
+                    .iter()
+                    .map(|arg| arg.as_str().map(|s| s.to_string()))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| CompileCommandsError::Malformed("non-string argument".into()))?,
+                (_, Some(command)) => shlex::split(command).ok_or_else(|| {
+                    CompileCommandsError::Malformed(format!("unable to split {}", command))
+                })?,
+                _ => {
+                    return Err(CompileCommandsError::Malformed(
+                        "entry without arguments or command".into(),
+                    ))
+                }
+            };
+            return Ok(Self::from_args(&args, &directory));
+        }
+        Err(CompileCommandsError::NoEntryForFile(for_file))
+    }
+
+    fn from_args(args: &[String], directory: &Path) -> Self {
+        let mut flags = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            // Each of these flags may be followed by its value either
+            // directly or as the next argument.
+            let mut value_of = |flag: &str| {
+                arg.strip_prefix(flag).map(|value| {
+                    if value.is_empty() {
+                        args.next().cloned().unwrap_or_default()
+                    } else {
+                        value.to_string()
+                    }
+                })
+            };
+            if let Some(dir) = value_of("-I")
+                .or_else(|| value_of("-iquote"))
+                .or_else(|| value_of("-isystem"))
+            {
+                flags.include_dirs.push(directory.join(dir));
+            } else if let Some(define) = value_of("-D") {
+                flags.defines.push(define);
+            } else if let Some(std) = arg.strip_prefix("-std=") {
+                flags.cpp_standard = parse_standard(std);
+            }
+        }
+        flags
+    }
+
+    /// The defines as clang arguments.
+    pub fn define_args(&self) -> Vec<String> {
+        self.defines
+            .iter()
+            .map(|define| format!("-D{}", define))
+            .collect()
+    }
+}
+
+/// Understands GNU variants and the pre-standardization names of
+/// each standard, too.
+fn parse_standard(std: &str) -> Option<CppStandard> {
+    let std = std.replace("gnu++", "c++");
+    let std = match std.as_str() {
+        "c++0x" => "c++11",
+        "c++1y" => "c++14",
+        "c++1z" => "c++17",
+        "c++2a" => "c++20",
+        std => std,
+    };
+    std.parse().ok()
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CompileCommandFlags;
+    use crate::CppStandard;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_compile_commands() {
+        let json = r#"[
+            {
+                "directory": "/build",
+                "command": "/usr/bin/c++ -DFOO -D BAR=2 -I../src -isystem /opt/include -std=gnu++17 -o a.o -c /src/a.cc",
+                "file": "/src/a.cc"
+            },
+            {
+                "directory": "/build",
+                "arguments": ["c++", "-Iinc", "-DBAZ", "-std=c++2a", "-c", "b.cc"],
+                "file": "b.cc"
+            }
+        ]"#;
+        assert_eq!(
+            CompileCommandFlags::from_json(json, Path::new("/src/a.cc")).unwrap(),
+            CompileCommandFlags {
+                include_dirs: vec![
+                    PathBuf::from("/build/../src"),
+                    PathBuf::from("/opt/include")
+                ],
+                defines: vec!["FOO".into(), "BAR=2".into()],
+                cpp_standard: Some(CppStandard::Cpp17),
+            }
+        );
+        let flags = CompileCommandFlags::from_json(json, Path::new("/build/b.cc")).unwrap();
+        assert_eq!(flags.include_dirs, vec![PathBuf::from("/build/inc")]);
+        assert_eq!(flags.cpp_standard, Some(CppStandard::Cpp20));
+        assert_eq!(flags.define_args(), vec!["-DBAZ"]);
+        assert!(CompileCommandFlags::from_json(json, Path::new("/src/c.cc")).is_err());
+    }
+}
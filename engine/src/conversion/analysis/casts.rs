@@ -119,6 +119,7 @@ fn create_cast(from: &QualifiedName, to: &QualifiedName, mutable: CastMutability
                 to_type: to.clone(),
                 mutable,
             }),
+            variadic: false,
         }),
         analysis: (),
     }
@@ -0,0 +1,125 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, HashSet};
+
+use syn::{ReturnType, Type};
+
+use super::{
+    fun::{FnAnalysis, FnKind, FnPhase, MethodKind, ReceiverMutability},
+    tdef::TypedefAnalysis,
+};
+use crate::{
+    conversion::api::{Api, ContainerDetails, TypedefKind},
+    types::QualifiedName,
+};
+
+/// Spot types with `const` `begin()` and `end()` methods, so that we can
+/// generate Rust iterators for them. We also need to know what they
+/// yield: either `begin()` returns a pointer, or else we use the type's
+/// `value_type` member typedef. Either way, the C++ iterator must yield
+/// references into the container.
+///
+/// We call `begin()` and `end()` from C++, not Rust, but we only look at
+/// those methods we could generate bindings for, since otherwise we don't
+/// know that they're `const`.
+pub(crate) fn mark_user_containers(mut apis: Vec<Api<FnPhase>>) -> Vec<Api<FnPhase>> {
+    let mut begins: HashMap<QualifiedName, (QualifiedName, Option<Type>)> = HashMap::new();
+    let mut ends = HashSet::new();
+    let mut clashes = HashSet::new();
+    let mut value_types = HashMap::new();
+    for api in &apis {
+        match api {
+            // If there's already a Rust method called iter, it would clash
+            // with ours.
+            Api::Function {
+                analysis:
+                    FnAnalysis {
+                        kind: FnKind::Method(self_ty, _),
+                        rust_name,
+                        ..
+                    },
+                ..
+            } if rust_name == "iter" => {
+                clashes.insert(self_ty.clone());
+            }
+            Api::Function {
+                name,
+                analysis:
+                    FnAnalysis {
+                        kind: FnKind::Method(self_ty, MethodKind::Normal(ReceiverMutability::Const)),
+                        params,
+                        ret_type,
+                        ..
+                    },
+                ..
+            } if params.len() == 1 => match name.cpp_name().as_str() {
+                "begin" => {
+                    begins.insert(self_ty.clone(), (name.name.clone(), pointee(ret_type)));
+                }
+                "end" => {
+                    ends.insert(self_ty.clone());
+                }
+                _ => {}
+            },
+            Api::Typedef {
+                name,
+                analysis:
+                    TypedefAnalysis {
+                        kind: TypedefKind::Type(ity),
+                        ..
+                    },
+                ..
+            } => {
+                // Keyed by namespace and C++ name, since the container
+                // may itself be nested.
+                if let Some(container) = name.cpp_name().strip_suffix("::value_type") {
+                    value_types.insert(
+                        (name.name.get_namespace().clone(), container.to_string()),
+                        (name.name.clone(), (*ity.ty).clone()),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    for api in apis.iter_mut() {
+        if let Api::Struct { name, analysis, .. } = api {
+            if !ends.contains(&name.name) || clashes.contains(&name.name) {
+                continue;
+            }
+            let value_type = value_types.get(&(name.name.get_namespace().clone(), name.cpp_name()));
+            let (element, source) = match (begins.get(&name.name), value_type) {
+                (Some((begin, Some(pointee))), _) => (pointee.clone(), begin.clone()),
+                (Some(_), Some((value_type, element))) => (element.clone(), value_type.clone()),
+                _ => continue,
+            };
+            if matches!(element, Type::Path(_)) {
+                analysis.container = Some(Box::new(ContainerDetails::Range { element, source }));
+            }
+        }
+    }
+    apis
+}
+
+/// If this returns a pointer, what it points to.
+fn pointee(ret_type: &ReturnType) -> Option<Type> {
+    match ret_type {
+        ReturnType::Type(_, ty) => match ty.as_ref() {
+            Type::Ptr(ptr) => Some((*ptr.elem).clone()),
+            _ => None,
+        },
+        ReturnType::Default => None,
+    }
+}
@@ -16,6 +16,7 @@ use crate::{
     conversion::api::SubclassName,
     types::{Namespace, QualifiedName},
 };
+use quote::ToTokens;
 use syn::{parse_quote, Ident, Type};
 
 #[derive(Clone, Debug)]
@@ -23,6 +24,13 @@ pub(crate) enum CppConversionType {
     None,
     FromUniquePtrToValue,
     FromValueToUniquePtr,
+    FromBoxedClosureToStdFunction(QualifiedName),
+    /// cxx passes references to `std::array` where the C++ API
+    /// wants references to C arrays.
+    FromStdArrayToCArray,
+    FromCArrayToStdArray,
+    /// A `rust::Str` passed to C++ as a NUL-terminated `const char*`.
+    FromRustStrToCString,
 }
 
 impl CppConversionType {
@@ -31,15 +39,69 @@ impl CppConversionType {
             CppConversionType::None => CppConversionType::None,
             CppConversionType::FromUniquePtrToValue => CppConversionType::FromValueToUniquePtr,
             CppConversionType::FromValueToUniquePtr => CppConversionType::FromUniquePtrToValue,
+            CppConversionType::FromStdArrayToCArray => CppConversionType::FromCArrayToStdArray,
+            CppConversionType::FromCArrayToStdArray => CppConversionType::FromStdArrayToCArray,
+            CppConversionType::FromBoxedClosureToStdFunction(_) => {
+                panic!("Did not expect to convert a std::function back into a Rust closure")
+            }
+            CppConversionType::FromRustStrToCString => {
+                panic!("Did not expect to convert a C string back into a rust::Str")
+            }
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub(crate) enum RustConversionType {
     None,
     FromStr,
     ToBoxedUpHolder(SubclassName),
+    ToBoxedClosure(QualifiedName, Box<Type>),
+    /// A `&CStr` passed to C++ as its `const char*`.
+    FromCStr,
+    /// A `const char*` returned from C++, presented as a `&CStr` or a
+    /// `CString` according to its [`CStrLifetime`] (or an `Option` of
+    /// either if it may be null).
+    ToCStr {
+        nullable: bool,
+        lifetime: CStrLifetime,
+    },
+}
+
+/// How long a `const char*` returned from C++ lives, so far as Rust knows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CStrLifetime {
+    /// It may point into the receiver of a method, so is a `&CStr`
+    /// borrowing from it.
+    Receiver,
+    /// The user promises it lives forever, so is a `&'static CStr`.
+    Static,
+    /// We can't tell, so it's copied into a `CString`.
+    Copied,
+}
+
+// Written by hand because syn types only implement Debug with the
+// extra-traits feature, which we don't enable.
+impl std::fmt::Debug for RustConversionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RustConversionType::None => write!(f, "None"),
+            RustConversionType::FromStr => write!(f, "FromStr"),
+            RustConversionType::ToBoxedUpHolder(sub) => write!(f, "ToBoxedUpHolder({:?})", sub),
+            RustConversionType::ToBoxedClosure(closure, dyn_fn) => write!(
+                f,
+                "ToBoxedClosure({}, {})",
+                closure,
+                dyn_fn.to_token_stream()
+            ),
+            RustConversionType::FromCStr => write!(f, "FromCStr"),
+            RustConversionType::ToCStr { nullable, lifetime } => write!(
+                f,
+                "ToCStr {{ nullable: {}, lifetime: {:?} }}",
+                nullable, lifetime
+            ),
+        }
+    }
 }
 
 /// A policy for converting types. Conversion may occur on both the Rust and
@@ -101,6 +163,74 @@ impl TypeConversionPolicy {
         }
     }
 
+    /// A reference to a C array, which cxx represents as a `std::array`.
+    pub(crate) fn new_from_std_array(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty,
+            cpp_conversion: CppConversionType::FromStdArrayToCArray,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    pub(crate) fn new_to_std_array(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty,
+            cpp_conversion: CppConversionType::FromCArrayToStdArray,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A `&str` which C++ needs as a `const char*`, e.g. because it's
+    /// being passed through the `...` of a variadic function.
+    pub(crate) fn new_from_rust_str_to_c_string(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty,
+            cpp_conversion: CppConversionType::FromRustStrToCString,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A `const char*` parameter which Rust code supplies as a `&CStr`.
+    pub(crate) fn new_from_cstr(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty,
+            cpp_conversion: CppConversionType::None,
+            rust_conversion: RustConversionType::FromCStr,
+        }
+    }
+
+    /// A `const char*` return value which Rust code receives as a `&CStr`
+    /// or `CString`.
+    pub(crate) fn new_to_cstr(ty: Type, nullable: bool, lifetime: CStrLifetime) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty,
+            cpp_conversion: CppConversionType::None,
+            rust_conversion: RustConversionType::ToCStr { nullable, lifetime },
+        }
+    }
+
+    /// A `std::function` parameter. Rust code passes a boxed closure,
+    /// which we wrap in a synthesized Rust type and pass into C++,
+    /// where a `std::function` is constructed to call back into it.
+    pub(crate) fn new_from_rust_closure(
+        ty: Type,
+        closure: QualifiedName,
+        dyn_fn: Box<Type>,
+    ) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty,
+            cpp_conversion: CppConversionType::FromBoxedClosureToStdFunction(closure.clone()),
+            rust_conversion: RustConversionType::ToBoxedClosure(closure, dyn_fn),
+        }
+    }
+
+    pub(crate) fn is_closure(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromBoxedClosureToStdFunction(_)
+        )
+    }
+
     pub(crate) fn cpp_work_needed(&self) -> bool {
         !matches!(self.cpp_conversion, CppConversionType::None)
     }
@@ -169,5 +299,8 @@ pub(crate) struct CppFunction {
     pub(crate) argument_conversion: Vec<TypeConversionPolicy>,
     pub(crate) kind: CppFunctionKind,
     pub(crate) pass_obs_field: bool,
+    /// Whether to hold the subclass's `obs_mutex` while calling into Rust,
+    /// for thread-safe subclasses.
+    pub(crate) lock_obs_field: bool,
     pub(crate) qualification: Option<QualifiedName>,
 }
@@ -25,8 +25,8 @@ use crate::{
             type_converter::{self, add_analysis, TypeConversionContext, TypeConverter},
         },
         api::{
-            ApiName, CastMutability, CppVisibility, FuncToConvert, References, SubclassName,
-            Synthesis, Virtualness,
+            ApiName, CastMutability, ContainerDetails, CppVisibility, FuncToConvert, References,
+            SubclassName, Synthesis, Virtualness,
         },
         convert_error::ConvertErrorWithContext,
         convert_error::ErrorContext,
@@ -37,8 +37,12 @@ use crate::{
 };
 use std::collections::{HashMap, HashSet};
 
-use autocxx_parser::{IncludeCppConfig, UnsafePolicy};
-use function_wrapper::{CppFunction, CppFunctionBody, TypeConversionPolicy};
+use autocxx_parser::{
+    CStrParamPolicy, CStrPolicy, CStrReturnPolicy, IncludeCppConfig, UnsafePolicy,
+};
+use function_wrapper::{
+    CStrLifetime, CppFunction, CppFunctionBody, RustConversionType, TypeConversionPolicy,
+};
 use itertools::Itertools;
 use proc_macro2::{Span, TokenStream};
 use quote::{quote, ToTokens};
@@ -59,7 +63,10 @@ use self::{
     bridge_name_tracker::BridgeNameTracker,
     overload_tracker::OverloadTracker,
     rust_name_tracker::RustNameTracker,
-    subclass::{create_subclass_constructor, create_subclass_fn_wrapper, create_subclass_function},
+    subclass::{
+        create_subclass_constructor, create_subclass_fn_wrapper, create_subclass_function,
+        create_subclass_protected_accessor,
+    },
 };
 
 use super::{
@@ -119,6 +126,9 @@ pub(crate) enum RustRenameStrategy {
 pub(crate) enum UnsafetyNeeded {
     None,
     JustReceiver,
+    /// The cxx::bridge function deals in raw pointers, but the Rust
+    /// wrapper function makes that safe.
+    JustBridge,
     Always,
 }
 
@@ -178,6 +188,7 @@ pub(crate) struct FnAnalyzer<'a> {
     config: &'a IncludeCppConfig,
     overload_trackers_by_mod: HashMap<Namespace, OverloadTracker>,
     subclasses_by_superclass: HashMap<QualifiedName, Vec<SubclassName>>,
+    subclasses_by_primary_superclass: HashMap<QualifiedName, Vec<SubclassName>>,
     has_unrepresentable_constructors: HashSet<QualifiedName>,
     nested_type_name_map: HashMap<QualifiedName, String>,
 }
@@ -197,7 +208,8 @@ impl<'a> FnAnalyzer<'a> {
             config,
             overload_trackers_by_mod: HashMap::new(),
             pod_safe_types: Self::build_pod_safe_type_set(&apis),
-            subclasses_by_superclass: subclass::subclasses_by_superclass(&apis),
+            subclasses_by_superclass: subclass::subclasses_by_superclass(&apis, false),
+            subclasses_by_primary_superclass: subclass::subclasses_by_superclass(&apis, true),
             has_unrepresentable_constructors: HashSet::new(),
             nested_type_name_map: Self::build_nested_type_map(&apis),
         };
@@ -292,6 +304,29 @@ impl<'a> FnAnalyzer<'a> {
         self.rust_name_tracker.ok_to_use_rust_name(rust_name)
     }
 
+    /// Find any `c_str!` directive for this function. Free functions are
+    /// identified by their namespaced name, methods by `Class::method`.
+    fn get_c_str_policy(
+        &self,
+        kind: &FnKind,
+        ns: &Namespace,
+        cpp_name: &str,
+    ) -> Option<&'a CStrPolicy> {
+        let qualified_name = match kind {
+            FnKind::Function => ns
+                .iter()
+                .chain(std::iter::once(&cpp_name.to_string()))
+                .join("::"),
+            // Subclasses need to see virtual functions exactly as C++
+            // declares them.
+            FnKind::Method(_, MethodKind::Virtual(_))
+            | FnKind::Method(_, MethodKind::PureVirtual(_))
+            | FnKind::TraitMethod { .. } => return None,
+            FnKind::Method(self_ty, _) => format!("{}::{}", self_ty.to_cpp_name(), cpp_name),
+        };
+        self.config.get_c_str_policy(&qualified_name)
+    }
+
     fn is_on_allowlist(&self, type_name: &QualifiedName) -> bool {
         self.config.is_on_allowlist(&type_name.to_cpp_name())
     }
@@ -306,6 +341,11 @@ impl<'a> FnAnalyzer<'a> {
             .any(|pd| pd.self_type.is_none() && pd.requires_unsafe)
         {
             UnsafetyNeeded::Always
+        } else if param_details
+            .iter()
+            .any(|pd| matches!(pd.conversion.rust_conversion, RustConversionType::FromCStr))
+        {
+            UnsafetyNeeded::JustBridge
         } else if param_details.iter().any(|pd| pd.requires_unsafe) {
             UnsafetyNeeded::JustReceiver
         } else {
@@ -320,6 +360,9 @@ impl<'a> FnAnalyzer<'a> {
         name: ApiName,
         fun: Box<FuncToConvert>,
     ) -> Result<Box<dyn Iterator<Item = Api<FnPhase>>>, ConvertErrorWithContext> {
+        if fun.variadic {
+            return self.instantiate_variadic(name, &fun);
+        }
         let initial_name = name.clone();
         let maybe_analysis_and_name = self.analyze_foreign_fn(name, &fun)?;
 
@@ -337,7 +380,23 @@ impl<'a> FnAnalyzer<'a> {
                 let make_unique_func = self.create_make_unique(&fun);
                 self.analyze_and_add_if_necessary(initial_name, make_unique_func, &mut results)?;
 
-                for sub in self.subclasses_by_superclass(sup) {
+                // We can't yet pass a std::function through to a superclass
+                // constructor, so subclasses just won't get this constructor.
+                let subclasses = if analysis
+                    .param_details
+                    .iter()
+                    .any(|pd| pd.conversion.is_closure())
+                {
+                    Vec::new()
+                } else {
+                    // Subclasses inherit constructors only from their
+                    // first superclass.
+                    self.subclasses_by_primary_superclass
+                        .get(sup)
+                        .cloned()
+                        .unwrap_or_default()
+                };
+                for sub in subclasses {
                     // Create a subclass constructor. This is a synthesized function
                     // which didn't exist in the original C++.
                     let (subclass_constructor_func, subclass_constructor_name) =
@@ -361,7 +420,24 @@ impl<'a> FnAnalyzer<'a> {
                 MethodKind::Virtual(receiver_mutability)
                 | MethodKind::PureVirtual(receiver_mutability),
             ) => {
-                for sub in self.subclasses_by_superclass(sup) {
+                let mut subclasses = self.subclasses_by_superclass(sup).peekable();
+                if subclasses.peek().is_some()
+                    && analysis
+                        .param_details
+                        .iter()
+                        .any(|pd| pd.conversion.is_closure())
+                {
+                    // We'd need to turn a std::function back into something
+                    // Rust can call, which we can't yet do.
+                    return Err(ConvertErrorWithContext(
+                        ConvertError::StdFunctionInUnsupportedPosition,
+                        Some(ErrorContext::Method {
+                            self_ty: sup.get_final_ident(),
+                            method: make_ident(&analysis.rust_name),
+                        }),
+                    ));
+                }
+                for sub in subclasses {
                     // For each subclass, we need to create a plain-C++ method to call its superclass
                     // and a Rust/C++ bridge API to call _that_.
                     // What we're generating here is entirely about the subclass, so the
@@ -385,6 +461,7 @@ impl<'a> FnAnalyzer<'a> {
                         } else {
                             Some(&super_fn_name)
                         },
+                        self.config,
                     ));
 
                     if !is_pure_virtual {
@@ -394,6 +471,31 @@ impl<'a> FnAnalyzer<'a> {
                     }
                 }
             }
+            FnKind::Method(sup, MethodKind::Normal(receiver_mutability))
+                if fun.cpp_vis == CppVisibility::Protected
+                    && !analysis
+                        .param_details
+                        .iter()
+                        .any(|pd| pd.conversion.is_closure()) =>
+            {
+                // Protected helpers can't be called from Rust directly, but
+                // a subclass's C++ peer can expose them publicly.
+                let super_fn_name =
+                    SubclassName::get_super_fn_name(&Namespace::new(), &analysis.rust_name);
+                for sub in self.subclasses_by_superclass(sup) {
+                    let accessor = create_subclass_protected_accessor(
+                        sub,
+                        &analysis,
+                        &name,
+                        receiver_mutability,
+                        sup,
+                        &super_fn_name,
+                        &fun,
+                    );
+                    let super_fn_name = ApiName::new_from_qualified_name(super_fn_name.clone());
+                    self.analyze_and_add_if_necessary(super_fn_name, accessor, &mut results)?;
+                }
+            }
             _ => {}
         }
 
@@ -425,6 +527,56 @@ impl<'a> FnAnalyzer<'a> {
         Ok(())
     }
 
+    /// cxx can't call a C variadic function, but we can generate C++
+    /// wrappers with fixed parameter lists for each instantiation which
+    /// the user requested using `variadic!`. These are then named just
+    /// like overloads.
+    fn instantiate_variadic(
+        &mut self,
+        name: ApiName,
+        fun: &FuncToConvert,
+    ) -> Result<Box<dyn Iterator<Item = Api<FnPhase>>>, ConvertErrorWithContext> {
+        let ns = name.name.get_namespace();
+        let cpp_name = name.cpp_name();
+        let qualified_cpp_name = ns.iter().chain(std::iter::once(&cpp_name)).join("::");
+        let config = self.config;
+        let mut instantiations = config
+            .get_variadic_instantiations(&qualified_cpp_name)
+            .peekable();
+        if instantiations.peek().is_none() {
+            return Err(ConvertErrorWithContext(
+                ConvertError::Variadic,
+                Some(ErrorContext::Item(name.name.get_final_ident())),
+            ));
+        }
+        let num_fixed_params = fun.inputs.len();
+        let instantiation_name =
+            ApiName::new_with_cpp_name(ns, name.name.get_final_ident(), Some(cpp_name.clone()));
+        let mut results = Vec::new();
+        for instantiation in instantiations {
+            let mut new_fun = fun.clone();
+            new_fun.variadic = false;
+            new_fun.synthesis = Some(Synthesis::VariadicInstantiation { num_fixed_params });
+            for (counter, ty) in instantiation.extra_params.iter().enumerate() {
+                let arg_name = make_ident(format!("autocxx_vararg{}", counter));
+                // Refer to &str in the way bindgen would, so that the type
+                // converter recognizes it.
+                let ty: Type = if is_rust_str(ty) {
+                    parse_quote! { rust::Str }
+                } else {
+                    ty.clone()
+                };
+                new_fun.inputs.push(parse_quote! { #arg_name: #ty });
+            }
+            self.analyze_and_add_if_necessary(
+                instantiation_name.clone(),
+                Box::new(new_fun),
+                &mut results,
+            )?;
+        }
+        Ok(Box::new(results.into_iter()))
+    }
+
     /// Take a constructor e.g. pub fn A_A(this: *mut root::A);
     /// and synthesize a make_unique e.g. pub fn make_unique() -> cxx::UniquePtr<A>
     fn create_make_unique(&mut self, fun: &FuncToConvert) -> Box<FuncToConvert> {
@@ -485,6 +637,17 @@ impl<'a> FnAnalyzer<'a> {
             .partition(Result::is_ok);
         let (mut params, mut param_details): (Punctuated<_, Comma>, Vec<_>) =
             param_details.into_iter().map(Result::unwrap).unzip();
+        if let Some(Synthesis::VariadicInstantiation { num_fixed_params }) = fun.synthesis {
+            // A rust::Str can't usefully be passed through `...`, so C++
+            // gets a NUL-terminated copy instead.
+            for pd in param_details.iter_mut().skip(num_fixed_params) {
+                if is_rust_str(&pd.conversion.unwrapped_type) {
+                    pd.conversion = TypeConversionPolicy::new_from_rust_str_to_c_string(
+                        pd.conversion.unwrapped_type.clone(),
+                    );
+                }
+            }
+        }
 
         let params_deps: HashSet<_> = param_details
             .iter()
@@ -522,6 +685,8 @@ impl<'a> FnAnalyzer<'a> {
             }
         };
 
+        let c_str_policy_name = cpp_name.clone().unwrap_or_else(|| ideal_rust_name.clone());
+
         // Let's spend some time figuring out the kind of this function (i.e. method,
         // virtual function, etc.)
         // Part one, work out if this is a static method.
@@ -655,6 +820,25 @@ impl<'a> FnAnalyzer<'a> {
             param_details[0] = analysis0;
         }
 
+        // If the user has asked for `const char*`s to be represented as
+        // Rust strings, do that now, before we decide on safety.
+        let c_str_policy = self.get_c_str_policy(&kind, ns, &c_str_policy_name);
+        if let Some(c_str_policy) = c_str_policy {
+            for pd in param_details.iter_mut().filter(|pd| {
+                pd.self_type.is_none() && is_const_c_char_ptr(&pd.conversion.unwrapped_type)
+            }) {
+                pd.conversion = match c_str_policy.params {
+                    CStrParamPolicy::CStr => {
+                        TypeConversionPolicy::new_from_cstr(pd.conversion.unwrapped_type.clone())
+                    }
+                    CStrParamPolicy::Str => {
+                        TypeConversionPolicy::new_from_rust_str_to_c_string(parse_quote! { &str })
+                    }
+                };
+                pd.requires_unsafe = false;
+            }
+        }
+
         let requires_unsafe = self.should_be_unsafe(&param_details);
         // Skip private methods; but if we've a private constructor, keep
         // a note of it. We continue to process protected methods since,
@@ -757,7 +941,29 @@ impl<'a> FnAnalyzer<'a> {
             )));
         }
         let mut ret_type = return_analysis.rt;
-        let ret_type_conversion = return_analysis.conversion;
+        let mut ret_type_conversion = return_analysis.conversion;
+        if let Some(c_str_policy) = c_str_policy {
+            if let ReturnType::Type(_, ref ty) = ret_type {
+                if is_const_c_char_ptr(ty) {
+                    // Unless the user promises they're static, strings
+                    // returned from methods may well point into the object,
+                    // so can live no longer than it. Otherwise we can't tell
+                    // how long they'll live, so copy them.
+                    let lifetime = if c_str_policy.is_static {
+                        CStrLifetime::Static
+                    } else if matches!(kind, FnKind::Method(_, MethodKind::Normal(_))) {
+                        CStrLifetime::Receiver
+                    } else {
+                        CStrLifetime::Copied
+                    };
+                    ret_type_conversion = Some(TypeConversionPolicy::new_to_cstr(
+                        ty.as_ref().clone(),
+                        c_str_policy.returns == CStrReturnPolicy::Nullable,
+                        lifetime,
+                    ));
+                }
+            }
+        }
 
         // Do we need to convert either parameters or return type?
         let param_conversion_needed = param_details.iter().any(|b| b.conversion.cpp_work_needed());
@@ -780,10 +986,30 @@ impl<'a> FnAnalyzer<'a> {
             | FnKind::Method(_, MethodKind::Virtual(_))
             | FnKind::Method(_, MethodKind::PureVirtual(_)) => true,
             FnKind::Method(..) if cxxbridge_name != rust_name => true,
+            // Methods with Rust-side conversions need a free function
+            // in the cxx::bridge for the Rust wrapper to call.
+            FnKind::Method(..)
+                if param_details
+                    .iter()
+                    .any(|pd| pd.conversion.rust_work_needed())
+                    || matches!(&ret_type_conversion, Some(x) if x.rust_work_needed()) =>
+            {
+                true
+            }
             _ if param_conversion_needed => true,
             _ if ret_type_conversion_needed => true,
             _ if cpp_name_incompatible_with_cxx => true,
             _ if synthetic_cpp_function_contents.is_some() => true,
+            _ if matches!(fun.synthesis, Some(Synthesis::VariadicInstantiation { .. })) => true,
+            // The accessor itself is emitted into the subclass's C++ peer
+            // class alongside the wrapper.
+            _ if matches!(
+                fun.synthesis,
+                Some(Synthesis::SubclassProtectedAccessor { .. })
+            ) =>
+            {
+                true
+            }
             _ => false,
         };
 
@@ -860,6 +1086,7 @@ impl<'a> FnAnalyzer<'a> {
                 argument_conversion: param_details.iter().map(|d| d.conversion.clone()).collect(),
                 kind: cpp_function_kind,
                 pass_obs_field: false,
+                lock_obs_field: false,
                 qualification: None,
             })
         } else {
@@ -1055,8 +1282,17 @@ impl<'a> FnAnalyzer<'a> {
                     type_converter::TypeKind::SubclassHolder(holder) => Some(holder),
                     _ => None,
                 };
-                let conversion =
-                    self.argument_conversion_details(&new_ty, &subclass_holder.cloned());
+                let conversion = match annotated_type.kind {
+                    type_converter::TypeKind::RustClosure {
+                        ref closure,
+                        ref dyn_fn,
+                    } => TypeConversionPolicy::new_from_rust_closure(
+                        new_ty.as_ref().clone(),
+                        closure.clone(),
+                        dyn_fn.clone(),
+                    ),
+                    _ => self.argument_conversion_details(&new_ty, &subclass_holder.cloned()),
+                };
                 pt.pat = Box::new(new_pat.clone());
                 pt.ty = new_ty;
                 (
@@ -1109,6 +1345,9 @@ impl<'a> FnAnalyzer<'a> {
                     TypeConversionPolicy::new_from_unique_ptr(ty.clone())
                 }
             }
+            Type::Reference(r) if matches!(r.elem.as_ref(), Type::Array(_)) => {
+                TypeConversionPolicy::new_from_std_array(ty.clone())
+            }
             _ => TypeConversionPolicy::new_unconverted(ty.clone()),
         }
     }
@@ -1123,6 +1362,9 @@ impl<'a> FnAnalyzer<'a> {
                     TypeConversionPolicy::new_to_unique_ptr(ty.clone())
                 }
             }
+            Type::Reference(r) if matches!(r.elem.as_ref(), Type::Array(_)) => {
+                TypeConversionPolicy::new_to_std_array(ty.clone())
+            }
             _ => TypeConversionPolicy::new_unconverted(ty.clone()),
         }
     }
@@ -1144,6 +1386,9 @@ impl<'a> FnAnalyzer<'a> {
                 // TODO remove the below clone
                 let annotated_type =
                     self.convert_boxed_type(boxed_type.clone(), ns, references.ref_return)?;
+                if let type_converter::TypeKind::RustClosure { .. } = annotated_type.kind {
+                    return Err(ConvertError::StdFunctionInUnsupportedPosition);
+                }
                 let boxed_type = annotated_type.ty;
                 let was_reference = matches!(boxed_type.as_ref(), Type::Reference(_));
                 let conversion = self.return_type_conversion_details(boxed_type.as_ref());
@@ -1214,6 +1459,7 @@ impl<'a> FnAnalyzer<'a> {
                         original_name: None,
                         synthesized_this_type: None,
                         synthesis: None,
+                        variadic: false,
                     }),
                 )
             });
@@ -1231,6 +1477,24 @@ impl<'a> FnAnalyzer<'a> {
     }
 }
 
+fn is_rust_str(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(r) => matches!(r.elem.as_ref(), Type::Path(p) if p.path.is_ident("str")),
+        _ => false,
+    }
+}
+
+/// Whether this is a `const char*`, as bindgen represents it.
+fn is_const_c_char_ptr(ty: &Type) -> bool {
+    match ty {
+        Type::Ptr(ptr) if ptr.const_token.is_some() => matches!(
+            ptr.elem.as_ref(),
+            Type::Path(p) if matches!(p.path.segments.last(), Some(s) if s.ident == "c_char")
+        ),
+        _ => false,
+    }
+}
+
 fn synthesic_cpp_need(fun: &FuncToConvert) -> Option<(CppFunctionBody, CppFunctionKind)> {
     match fun.synthesis {
         Some(Synthesis::Cast { .. }) => Some((CppFunctionBody::Cast, CppFunctionKind::Function)),
@@ -1253,6 +1517,13 @@ impl Api<FnPhase> {
                 }
             },
             Api::RustSubclassFn { subclass, .. } => subclass.0.name.clone(),
+            // Keep methods which we couldn't generate alongside their type,
+            // so that their error messages are shown in its documentation.
+            Api::IgnoredItem {
+                name,
+                ctx: ErrorContext::Method { self_ty, .. },
+                ..
+            } => QualifiedName::new(name.name.get_namespace(), self_ty.clone()),
             _ => self.name().clone(),
         }
     }
@@ -1274,9 +1545,17 @@ impl Api<FnPhase> {
                 ..
             } | Api::StringConstructor { .. }
                 | Api::ConcreteType { .. }
+                | Api::Struct {
+                    analysis: PodAnalysis {
+                        container: Some(..),
+                        ..
+                    },
+                    ..
+                }
                 | Api::CType { .. }
                 | Api::RustSubclassFn { .. }
                 | Api::Subclass { .. }
+                | Api::RustClosure { .. }
         )
     }
 
@@ -1300,18 +1579,25 @@ impl Api<FnPhase> {
                 ..
             } => Box::new(old_tyname.iter().chain(deps.iter()).cloned()),
             Api::Struct { analysis, name, .. } => Box::new(
-                analysis.field_deps.iter().cloned().chain(
-                    analysis
-                        .castable_bases
-                        .iter()
-                        .flat_map(|base| all_cast_names(&name.name, base)),
-                ),
+                analysis
+                    .field_deps
+                    .iter()
+                    .cloned()
+                    .chain(
+                        analysis
+                            .castable_bases
+                            .iter()
+                            .flat_map(|base| all_cast_names(&name.name, base)),
+                    )
+                    .chain(analysis.container.iter().filter_map(
+                        |container| match container.as_ref() {
+                            ContainerDetails::Range { source, .. } => Some(source.clone()),
+                            _ => None,
+                        },
+                    )),
             ),
             Api::Function { analysis, .. } => Box::new(analysis.deps.iter().cloned()),
-            Api::Subclass {
-                name: _,
-                superclass,
-            } => Box::new(std::iter::once(superclass.clone())),
+            Api::Subclass { superclasses, .. } => Box::new(superclasses.iter().cloned()),
             Api::RustSubclassFn { details, .. } => Box::new(details.dependency.iter().cloned()),
             _ => Box::new(std::iter::empty()),
         }
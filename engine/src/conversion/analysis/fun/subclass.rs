@@ -14,6 +14,8 @@
 
 use std::collections::HashMap;
 
+use autocxx_parser::IncludeCppConfig;
+
 use syn::{parse_quote, FnArg, PatType, Type, TypePtr};
 
 use crate::conversion::analysis::fun::{FnKind, MethodKind, ReceiverMutability};
@@ -33,17 +35,27 @@ use crate::{
 
 use super::{FnAnalysis, FnPhase};
 
+/// Finds the subclasses of each superclass. If `primary_only`, each
+/// subclass is listed only under its first superclass, which is the one
+/// whose constructors it inherits.
 pub(super) fn subclasses_by_superclass(
     apis: &[Api<PodPhase>],
+    primary_only: bool,
 ) -> HashMap<QualifiedName, Vec<SubclassName>> {
     let mut subclasses_per_superclass: HashMap<QualifiedName, Vec<SubclassName>> = HashMap::new();
 
     for api in apis.iter() {
-        if let Api::Subclass { name, superclass } = api {
-            subclasses_per_superclass
-                .entry(superclass.clone())
-                .or_default()
-                .push(name.clone());
+        if let Api::Subclass { name, superclasses } = api {
+            let superclasses =
+                superclasses
+                    .iter()
+                    .take(if primary_only { 1 } else { superclasses.len() });
+            for superclass in superclasses {
+                subclasses_per_superclass
+                    .entry(superclass.clone())
+                    .or_default()
+                    .push(name.clone());
+            }
         }
     }
     subclasses_per_superclass
@@ -70,9 +82,54 @@ pub(super) fn create_subclass_fn_wrapper(
         original_name: None,
         references: fun.references.clone(),
         synthesis: fun.synthesis.clone(),
+        variadic: false,
     })
 }
 
+/// Creates a method on the subclass's C++ peer which publicly exposes a
+/// protected, non-virtual method of its superclass.
+pub(super) fn create_subclass_protected_accessor(
+    sub: SubclassName,
+    analysis: &FnAnalysis,
+    name: &ApiName,
+    receiver_mutability: &ReceiverMutability,
+    superclass: &QualifiedName,
+    super_fn_name: &QualifiedName,
+    fun: &FuncToConvert,
+) -> Box<FuncToConvert> {
+    let kind = if matches!(receiver_mutability, ReceiverMutability::Mutable) {
+        CppFunctionKind::Method
+    } else {
+        CppFunctionKind::ConstMethod
+    };
+    let cpp_impl = CppFunction {
+        payload: CppFunctionBody::StaticMethodCall(
+            superclass.get_namespace().clone(),
+            superclass.get_final_ident(),
+            make_ident(name.cpp_name()),
+        ),
+        wrapper_function_name: super_fn_name.get_final_ident(),
+        original_cpp_name: name.cpp_name(),
+        return_conversion: analysis.ret_conversion.clone(),
+        argument_conversion: analysis
+            .param_details
+            .iter()
+            .skip(1)
+            .map(|p| p.conversion.clone())
+            .collect(),
+        kind,
+        pass_obs_field: false,
+        lock_obs_field: false,
+        qualification: Some(sub.cpp()),
+    };
+    let mut accessor = create_subclass_fn_wrapper(sub.clone(), super_fn_name, fun);
+    accessor.synthesis = Some(Synthesis::SubclassProtectedAccessor {
+        subclass: sub,
+        cpp_impl: Box::new(cpp_impl),
+    });
+    accessor
+}
+
 pub(super) fn create_subclass_function(
     sub: &SubclassName,
     analysis: &super::FnAnalysis,
@@ -80,7 +137,9 @@ pub(super) fn create_subclass_function(
     receiver_mutability: &ReceiverMutability,
     superclass: &QualifiedName,
     dependency: Option<&QualifiedName>,
+    config: &IncludeCppConfig,
 ) -> Api<FnPhase> {
+    let thread_safe = config.is_subclass_thread_safe(sub.0.name.get_final_item());
     let cpp = sub.cpp();
     let holder_name = sub.holder();
     let rust_call_name = make_ident(format!(
@@ -118,11 +177,14 @@ pub(super) fn create_subclass_function(
                     .collect(),
                 kind,
                 pass_obs_field: true,
+                lock_obs_field: thread_safe,
                 qualification: Some(cpp),
             },
             superclass: superclass.clone(),
             receiver_mutability: receiver_mutability.clone(),
             dependency: dependency.cloned(),
+            thread_safe,
+            error_policy: config.get_subclass_error_policy(sub.0.name.get_final_item()),
             requires_unsafe: analysis.param_details.iter().any(|pd| pd.requires_unsafe),
             is_pure_virtual: matches!(
                 analysis.kind,
@@ -160,6 +222,7 @@ pub(super) fn create_subclass_constructor(
             argument_conversion: args.collect(),
             kind: CppFunctionKind::SynthesizedConstructor,
             pass_obs_field: false,
+            lock_obs_field: false,
             qualification: Some(cpp.clone()),
             original_cpp_name: cpp.to_cpp_name(),
         };
@@ -208,6 +271,7 @@ pub(super) fn create_subclass_constructor(
         synthesized_this_type: Some(cpp.clone()),
         self_ty: Some(cpp),
         synthesis,
+        variadic: false,
     });
     let subclass_constructor_name = ApiName::new_with_cpp_name(
         &Namespace::new(),
@@ -20,12 +20,14 @@ use super::{convert_error::ConvertErrorWithContext, ConvertError};
 
 pub(crate) mod abstract_types;
 pub(crate) mod casts;
+pub(crate) mod containers;
 pub(crate) mod ctypes;
 pub(crate) mod fun;
 pub(crate) mod gc;
 mod name_check;
 pub(crate) mod pod; // hey, that rhymes
 pub(crate) mod remove_ignored;
+pub(crate) mod strict;
 pub(crate) mod tdef;
 mod type_converter;
 
@@ -17,11 +17,7 @@ use std::collections::HashMap;
 use syn::Ident;
 
 use crate::{
-    conversion::{
-        api::{Api, SubclassName},
-        error_reporter::convert_item_apis,
-        ConvertError,
-    },
+    conversion::{api::Api, error_reporter::convert_item_apis, ConvertError},
     types::{validate_ident_ok_for_cxx, QualifiedName},
 };
 
@@ -54,11 +50,14 @@ pub(crate) fn check_names(apis: Vec<Api<FnPhase>>) -> Vec<Api<FnPhase>> {
             Ok(Box::new(std::iter::once(api)))
         }
         Api::Subclass {
-            name: SubclassName(ref name),
-            ref superclass,
+            name: ref subclass,
+            ref superclasses,
         } => {
-            validate_all_segments_ok_for_cxx(name.name.segment_iter())?;
-            validate_all_segments_ok_for_cxx(superclass.segment_iter())?;
+            validate_all_segments_ok_for_cxx(subclass.0.name.segment_iter())?;
+            validate_all_segments_ok_for_cxx(subclass.cpp().segment_iter())?;
+            for superclass in superclasses {
+                validate_all_segments_ok_for_cxx(superclass.segment_iter())?;
+            }
             Ok(Box::new(std::iter::once(api)))
         }
         Api::Function { ref name, .. } => {
@@ -75,6 +74,7 @@ pub(crate) fn check_names(apis: Vec<Api<FnPhase>>) -> Vec<Api<FnPhase>> {
         | Api::RustType { .. }
         | Api::RustSubclassFn { .. }
         | Api::RustFn { .. }
+        | Api::RustClosure { .. }
         | Api::IgnoredItem { .. } => Ok(Box::new(std::iter::once(api))),
     });
 
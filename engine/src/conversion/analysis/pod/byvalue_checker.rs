@@ -21,7 +21,7 @@ use crate::{
     types::{Namespace, QualifiedName},
 };
 use autocxx_parser::IncludeCppConfig;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use syn::{ItemStruct, Type};
 
 #[derive(Clone)]
@@ -123,14 +123,11 @@ impl ByValueChecker {
                 _ => {}
             }
         }
-        let pod_requests = config
-            .get_pod_requests()
-            .iter()
-            .map(|ty| QualifiedName::new_from_cpp_name(ty))
-            .collect();
-        byvalue_checker
-            .satisfy_requests(pod_requests)
-            .map_err(ConvertError::UnsafePodType)?;
+        for pod_request in config.get_pod_requests() {
+            byvalue_checker
+                .satisfy_requests(vec![QualifiedName::new_from_cpp_name(pod_request)])
+                .map_err(|err| ConvertError::UnsafePodType(pod_request.clone(), err))?;
+        }
         Ok(byvalue_checker)
     }
 
@@ -230,14 +227,46 @@ impl ByValueChecker {
         )
     }
 
+    /// Why this type couldn't be POD, were it requested to be, if we know
+    /// of a reason. Unlike `satisfy_requests` this doesn't record anything,
+    /// so can be asked of any type.
+    pub(crate) fn pod_unsafety(&self, ty_id: &QualifiedName) -> Option<String> {
+        let mut todo = vec![ty_id.clone()];
+        let mut done = HashSet::new();
+        while let Some(ty_id) = todo.pop() {
+            if !done.insert(ty_id.clone()) {
+                continue;
+            }
+            match self.results.get(&ty_id) {
+                None => {
+                    return Some(format!(
+                        "Unable to make {} POD because we never saw a struct definition",
+                        ty_id
+                    ))
+                }
+                Some(deets) => match &deets.state {
+                    PodState::UnsafeToBePod(error_msg) => return Some(error_msg.clone()),
+                    PodState::IsPod => {}
+                    PodState::SafeToBePod => todo.extend_from_slice(&deets.dependent_structs),
+                    PodState::IsAlias(target_type) => todo.push(target_type.clone()),
+                },
+            }
+        }
+        None
+    }
+
     fn get_field_types(def: &ItemStruct) -> Vec<QualifiedName> {
         let mut results = Vec::new();
         for f in &def.fields {
-            let fty = &f.ty;
+            let mut fty = &f.ty;
+            // An array is POD-safe if its elements are.
+            while let Type::Array(arr) = fty {
+                fty = arr.elem.as_ref();
+            }
             if let Type::Path(p) = fty {
                 results.push(QualifiedName::from_type_path(p));
             }
-            // TODO handle anything else which bindgen might spit out, e.g. arrays?
+            // TODO handle anything else which bindgen might spit out
         }
         results
     }
@@ -334,4 +363,37 @@ mod tests {
         bvc.ingest_struct(&t, &Namespace::new());
         assert!(bvc.satisfy_requests(vec![t_id]).is_err());
     }
+
+    #[test]
+    fn test_with_arrays() {
+        let mut bvc = ByValueChecker::new();
+        let t: ItemStruct = parse_quote! {
+            struct Foo {
+                a: [[i32; 4usize]; 4usize],
+            }
+        };
+        bvc.ingest_struct(&t, &Namespace::new());
+        let t: ItemStruct = parse_quote! {
+            struct Bar {
+                a: [Foo; 2usize],
+            }
+        };
+        let t_id = ty_from_ident(&t.ident);
+        bvc.ingest_struct(&t, &Namespace::new());
+        bvc.satisfy_requests(vec![t_id.clone()]).unwrap();
+        assert!(bvc.is_pod(&t_id));
+    }
+
+    #[test]
+    fn test_with_cxxstring_array() {
+        let mut bvc = ByValueChecker::new();
+        let t: ItemStruct = parse_quote! {
+            struct Bar {
+                a: [CxxString; 2usize],
+            }
+        };
+        let t_id = ty_from_ident(&t.ident);
+        bvc.ingest_struct(&t, &Namespace::new());
+        assert!(bvc.satisfy_requests(vec![t_id]).is_err());
+    }
 }
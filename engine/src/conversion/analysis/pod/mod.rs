@@ -23,7 +23,10 @@ use syn::{ItemEnum, ItemStruct, Type, Visibility};
 use crate::{
     conversion::{
         analysis::type_converter::{add_analysis, TypeConversionContext, TypeConverter},
-        api::{AnalysisPhase, Api, ApiName, CppVisibility, StructDetails, TypeKind, UnanalyzedApi},
+        api::{
+            AnalysisPhase, Api, ApiName, ContainerDetails, CppVisibility, StructDetails, TypeKind,
+            UnanalyzedApi,
+        },
         convert_error::{ConvertErrorWithContext, ErrorContext},
         error_reporter::convert_apis,
         ConvertError,
@@ -42,6 +45,12 @@ pub(crate) struct PodAnalysis {
     /// abstract or not.
     pub(crate) castable_bases: HashSet<QualifiedName>,
     pub(crate) field_deps: HashSet<QualifiedName>,
+    /// If this isn't POD, why it couldn't be made so, if there's
+    /// some specific reason.
+    pub(crate) pod_unsafety: Option<String>,
+    /// Whether we can iterate over this type; filled in once we know
+    /// about its methods. See [`super::containers`].
+    pub(crate) container: Option<Box<ContainerDetails>>,
 }
 
 pub(crate) struct PodPhase;
@@ -137,6 +146,7 @@ fn analyze_struct(
     super::remove_bindgen_attrs(&mut details.item.attrs, id.clone())?;
     let bases = get_bases(&details.item);
     let mut field_deps = HashSet::new();
+    let pod_unsafety = byvalue_checker.pod_unsafety(&name.name);
     let type_kind = if byvalue_checker.is_pod(&name.name) {
         // It's POD so let's mark dependencies on things in its field
         get_struct_field_types(
@@ -166,6 +176,8 @@ fn analyze_struct(
             bases: bases.into_keys().collect(),
             castable_bases,
             field_deps,
+            pod_unsafety,
+            container: None,
         },
     })))
 }
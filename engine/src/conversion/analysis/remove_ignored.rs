@@ -12,16 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use super::fun::{FnAnalysis, FnKind, FnPhase};
 use crate::conversion::{convert_error::ErrorContext, ConvertError};
+use crate::types::make_ident;
 use crate::{conversion::api::Api, known_types};
 
 /// Remove any APIs which depend on other items which have been ignored.
 /// We also eliminate any APIs that depend on some type that we just don't
 /// know about at all. In either case, we don't simply remove the type, but instead
-/// replace it with an error marker.
+/// replace it with an error marker. For the former, the error records the chain
+/// of dependencies which led to the item which was originally ignored, and why.
 pub(crate) fn filter_apis_by_ignored_dependents(mut apis: Vec<Api<FnPhase>>) -> Vec<Api<FnPhase>> {
     let (ignored_items, valid_items): (Vec<&Api<_>>, Vec<&Api<_>>) = apis.iter().partition(|api| {
         matches!(
@@ -32,9 +34,12 @@ pub(crate) fn filter_apis_by_ignored_dependents(mut apis: Vec<Api<FnPhase>>) ->
             }
         )
     });
-    let mut ignored_items: HashSet<_> = ignored_items
+    let mut ignored_items: HashMap<_, _> = ignored_items
         .into_iter()
-        .map(|api| api.name().clone())
+        .filter_map(|api| match api {
+            Api::IgnoredItem { name, err, .. } => Some((name.name.clone(), err.clone())),
+            _ => None,
+        })
         .collect();
     let valid_types: HashSet<_> = valid_items
         .into_iter()
@@ -46,10 +51,20 @@ pub(crate) fn filter_apis_by_ignored_dependents(mut apis: Vec<Api<FnPhase>>) ->
         apis = apis
             .into_iter()
             .map(|api| {
-                if api.deps().any(|dep| ignored_items.contains(&dep)) {
+                let ignored_dep = api.deps().find(|dep| ignored_items.contains_key(dep));
+                if let Some(ignored_dep) = ignored_dep {
                     iterate_again = true;
-                    ignored_items.insert(api.name().clone());
-                    create_ignore_item(api, ConvertError::IgnoredDependent)
+                    let err = match &ignored_items[&ignored_dep] {
+                        ConvertError::IgnoredDependent(chain, reason) => {
+                            let chain = std::iter::once(ignored_dep).chain(chain.iter().cloned());
+                            ConvertError::IgnoredDependent(chain.collect(), reason.clone())
+                        }
+                        err => {
+                            ConvertError::IgnoredDependent(vec![ignored_dep], Box::new(err.clone()))
+                        }
+                    };
+                    ignored_items.insert(api.name().clone(), err.clone());
+                    create_ignore_item(api, err)
                 } else {
                     let mut missing_deps = api.deps().filter(|dep| {
                         !valid_types.contains(dep) && !known_types().is_known_type(dep)
@@ -79,12 +94,13 @@ fn create_ignore_item(api: Api<FnPhase>, err: ConvertError) -> Api<FnPhase> {
                 analysis:
                     FnAnalysis {
                         kind: FnKind::Method(self_ty, _),
+                        rust_name,
                         ..
                     },
                 ..
             } => ErrorContext::Method {
                 self_ty: self_ty.get_final_ident(),
-                method: id,
+                method: make_ident(rust_name),
             },
             _ => ErrorContext::Item(id),
         },
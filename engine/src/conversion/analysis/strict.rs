@@ -0,0 +1,50 @@
+// Copyright 2021 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use autocxx_parser::IncludeCppConfig;
+
+use super::fun::FnPhase;
+use crate::conversion::{api::Api, ConvertError};
+
+/// In strict mode, fail if any item which the user explicitly asked
+/// for, or any method thereof, has been ignored. This must run before
+/// garbage collection, which discards ignored methods.
+pub(crate) fn check_for_skipped_items(
+    apis: &[Api<FnPhase>],
+    config: &IncludeCppConfig,
+) -> Result<(), ConvertError> {
+    if !config.is_strict() {
+        return Ok(());
+    }
+    let must_generate: Vec<_> = config.must_generate_list().collect();
+    let mut skipped: Vec<_> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::IgnoredItem { err, .. } => {
+                let (cpp_name, owner) = api.ignored_item_names().unwrap();
+                let owner = owner.to_cpp_name();
+                (must_generate.contains(&owner) && !config.is_skip_allowed(&cpp_name))
+                    .then(|| (cpp_name, err.to_string()))
+            }
+            _ => None,
+        })
+        .collect();
+    if skipped.is_empty() {
+        Ok(())
+    } else {
+        skipped.sort();
+        skipped.dedup();
+        Err(ConvertError::StrictModeSkipped(skipped))
+    }
+}
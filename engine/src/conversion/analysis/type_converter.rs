@@ -14,11 +14,14 @@
 
 use crate::{
     conversion::{
-        api::{AnalysisPhase, Api, ApiName, TypedefKind, UnanalyzedApi},
+        api::{
+            AnalysisPhase, Api, ApiName, ContainerDetails, RustClosureDetails, TypedefKind,
+            UnanalyzedApi,
+        },
         codegen_cpp::type_to_cpp::type_to_cpp,
         ConvertError,
     },
-    known_types::{known_types, CxxGenericType},
+    known_types::{known_types, ContainerKind, CxxGenericType},
     types::{make_ident, Namespace, QualifiedName},
 };
 use autocxx_parser::IncludeCppConfig;
@@ -26,8 +29,8 @@ use proc_macro2::Ident;
 use quote::ToTokens;
 use std::collections::{HashMap, HashSet};
 use syn::{
-    parse_quote, punctuated::Punctuated, GenericArgument, PathArguments, PathSegment, Type,
-    TypePath, TypePtr,
+    parse_quote, punctuated::Punctuated, Expr, ExprLit, GenericArgument, Lit, LitInt,
+    PathArguments, PathSegment, ReturnType, Token, Type, TypePath, TypePtr,
 };
 
 use super::tdef::TypedefAnalysis;
@@ -39,6 +42,13 @@ pub(crate) enum TypeKind {
     SubclassHolder(Ident),
     Reference,
     MutableReference,
+    /// A `std::function`, represented to cxx as a `Box` of the given
+    /// synthesized Rust type. `dyn_fn` is the type of closure which
+    /// users pass in.
+    RustClosure {
+        closure: QualifiedName,
+        dyn_fn: Box<Type>,
+    },
 }
 
 /// Results of some type conversion, annotated with a list of every type encountered,
@@ -114,6 +124,7 @@ pub(crate) struct TypeConverter<'a> {
     types_found: HashSet<QualifiedName>,
     typedefs: HashMap<QualifiedName, Type>,
     concrete_templates: HashMap<String, QualifiedName>,
+    closures: HashMap<String, QualifiedName>,
     forward_declarations: HashSet<QualifiedName>,
     config: &'a IncludeCppConfig,
 }
@@ -127,6 +138,7 @@ impl<'a> TypeConverter<'a> {
             types_found: Self::find_types(apis),
             typedefs: Self::find_typedefs(apis),
             concrete_templates: Self::find_concrete_templates(apis),
+            closures: Self::find_closures(apis),
             forward_declarations: Self::find_incomplete_types(apis),
             config,
         }
@@ -180,6 +192,15 @@ impl<'a> TypeConverter<'a> {
             Type::Reference(mut r) => {
                 let innerty =
                     self.convert_boxed_type(r.elem, ns, &TypeConversionContext::CxxInnerType)?;
+                if let TypeKind::RustClosure { .. } = innerty.kind {
+                    // A const std::function& can be satisfied by a temporary
+                    // std::function, so treat this exactly as if it were
+                    // passed by value.
+                    if r.mutability.is_some() {
+                        return Err(ConvertError::StdFunctionInUnsupportedPosition);
+                    }
+                    return Ok(innerty.map(|ty| *ty));
+                }
                 r.elem = innerty.ty;
                 Annotated::new(
                     Type::Reference(r),
@@ -195,6 +216,9 @@ impl<'a> TypeConverter<'a> {
                 crate::known_types::ensure_pointee_is_valid(&ptr)?;
                 let innerty =
                     self.convert_boxed_type(ptr.elem, ns, &TypeConversionContext::CxxInnerType)?;
+                if let TypeKind::RustClosure { .. } = innerty.kind {
+                    return Err(ConvertError::StdFunctionInUnsupportedPosition);
+                }
                 ptr.elem = innerty.ty;
                 Annotated::new(
                     Type::Ptr(ptr),
@@ -203,6 +227,31 @@ impl<'a> TypeConverter<'a> {
                     TypeKind::Pointer,
                 )
             }
+            Type::Array(mut arr) => {
+                let innerty =
+                    self.convert_boxed_type(arr.elem, ns, &TypeConversionContext::CxxInnerType)?;
+                if let TypeKind::RustClosure { .. } = innerty.kind {
+                    return Err(ConvertError::StdFunctionInUnsupportedPosition);
+                }
+                arr.elem = innerty.ty;
+                // bindgen gives lengths such as `4usize`, but cxx wants
+                // a plain integer, and so does C++.
+                arr.len = match &arr.len {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Int(len), ..
+                    }) => {
+                        let len = LitInt::new(len.base10_digits(), len.span());
+                        parse_quote! { #len }
+                    }
+                    _ => return Err(ConvertError::UnknownType(arr.to_token_stream().to_string())),
+                };
+                Annotated::new(
+                    Type::Array(arr),
+                    innerty.types_encountered,
+                    innerty.extra_apis,
+                    TypeKind::Regular,
+                )
+            }
             _ => return Err(ConvertError::UnknownType(ty.to_token_stream().to_string())),
         };
         Ok(result)
@@ -274,6 +323,10 @@ impl<'a> TypeConverter<'a> {
             }
         };
 
+        if known_types().is_std_function(&tn) {
+            return self.convert_std_function(typ, ns, deps);
+        }
+
         // Now let's see if it's a known type.
         // (We may entirely reject some types at this point too.)
         let mut typ = match known_types().consider_substitution(&tn) {
@@ -315,7 +368,14 @@ impl<'a> TypeConverter<'a> {
                 // Oh poop. It's a generic type which cxx won't be able to handle.
                 // We'll have to come up with a concrete type in both the cxx::bridge (in Rust)
                 // and a corresponding typedef in C++.
-                let (new_tn, api) = self.get_templated_typename(&Type::Path(typ))?;
+                let container = match &last_seg.arguments {
+                    PathArguments::AngleBracketed(ab) => {
+                        let args = ab.args.clone();
+                        self.get_container_details(&tn, args, ns, &mut deps, &mut extra_apis)
+                    }
+                    _ => None,
+                };
+                let (new_tn, api) = self.get_templated_typename(&Type::Path(typ), container)?;
                 extra_apis.extend(api.into_iter());
                 deps.remove(&tn);
                 typ = new_tn.to_type_path();
@@ -348,6 +408,9 @@ impl<'a> TypeConverter<'a> {
                 GenericArgument::Type(t) => {
                     let mut innerty =
                         self.convert_type(t, ns, &TypeConversionContext::CxxInnerType)?;
+                    if let TypeKind::RustClosure { .. } = innerty.kind {
+                        return Err(ConvertError::StdFunctionInUnsupportedPosition);
+                    }
                     types_encountered.extend(innerty.types_encountered.drain());
                     extra_apis.append(&mut innerty.extra_apis);
                     GenericArgument::Type(innerty.ty)
@@ -373,6 +436,88 @@ impl<'a> TypeConverter<'a> {
         })
     }
 
+    /// A `std::function` is represented to cxx as a `Box` containing a
+    /// Rust type which we synthesize to hold a boxed closure. C++ wrapper
+    /// functions then turn that `rust::Box` into a real `std::function`
+    /// which calls back into Rust.
+    fn convert_std_function(
+        &mut self,
+        typ: TypePath,
+        ns: &Namespace,
+        mut deps: HashSet<QualifiedName>,
+    ) -> Result<Annotated<Type>, ConvertError> {
+        let tn = QualifiedName::from_type_path(&typ);
+        let bare_fn = match typ
+            .path
+            .segments
+            .into_iter()
+            .last()
+            .map(|seg| seg.arguments)
+        {
+            Some(PathArguments::AngleBracketed(ab)) => match ab.args.into_iter().next() {
+                Some(GenericArgument::Type(Type::BareFn(bare_fn))) => bare_fn,
+                _ => return Err(ConvertError::TemplatedTypeContainingNonPathArg(tn)),
+            },
+            _ => return Err(ConvertError::TemplatedTypeContainingNonPathArg(tn)),
+        };
+        if bare_fn.variadic.is_some() {
+            return Err(ConvertError::StdFunctionInUnsupportedPosition);
+        }
+        let mut extra_apis = Vec::new();
+        let mut convert_closure_type = |ty: Type| -> Result<Type, ConvertError> {
+            let mut annotated = self.convert_type(ty, ns, &TypeConversionContext::CxxInnerType)?;
+            match annotated.kind {
+                TypeKind::Regular | TypeKind::Reference => {}
+                _ => return Err(ConvertError::StdFunctionInUnsupportedPosition),
+            }
+            deps.extend(annotated.types_encountered.drain());
+            extra_apis.append(&mut annotated.extra_apis);
+            Ok(annotated.ty)
+        };
+        let params = bare_fn
+            .inputs
+            .into_iter()
+            .map(|arg| convert_closure_type(arg.ty))
+            .collect::<Result<Vec<_>, _>>()?;
+        let ret = match bare_fn.output {
+            ReturnType::Default => None,
+            ReturnType::Type(_, ty) => Some(convert_closure_type(*ty)?),
+        };
+        let details = RustClosureDetails { params, ret };
+        let dyn_fn = details.dyn_fn_type();
+        let key = dyn_fn.to_token_stream().to_string();
+        let closure = match self.closures.get(&key) {
+            Some(closure) => closure.clone(),
+            None => {
+                let api = UnanalyzedApi::RustClosure {
+                    name: ApiName::new_in_root_namespace(make_ident(format!(
+                        "AutocxxClosure{}",
+                        self.closures.len()
+                    ))),
+                    details: Box::new(details),
+                };
+                let closure = api.name().clone();
+                self.closures.insert(key, closure.clone());
+                extra_apis.push(api);
+                closure
+            }
+        };
+        deps.remove(&tn);
+        deps.insert(closure.clone());
+        let closure_path = closure.to_type_path();
+        Ok(Annotated::new(
+            Type::Path(parse_quote! {
+                std::boxed::Box < #closure_path >
+            }),
+            deps,
+            extra_apis,
+            TypeKind::RustClosure {
+                closure,
+                dyn_fn: Box::new(dyn_fn),
+            },
+        ))
+    }
+
     fn convert_ptr_to_reference(
         &mut self,
         ptr: TypePtr,
@@ -385,6 +530,10 @@ impl<'a> TypeConverter<'a> {
         // headers; it manifests as &str in Rust but on the C++ side it must
         // be a plain value. We should detect and abort.
         let mut outer = elem.map(|elem| match mutability {
+            // Arrays are Unpin so there's no need to Pin them.
+            Some(_) if matches!(elem.as_ref(), Type::Array(_)) => Type::Reference(parse_quote! {
+                &mut #elem
+            }),
             Some(_) => Type::Path(parse_quote! {
                 ::std::pin::Pin < & #mutability #elem >
             }),
@@ -400,9 +549,55 @@ impl<'a> TypeConverter<'a> {
         Ok(outer)
     }
 
+    /// Work out whether this template is a C++ container for which we
+    /// can generate iterators and accessors. If the element types
+    /// can't be represented, we still make an opaque concrete type but
+    /// without these extras.
+    fn get_container_details(
+        &mut self,
+        tn: &QualifiedName,
+        args: Punctuated<GenericArgument, Token![,]>,
+        ns: &Namespace,
+        deps: &mut HashSet<QualifiedName>,
+        extra_apis: &mut Vec<UnanalyzedApi>,
+    ) -> Option<Box<ContainerDetails>> {
+        // bindgen emits all template parameters including defaulted
+        // comparators, hashers and allocators. We only care about the first
+        // one or two.
+        let significant_args = match known_types().container_kind(tn)? {
+            ContainerKind::Values => 1,
+            ContainerKind::Map => 2,
+        };
+        let mut types = Vec::new();
+        for arg in args.into_iter().take(significant_args) {
+            let ty = match arg {
+                GenericArgument::Type(ty) => ty,
+                _ => return None,
+            };
+            let mut innerty = self
+                .convert_type(ty, ns, &TypeConversionContext::CxxInnerType)
+                .ok()?;
+            deps.extend(innerty.types_encountered.drain());
+            extra_apis.append(&mut innerty.extra_apis);
+            match (&innerty.ty, &innerty.kind) {
+                (Type::Path(_), TypeKind::Regular) => {}
+                _ => return None,
+            }
+            types.push(innerty.ty);
+        }
+        let mut types = types.into_iter();
+        let details = match (types.next(), types.next()) {
+            (Some(element), None) => ContainerDetails::Values { element },
+            (Some(key), Some(value)) => ContainerDetails::Map { key, value },
+            _ => return None,
+        };
+        Some(Box::new(details))
+    }
+
     fn get_templated_typename(
         &mut self,
         rs_definition: &Type,
+        container: Option<Box<ContainerDetails>>,
     ) -> Result<(QualifiedName, Option<UnanalyzedApi>), ConvertError> {
         let count = self.concrete_templates.len();
         // We just use this as a hash key, essentially.
@@ -420,6 +615,7 @@ impl<'a> TypeConverter<'a> {
                     ))),
                     rs_definition: Box::new(rs_definition.clone()),
                     cpp_definition: cpp_definition.clone(),
+                    container,
                 };
                 self.concrete_templates
                     .insert(cpp_definition, api.name().clone());
@@ -502,6 +698,7 @@ impl<'a> TypeConverter<'a> {
                 | Api::Enum { .. }
                 | Api::Struct { .. }
                 | Api::Subclass { .. }
+                | Api::RustClosure { .. }
                 | Api::RustType { .. } => Some(api.name()),
                 Api::StringConstructor { .. }
                 | Api::Function { .. }
@@ -543,6 +740,18 @@ impl<'a> TypeConverter<'a> {
             .collect()
     }
 
+    fn find_closures<A: AnalysisPhase>(apis: &[Api<A>]) -> HashMap<String, QualifiedName> {
+        apis.iter()
+            .filter_map(|api| match &api {
+                Api::RustClosure { details, .. } => Some((
+                    details.dyn_fn_type().to_token_stream().to_string(),
+                    api.name().clone(),
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
     fn find_incomplete_types<A: AnalysisPhase>(apis: &[Api<A>]) -> HashSet<QualifiedName> {
         apis.iter()
             .filter_map(|api| match api {
@@ -565,12 +774,15 @@ pub(crate) fn add_analysis<A: AnalysisPhase>(api: UnanalyzedApi) -> Api<A> {
             name,
             rs_definition,
             cpp_definition,
+            container,
         } => Api::ConcreteType {
             name,
             rs_definition,
             cpp_definition,
+            container,
         },
         Api::IgnoredItem { name, err, ctx } => Api::IgnoredItem { name, err, ctx },
+        Api::RustClosure { name, details } => Api::RustClosure { name, details },
         _ => panic!("Function analysis created an unexpected type of extra API"),
     }
 }
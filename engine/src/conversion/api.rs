@@ -15,11 +15,12 @@
 use std::collections::HashSet;
 
 use crate::types::{make_ident, Namespace, QualifiedName};
-use autocxx_parser::RustPath;
+use autocxx_parser::{RustPath, SubclassErrorPolicy};
+use quote::quote;
 use syn::{
-    parse::Parse, punctuated::Punctuated, token::Comma, Attribute, FnArg, Ident, ImplItem,
-    ItemConst, ItemEnum, ItemStruct, ItemType, ItemUse, LitBool, LitInt, ReturnType, Signature,
-    Type, Visibility,
+    parse::Parse, parse_quote, punctuated::Punctuated, token::Comma, Attribute, FnArg, Ident,
+    ImplItem, ItemConst, ItemEnum, ItemStruct, ItemType, ItemUse, LitBool, LitInt, ReturnType,
+    Signature, Type, Visibility,
 };
 
 use super::{
@@ -110,10 +111,22 @@ pub(crate) enum Synthesis {
         cpp_impl: Box<CppFunction>,
         is_trivial: bool,
     },
+    /// A public accessor on a subclass's C++ peer class which calls a
+    /// protected, non-virtual method of its superclass.
+    SubclassProtectedAccessor {
+        subclass: SubclassName,
+        cpp_impl: Box<CppFunction>,
+    },
     Cast {
         to_type: QualifiedName,
         mutable: CastMutability,
     },
+    /// A fixed-arity instantiation of a variadic function, as requested
+    /// by `variadic!`. Parameters after the first `num_fixed_params` are
+    /// those which are passed in place of the `...`.
+    VariadicInstantiation {
+        num_fixed_params: usize,
+    },
 }
 
 /// Information about references (as opposed to pointers) to be found
@@ -169,6 +182,9 @@ pub(crate) struct FuncToConvert {
     /// If Some, this function didn't really exist in the original
     /// C++ and instead we're synthesizing it.
     pub(crate) synthesis: Option<Synthesis>,
+    /// Whether this is a C variadic function, i.e. its parameters
+    /// end with `...`.
+    pub(crate) variadic: bool,
 }
 
 /// Layers of analysis which may be applied to decorate each API.
@@ -251,15 +267,20 @@ impl std::fmt::Debug for ApiName {
 /// This is a simple newtype wrapper which exists such that
 /// we can consistently generate the names of the various subsidiary
 /// types which are required both in C++ and Rust codegen.
+/// The subclass itself is always in the root namespace, but its
+/// C++ peer may be placed into a namespace of the user's choosing.
 #[derive(Clone, Hash, PartialEq, Eq, Debug)]
-pub(crate) struct SubclassName(pub(crate) ApiName);
+pub(crate) struct SubclassName(pub(crate) ApiName, Namespace);
 
 impl SubclassName {
-    pub(crate) fn new(id: Ident) -> Self {
-        Self(ApiName::new_in_root_namespace(id))
+    pub(crate) fn new(id: Ident, cpp_namespace: Namespace) -> Self {
+        Self(ApiName::new_in_root_namespace(id), cpp_namespace)
     }
     pub(crate) fn from_holder_name(id: &Ident) -> Self {
-        Self::new(make_ident(id.to_string().strip_suffix("Holder").unwrap()))
+        Self::new(
+            make_ident(id.to_string().strip_suffix("Holder").unwrap()),
+            Namespace::new(),
+        )
     }
     pub(crate) fn id(&self) -> Ident {
         self.0.name.get_final_ident()
@@ -271,7 +292,7 @@ impl SubclassName {
     /// Generate the name for the 'Cpp' type
     pub(crate) fn cpp(&self) -> QualifiedName {
         let id = self.with_suffix("Cpp");
-        QualifiedName::new(self.0.name.get_namespace(), id)
+        QualifiedName::new(&self.1, id)
     }
     pub(crate) fn cpp_remove_ownership(&self) -> Ident {
         self.with_suffix("Cpp_remove_ownership")
@@ -279,6 +300,21 @@ impl SubclassName {
     pub(crate) fn remove_ownership(&self) -> Ident {
         self.with_suffix("_remove_ownership")
     }
+    /// Generate the name for the function converting a `std::unique_ptr`
+    /// to the 'Cpp' type into a `std::shared_ptr`
+    pub(crate) fn cpp_into_shared(&self) -> Ident {
+        self.with_suffix("Cpp_into_shared")
+    }
+    /// Generate the name for the function converting a `std::shared_ptr`
+    /// to the 'Cpp' type into one to the given superclass
+    pub(crate) fn cpp_shared_as(&self, superclass: &QualifiedName) -> Ident {
+        self.with_suffix(&format!("Cpp_As_{}_shared", superclass.get_final_item()))
+    }
+    /// Generate the name for the Rust function which C++ calls to
+    /// construct a new instance of the subclass
+    pub(crate) fn cpp_create(&self) -> Ident {
+        self.with_suffix("Cpp_create")
+    }
     fn with_suffix(&self, suffix: &str) -> Ident {
         make_ident(format!("{}{}", self.0.name.get_final_item(), suffix))
     }
@@ -324,6 +360,7 @@ pub(crate) enum Api<T: AnalysisPhase> {
         name: ApiName,
         rs_definition: Box<Type>,
         cpp_definition: String,
+        container: Option<Box<ContainerDetails>>,
     },
     /// A simple note that we want to make a constructor for
     /// a `std::string` on the heap.
@@ -386,10 +423,17 @@ pub(crate) enum Api<T: AnalysisPhase> {
         subclass: SubclassName,
         details: Box<RustSubclassFnDetails>,
     },
-    /// A Rust subclass of a C++ class.
+    /// A Rust subclass of one or more C++ classes. Constructors are
+    /// inherited from the first superclass.
     Subclass {
         name: SubclassName,
-        superclass: QualifiedName,
+        superclasses: Vec<QualifiedName>,
+    },
+    /// A type we've manufactured to carry a boxed Rust closure
+    /// into C++, where it's presented as a `std::function`.
+    RustClosure {
+        name: ApiName,
+        details: Box<RustClosureDetails>,
     },
 }
 
@@ -401,10 +445,77 @@ pub(crate) struct RustSubclassFnDetails {
     pub(crate) superclass: QualifiedName,
     pub(crate) receiver_mutability: ReceiverMutability,
     pub(crate) dependency: Option<QualifiedName>,
+    pub(crate) thread_safe: bool,
+    pub(crate) error_policy: SubclassErrorPolicy,
     pub(crate) requires_unsafe: bool,
     pub(crate) is_pure_virtual: bool,
 }
 
+/// The signature of a `std::function` which we'll fulfil
+/// using a Rust closure.
+pub(crate) struct RustClosureDetails {
+    /// Parameter types, as they'll be represented in the [cxx::bridge].
+    pub(crate) params: Vec<Type>,
+    /// Return type, if any.
+    pub(crate) ret: Option<Type>,
+}
+
+impl RustClosureDetails {
+    /// The type of the closure which Rust code will pass in.
+    pub(crate) fn dyn_fn_type(&self) -> Type {
+        let params = &self.params;
+        let ret = self.ret.as_ref().map(|ret| quote! { -> #ret });
+        parse_quote! {
+            dyn FnMut( #(#params),* ) #ret
+        }
+    }
+
+    /// The extern "Rust" function which C++ calls to invoke the closure.
+    pub(crate) fn get_call_fn_name(closure: &QualifiedName) -> Ident {
+        make_ident(format!("{}_call", closure.get_final_item()))
+    }
+
+    /// The C++ function which converts the boxed closure into a
+    /// `std::function`.
+    pub(crate) fn get_to_std_function_name(closure: &QualifiedName) -> String {
+        format!("{}_to_std_function", closure.get_final_item())
+    }
+}
+
+/// A type which is a C++ container, and for which we therefore generate
+/// iterators and accessors.
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum ContainerDetails {
+    /// A container of single values, e.g. `std::set`.
+    Values { element: Type },
+    /// An associative container, e.g. `std::map`.
+    Map { key: Type, value: Type },
+    /// Any other type with `begin()` and `end()` methods. We can iterate
+    /// over it but know nothing else about it. `source` is the API from
+    /// which we learnt the element type.
+    Range {
+        element: Type,
+        source: QualifiedName,
+    },
+}
+
+impl ContainerDetails {
+    /// The C++ iterator wrapper type.
+    pub(crate) fn get_iter_type_name(container: &QualifiedName) -> Ident {
+        make_ident(format!("{}_Iter", container.get_final_item()))
+    }
+
+    /// The name of a C++ helper function for this container.
+    pub(crate) fn get_helper_fn_name(container: &QualifiedName, suffix: &str) -> Ident {
+        make_ident(format!("{}_{}", container.get_final_item(), suffix))
+    }
+
+    /// The Rust type which implements [Iterator] for this container.
+    pub(crate) fn get_rust_iterator_name(container: &QualifiedName) -> Ident {
+        make_ident(format!("{}Iterator", container.get_final_item()))
+    }
+}
+
 impl<T: AnalysisPhase> Api<T> {
     pub(crate) fn name_info(&self) -> &ApiName {
         match self {
@@ -422,6 +533,7 @@ impl<T: AnalysisPhase> Api<T> {
             Api::RustFn { name, .. } => name,
             Api::RustSubclassFn { name, .. } => name,
             Api::Subclass { name, .. } => &name.0,
+            Api::RustClosure { name, .. } => name,
         }
     }
 
@@ -449,6 +561,36 @@ impl<T: AnalysisPhase> Api<T> {
             .unwrap_or_else(|| self.name().get_final_item())
     }
 
+    /// For an [`Api::IgnoredItem`], the fully-qualified C++ name of whatever
+    /// was ignored, along with the item to which it belongs: its type, if
+    /// it's a method, or otherwise the item itself.
+    pub(crate) fn ignored_item_names(&self) -> Option<(String, QualifiedName)> {
+        match self {
+            Api::IgnoredItem { name, ctx, .. } => {
+                let ns = name.name.get_namespace();
+                Some(match ctx {
+                    ErrorContext::Item(_) => {
+                        let item = QualifiedName::new(ns, make_ident(self.effective_cpp_name()));
+                        (item.to_cpp_name(), item)
+                    }
+                    ErrorContext::Method { self_ty, method } => {
+                        // Methods rejected during function analysis are
+                        // named after their type; those rejected later on
+                        // account of their dependencies, after themselves.
+                        let method = if name.name.get_final_ident() == *self_ty {
+                            method.to_string()
+                        } else {
+                            self.effective_cpp_name().to_string()
+                        };
+                        let self_ty = QualifiedName::new(ns, self_ty.clone());
+                        (format!("{}::{}", self_ty.to_cpp_name(), method), self_ty)
+                    }
+                })
+            }
+            _ => None,
+        }
+    }
+
     pub(crate) fn valid_types(&self) -> Box<dyn Iterator<Item = QualifiedName>> {
         match self {
             Api::Subclass { name, .. } => Box::new(
@@ -14,11 +14,15 @@
 
 use crate::conversion::{
     analysis::fun::function_wrapper::{CppConversionType, TypeConversionPolicy},
+    api::RustClosureDetails,
     ConvertError,
 };
 use crate::known_types::type_lacks_copy_constructor;
 
-use super::type_to_cpp::{type_to_cpp, CppNameMap};
+use super::{
+    type_to_cpp::{c_array_reference_to_cpp, type_to_cpp, CppNameMap},
+    Header,
+};
 
 impl TypeConversionPolicy {
     pub(super) fn unconverted_type(
@@ -34,6 +38,7 @@ impl TypeConversionPolicy {
     pub(super) fn converted_type(&self, cpp_name_map: &CppNameMap) -> Result<String, ConvertError> {
         match self.cpp_conversion {
             CppConversionType::FromValueToUniquePtr => self.wrapped_type(cpp_name_map),
+            CppConversionType::FromRustStrToCString => Ok("rust::Str".to_string()),
             _ => self.unwrapped_type_as_string(cpp_name_map),
         }
     }
@@ -69,6 +74,39 @@ impl TypeConversionPolicy {
                 self.unconverted_type(cpp_name_map)?,
                 var_name
             ),
+            CppConversionType::FromBoxedClosureToStdFunction(ref closure) => format!(
+                "{}(std::move({}))",
+                RustClosureDetails::get_to_std_function_name(closure),
+                var_name
+            ),
+            CppConversionType::FromStdArrayToCArray => format!(
+                "reinterpret_cast<{}>({})",
+                c_array_reference_to_cpp(&self.unwrapped_type, cpp_name_map)?,
+                var_name
+            ),
+            CppConversionType::FromCArrayToStdArray => format!(
+                "reinterpret_cast<{}>({})",
+                self.unwrapped_type_as_string(cpp_name_map)?,
+                var_name
+            ),
+            // The temporary std::string lives until the end of the full
+            // expression, i.e. until the call has completed.
+            CppConversionType::FromRustStrToCString => {
+                format!("std::string({}).c_str()", var_name)
+            }
         })
     }
+
+    /// Any headers required by the conversion.
+    pub(super) fn headers(&self) -> Vec<Header> {
+        match self.cpp_conversion {
+            CppConversionType::FromStdArrayToCArray | CppConversionType::FromCArrayToStdArray => {
+                vec![Header::System("array")]
+            }
+            CppConversionType::FromRustStrToCString => {
+                vec![Header::System("string"), Header::CxxH]
+            }
+            _ => Vec::new(),
+        }
+    }
 }
@@ -28,11 +28,14 @@ use type_to_cpp::{original_name_map_from_apis, type_to_cpp, CppNameMap};
 use self::type_to_cpp::namespaced_name_using_original_name_map;
 
 use super::{
-    analysis::fun::{
-        function_wrapper::{CppFunction, CppFunctionBody},
-        FnPhase,
+    analysis::{
+        fun::{
+            function_wrapper::{CppFunction, CppFunctionBody},
+            FnPhase,
+        },
+        pod::PodAnalysis,
     },
-    api::{Api, SubclassName, Synthesis},
+    api::{Api, ContainerDetails, RustClosureDetails, SubclassName, Synthesis},
     ConvertError,
 };
 
@@ -92,6 +95,8 @@ struct AdditionalFunction {
 /// need to be built and included in linking procedures.
 pub(crate) struct CppCodeGenerator<'a> {
     additional_functions: Vec<AdditionalFunction>,
+    /// C++ peers of Rust subclasses, each of which gets its own header.
+    subclass_peers: Vec<QualifiedName>,
     inclusions: String,
     original_name_map: CppNameMap,
     config: &'a IncludeCppConfig,
@@ -100,6 +105,7 @@ pub(crate) struct CppCodeGenerator<'a> {
 
 struct SubclassFunction<'a> {
     fun: &'a CppFunction,
+    superclass: &'a QualifiedName,
     is_pure_virtual: bool,
 }
 
@@ -109,7 +115,7 @@ impl<'a> CppCodeGenerator<'a> {
         apis: &[Api<FnPhase>],
         config: &'a IncludeCppConfig,
         cpp_codegen_options: &CppCodegenOptions,
-    ) -> Result<Option<CppFilePair>, ConvertError> {
+    ) -> Result<Vec<CppFilePair>, ConvertError> {
         let mut gen = CppCodeGenerator::new(
             inclusions,
             original_name_map_from_apis(apis),
@@ -130,6 +136,7 @@ impl<'a> CppCodeGenerator<'a> {
     ) -> Self {
         CppCodeGenerator {
             additional_functions: Vec::new(),
+            subclass_peers: Vec::new(),
             inclusions,
             original_name_map,
             config,
@@ -144,6 +151,7 @@ impl<'a> CppCodeGenerator<'a> {
     ) -> Result<(), ConvertError> {
         let mut constructors_by_subclass: HashMap<SubclassName, Vec<&CppFunction>> = HashMap::new();
         let mut methods_by_subclass: HashMap<SubclassName, Vec<SubclassFunction>> = HashMap::new();
+        let mut accessors_by_subclass: HashMap<SubclassName, Vec<&CppFunction>> = HashMap::new();
         let mut deferred_apis = Vec::new();
         for api in apis {
             match &api {
@@ -158,23 +166,58 @@ impl<'a> CppCodeGenerator<'a> {
                     fun,
                     ..
                 } => {
-                    if let Some(Synthesis::SubclassConstructor {
-                        subclass, cpp_impl, ..
-                    }) = &fun.synthesis
-                    {
-                        constructors_by_subclass
+                    match &fun.synthesis {
+                        Some(Synthesis::SubclassConstructor {
+                            subclass, cpp_impl, ..
+                        }) => constructors_by_subclass
                             .entry(subclass.clone())
                             .or_default()
-                            .push(cpp_impl);
+                            .push(cpp_impl),
+                        Some(Synthesis::SubclassProtectedAccessor { subclass, cpp_impl }) => {
+                            accessors_by_subclass
+                                .entry(subclass.clone())
+                                .or_default()
+                                .push(cpp_impl)
+                        }
+                        _ => {}
                     }
                     self.generate_cpp_function(cpp_wrapper)?
                 }
-                Api::ConcreteType { rs_definition, .. } => self.generate_typedef(
-                    api.name(),
-                    type_to_cpp(rs_definition, &self.original_name_map)?,
-                ),
+                Api::ConcreteType {
+                    rs_definition,
+                    container,
+                    ..
+                } => {
+                    self.generate_typedef(
+                        api.name(),
+                        type_to_cpp(rs_definition, &self.original_name_map)?,
+                    );
+                    if let Some(container) = container {
+                        self.generate_container_accessors(
+                            api.name(),
+                            api.name().get_final_item(),
+                            container,
+                        )?;
+                    }
+                }
+                Api::Struct {
+                    name,
+                    analysis:
+                        PodAnalysis {
+                            container: Some(container),
+                            ..
+                        },
+                    ..
+                } => {
+                    let cpp_name = namespaced_name_using_original_name_map(
+                        &name.name,
+                        &self.original_name_map,
+                    );
+                    self.generate_container_accessors(&name.name, &cpp_name, container)?;
+                }
                 Api::CType { typename, .. } => self.generate_ctype_typedef(typename),
                 Api::Subclass { .. } => deferred_apis.push(api),
+                Api::RustClosure { details, .. } => self.generate_closure(api.name(), details)?,
                 Api::RustSubclassFn {
                     subclass, details, ..
                 } => {
@@ -183,6 +226,7 @@ impl<'a> CppCodeGenerator<'a> {
                         .or_default()
                         .push(SubclassFunction {
                             fun: &details.cpp_impl,
+                            superclass: &details.superclass,
                             is_pure_virtual: details.is_pure_virtual,
                         });
                 }
@@ -192,11 +236,12 @@ impl<'a> CppCodeGenerator<'a> {
 
         for api in deferred_apis.into_iter() {
             match api {
-                Api::Subclass { name, superclass } => self.generate_subclass(
-                    superclass,
+                Api::Subclass { name, superclasses } => self.generate_subclass(
+                    superclasses,
                     name,
                     constructors_by_subclass.remove(name).unwrap_or_default(),
                     methods_by_subclass.remove(name).unwrap_or_default(),
+                    accessors_by_subclass.remove(name).unwrap_or_default(),
                 )?,
                 _ => panic!("Unexpected deferred API"),
             }
@@ -204,9 +249,9 @@ impl<'a> CppCodeGenerator<'a> {
         Ok(())
     }
 
-    fn generate(&self) -> Option<CppFilePair> {
+    fn generate(&self) -> Vec<CppFilePair> {
         if self.additional_functions.is_empty() {
-            None
+            Vec::new()
         } else {
             let headers = self.collect_headers(|additional_need| &additional_need.headers);
             let cpp_headers = self.collect_headers(|additional_need| &additional_need.cpp_headers);
@@ -233,11 +278,41 @@ impl<'a> CppCodeGenerator<'a> {
             } else {
                 None
             };
-            Some(CppFilePair {
+            let subclass_headers: Vec<_> = self
+                .subclass_peers
+                .iter()
+                .map(|peer| Self::generate_subclass_header(peer, &header_name))
+                .collect();
+            std::iter::once(CppFilePair {
                 header: declarations.into_bytes(),
                 implementation,
                 header_name,
+                header_only: false,
             })
+            .chain(subclass_headers)
+            .collect()
+        }
+    }
+
+    /// Generates a header named after the C++ peer of a Rust subclass,
+    /// such that hand-written C++ can include it without knowing which
+    /// `include_cpp!` generated the subclass. Peers outside the root
+    /// namespace get headers named like `my.peers.BarCpp.h`, since dots
+    /// can't appear in C++ identifiers and so can't cause clashes.
+    fn generate_subclass_header(peer: &QualifiedName, header_name: &str) -> CppFilePair {
+        let guard = format!(
+            "__AUTOCXXGEN_{}_H__",
+            peer.segment_iter().join("__").to_uppercase()
+        );
+        CppFilePair {
+            header: format!(
+                "#ifndef {}\n#define {}\n\n#include \"{}\"\n\n#endif // {}\n",
+                guard, guard, header_name, guard
+            )
+            .into_bytes(),
+            implementation: None,
+            header_name: format!("{}.h", peer.segment_iter().join(".")),
+            header_only: true,
         }
     }
 
@@ -485,8 +560,15 @@ impl<'a> CppCodeGenerator<'a> {
             } else {
                 "".into()
             };
-        let definition_after_sig =
-            format!("{} {{ {} }}", field_assignments, underlying_function_call,);
+        let obs_lock = if details.lock_obs_field {
+            "std::lock_guard<std::recursive_mutex> obs_lock(obs_mutex); "
+        } else {
+            ""
+        };
+        let definition_after_sig = format!(
+            "{} {{ {}{} }}",
+            field_assignments, obs_lock, underlying_function_call,
+        );
         let (declaration, definition) = if requires_rust_declarations {
             (
                 Some(format!("{};", declaration)),
@@ -501,15 +583,83 @@ impl<'a> CppCodeGenerator<'a> {
                 None,
             )
         };
+        let headers = std::iter::once(Header::System("memory"))
+            .chain(
+                Some(Header::System("mutex"))
+                    .into_iter()
+                    .filter(|_| details.lock_obs_field),
+            )
+            .chain(
+                details
+                    .argument_conversion
+                    .iter()
+                    .chain(details.return_conversion.iter())
+                    .flat_map(|conversion| conversion.headers()),
+            )
+            .collect();
         Ok(AdditionalFunction {
             type_definition: None,
             declaration,
             definition,
-            headers: vec![Header::System("memory")],
+            headers,
             cpp_headers: Vec::new(),
         })
     }
 
+    /// Generates a function which turns a boxed Rust closure into a
+    /// `std::function`. `std::function` requires its target to be copyable,
+    /// so the `rust::Box` is held in a `std::shared_ptr`.
+    fn generate_closure(
+        &mut self,
+        name: &QualifiedName,
+        details: &RustClosureDetails,
+    ) -> Result<(), ConvertError> {
+        let id = name.get_final_item();
+        let params: Vec<_> = details
+            .params
+            .iter()
+            .map(|ty| type_to_cpp(ty, &self.original_name_map))
+            .try_collect()?;
+        let ret = match &details.ret {
+            Some(ret) => type_to_cpp(ret, &self.original_name_map)?,
+            None => "void".to_string(),
+        };
+        let lambda_params = params
+            .iter()
+            .enumerate()
+            .map(|(counter, ty)| format!("{} arg{}", ty, counter))
+            .join(", ");
+        let call_args = std::iter::once("**shared_closure".to_string())
+            .chain((0..params.len()).map(|counter| format!("arg{}", counter)))
+            .join(", ");
+        let declaration = format!(
+            "std::function<{}({})> {}(rust::Box<{}> closure)",
+            ret,
+            params.join(", "),
+            RustClosureDetails::get_to_std_function_name(name),
+            id
+        );
+        self.additional_functions.push(AdditionalFunction {
+            type_definition: Some(format!("struct {};", id)),
+            declaration: Some(format!("{};", declaration)),
+            definition: Some(format!(
+                "{} {{\nauto shared_closure = std::make_shared<rust::Box<{}>>(std::move(closure));\nreturn [shared_closure]({}) {{ return {}({}); }};\n}}\n",
+                declaration,
+                id,
+                lambda_params,
+                RustClosureDetails::get_call_fn_name(name),
+                call_args
+            )),
+            headers: vec![
+                Header::System("functional"),
+                Header::System("memory"),
+                Header::CxxH,
+            ],
+            cpp_headers: vec![Header::CxxgenH],
+        });
+        Ok(())
+    }
+
     fn generate_ctype_typedef(&mut self, tn: &QualifiedName) {
         let cpp_name = tn.to_cpp_name();
         self.generate_typedef(tn, cpp_name)
@@ -526,12 +676,144 @@ impl<'a> CppCodeGenerator<'a> {
         })
     }
 
+    /// Generates an iterator wrapper and accessors for a type which is a
+    /// C++ container. For standard containers we refer to element types
+    /// using the container's own member typedefs; for others we don't
+    /// assume those exist.
+    fn generate_container_accessors(
+        &mut self,
+        name: &QualifiedName,
+        cpp_name: &str,
+        container: &ContainerDetails,
+    ) -> Result<(), ConvertError> {
+        let iter_type = ContainerDetails::get_iter_type_name(name);
+        let helper = |suffix| ContainerDetails::get_helper_fn_name(name, suffix);
+        let mut functions = vec![
+            (
+                format!(
+                    "std::unique_ptr<{}> {}(const {}& container)",
+                    iter_type,
+                    helper("iter"),
+                    cpp_name
+                ),
+                format!(
+                    "return std::make_unique<{}>({}{{container.begin(), container.end()}});",
+                    iter_type, iter_type
+                ),
+            ),
+            (
+                format!("bool {}(const {}& it)", helper("Iter_is_end"), iter_type),
+                "return it.pos == it.end;".to_string(),
+            ),
+            (
+                format!("void {}({}& it)", helper("Iter_advance"), iter_type),
+                "++it.pos;".to_string(),
+            ),
+        ];
+        if !matches!(container, ContainerDetails::Range { .. }) {
+            functions.push((
+                format!("size_t {}(const {}& container)", helper("len"), cpp_name),
+                "return container.size();".to_string(),
+            ));
+        }
+        match container {
+            ContainerDetails::Values { .. } => functions.push((
+                format!(
+                    "const {}::value_type& {}(const {}& it)",
+                    cpp_name,
+                    helper("Iter_get"),
+                    iter_type
+                ),
+                "return *it.pos;".to_string(),
+            )),
+            ContainerDetails::Map { .. } => functions.extend([
+                (
+                    format!(
+                        "const {}::key_type& {}(const {}& it)",
+                        cpp_name,
+                        helper("Iter_key"),
+                        iter_type
+                    ),
+                    "return it.pos->first;".to_string(),
+                ),
+                (
+                    format!(
+                        "const {}::mapped_type& {}(const {}& it)",
+                        cpp_name,
+                        helper("Iter_value"),
+                        iter_type
+                    ),
+                    "return it.pos->second;".to_string(),
+                ),
+                (
+                    format!(
+                        "bool {}(const {}& container, const {}::key_type& key)",
+                        helper("contains"),
+                        cpp_name,
+                        cpp_name
+                    ),
+                    "return container.find(key) != container.end();".to_string(),
+                ),
+                (
+                    format!(
+                        "const {}::mapped_type& {}(const {}& container, const {}::key_type& key)",
+                        cpp_name,
+                        helper("at"),
+                        cpp_name,
+                        cpp_name
+                    ),
+                    "return container.at(key);".to_string(),
+                ),
+            ]),
+            ContainerDetails::Range { element, .. } => functions.push((
+                format!(
+                    "const {}& {}(const {}& it)",
+                    type_to_cpp(element, &self.original_name_map)?,
+                    helper("Iter_get"),
+                    iter_type
+                ),
+                "return *it.pos;".to_string(),
+            )),
+        }
+        let iterator_types = match container {
+            ContainerDetails::Range { .. } => (
+                format!("decltype(std::declval<const {}&>().begin())", cpp_name),
+                format!("decltype(std::declval<const {}&>().end())", cpp_name),
+            ),
+            _ => (
+                format!("{}::const_iterator", cpp_name),
+                format!("{}::const_iterator", cpp_name),
+            ),
+        };
+        self.additional_functions.push(AdditionalFunction {
+            type_definition: Some(format!(
+                "struct {} {{ {} pos; {} end; }};",
+                iter_type, iterator_types.0, iterator_types.1
+            )),
+            declaration: None,
+            definition: None,
+            headers: vec![Header::System("memory"), Header::System("utility")],
+            cpp_headers: Vec::new(),
+        });
+        for (declaration, body) in functions {
+            self.additional_functions.push(AdditionalFunction {
+                type_definition: None,
+                declaration: Some(format!("{};", declaration)),
+                definition: Some(format!("{} {{\n{}\n}}\n", declaration, body)),
+                headers: Vec::new(),
+                cpp_headers: Vec::new(),
+            });
+        }
+        Ok(())
+    }
+
     fn generate_subclass(
         &mut self,
-        superclass: &QualifiedName,
+        superclasses: &[QualifiedName],
         subclass: &SubclassName,
         constructors: Vec<&CppFunction>,
         methods: Vec<SubclassFunction>,
+        protected_accessors: Vec<&CppFunction>,
     ) -> Result<(), ConvertError> {
         let holder = subclass.holder();
         self.additional_functions.push(AdditionalFunction {
@@ -555,8 +837,10 @@ impl<'a> CppCodeGenerator<'a> {
             self.additional_functions.push(fn_impl);
             // And now the function to be called from Rust for default implementation (calls superclass in C++)
             if !method.is_pure_virtual {
+                let superclass = method.superclass;
                 let mut super_method = method.fun.clone();
                 super_method.pass_obs_field = false;
+                super_method.lock_obs_field = false;
                 super_method.wrapper_function_name = SubclassName::get_super_fn_name(
                     superclass.get_namespace(),
                     &method.fun.wrapper_function_name.to_string(),
@@ -578,16 +862,70 @@ impl<'a> CppCodeGenerator<'a> {
                 self.additional_functions.push(super_fn_impl);
             }
         }
-        // In future, for each superclass..
-        let super_name = superclass.get_final_item();
-        method_decls.push(format!(
-            "const {}& As_{}() const {{ return *this; }}",
-            super_name, super_name,
-        ));
-        method_decls.push(format!(
-            "{}& As_{}_mut() {{ return *this; }}",
-            super_name, super_name
-        ));
+        // Public methods which call protected, non-virtual superclass methods
+        for accessor in protected_accessors {
+            let mut fn_impl = self.generate_cpp_function_inner(
+                accessor,
+                true,
+                ConversionDirection::CppCallsCpp,
+                false,
+                None,
+            )?;
+            method_decls.push(fn_impl.declaration.take().unwrap());
+            self.additional_functions.push(fn_impl);
+        }
+        let mut shared_decls = vec![format!(
+            "inline std::shared_ptr<{}> {}(std::unique_ptr<{}> me) {{ return std::shared_ptr<{}>(std::move(me)); }}",
+            subclass.cpp(),
+            subclass.cpp_into_shared(),
+            subclass.cpp(),
+            subclass.cpp(),
+        )];
+        for superclass in superclasses {
+            let super_name = superclass.get_final_item();
+            method_decls.push(format!(
+                "const {}& As_{}() const {{ return *this; }}",
+                super_name, super_name,
+            ));
+            method_decls.push(format!(
+                "{}& As_{}_mut() {{ return *this; }}",
+                super_name, super_name
+            ));
+            // The superclass may be inaccessible outside the class, so
+            // shared_ptr's implicit conversion can't be used.
+            let super_cpp = superclass.to_cpp_name();
+            method_decls.push(format!(
+                "static std::shared_ptr<{}> As_{}_shared(const std::shared_ptr<{}>& me) {{ return std::shared_ptr<{}>(me, static_cast<{}*>(me.get())); }}",
+                super_cpp, super_name, subclass.cpp(), super_cpp, super_cpp,
+            ));
+            shared_decls.push(format!(
+                "inline std::shared_ptr<{}> {}(const std::shared_ptr<{}>& me) {{ return {}::As_{}_shared(me); }}",
+                super_cpp,
+                subclass.cpp_shared_as(superclass),
+                subclass.cpp(),
+                subclass.cpp(),
+                super_name,
+            ));
+        }
+        // C++ may construct the subclass itself, if Rust knows how to.
+        let cpp = subclass.cpp();
+        let factory_def = if self
+            .config
+            .is_subclass_cpp_factory(subclass.0.name.get_final_item())
+        {
+            method_decls.push(format!(
+                "static std::unique_ptr<{}> create();",
+                cpp.get_final_item()
+            ));
+            format!(
+                "std::unique_ptr<{}> {}::create() {{\nreturn {}();\n}}\n",
+                cpp,
+                cpp,
+                subclass.cpp_create()
+            )
+        } else {
+            String::new()
+        };
         // And now constructors
         let mut constructor_decls: Vec<String> = Vec::new();
         for constructor in constructors {
@@ -602,28 +940,61 @@ impl<'a> CppCodeGenerator<'a> {
             constructor_decls.push(decl);
             self.additional_functions.push(fn_impl);
         }
+        // Thread-safe subclasses must not swap out `obs` while another
+        // thread is calling into Rust through it. The mutex is recursive
+        // so that a Rust method may relinquish ownership.
+        let (obs_mutex_decl, obs_lock, mut headers) = if self
+            .config
+            .is_subclass_thread_safe(subclass.0.name.get_final_item())
+        {
+            (
+                "mutable std::recursive_mutex obs_mutex;\n",
+                "std::lock_guard<std::recursive_mutex> obs_lock(obs_mutex);\n",
+                vec![Header::System("mutex")],
+            )
+        } else {
+            ("", "", Vec::new())
+        };
+        headers.push(Header::System("memory"));
+        // The class itself may live in a namespace; everything else
+        // refers to it by its fully qualified name.
+        let (namespace_open, namespace_close) =
+            cpp.get_namespace()
+                .iter()
+                .fold((String::new(), String::new()), |(open, close), ns| {
+                    (
+                        format!("{}namespace {} {{\n", open, ns),
+                        format!("{}}}\n", close),
+                    )
+                });
         self.additional_functions.push(AdditionalFunction {
             type_definition: Some(format!(
-                "class {} : {}\n{{\npublic:\n{}\n{}\nvoid {}() const;\nprivate:rust::Box<{}> obs;\nvoid really_remove_ownership();\n\n}};",
-                subclass.cpp(),
-                superclass.to_cpp_name(),
+                "{}class {} : {}\n{{\npublic:\n{}\n{}\nvoid {}() const;\nprivate:rust::Box<{}> obs;\n{}void really_remove_ownership();\n\n}};\n{}",
+                namespace_open,
+                cpp.get_final_item(),
+                superclasses.iter().map(|sup| sup.to_cpp_name()).join(", "),
                 constructor_decls.join("\n"),
                 method_decls.join("\n"),
                 subclass.cpp_remove_ownership(),
-                holder
+                holder,
+                obs_mutex_decl,
+                namespace_close,
             )),
             definition: Some(format!(
-                "void {}::{}() const {{\nconst_cast<{}*>(this)->really_remove_ownership();\n}}\nvoid {}::really_remove_ownership() {{\nauto new_obs = {}(std::move(obs));\nobs = std::move(new_obs);\n}}\n",
+                "void {}::{}() const {{\nconst_cast<{}*>(this)->really_remove_ownership();\n}}\nvoid {}::really_remove_ownership() {{\n{}auto new_obs = {}(std::move(obs));\nobs = std::move(new_obs);\n}}\n{}",
                 subclass.cpp(),
                 subclass.cpp_remove_ownership(),
                 subclass.cpp(),
                 subclass.cpp(),
-                subclass.remove_ownership()
+                obs_lock,
+                subclass.remove_ownership(),
+                factory_def,
             )),
-            declaration: None,
-            headers: Vec::new(),
+            declaration: Some(shared_decls.join("\n")),
+            headers,
             cpp_headers: vec![Header::CxxgenH],
         });
+        self.subclass_peers.push(cpp);
         Ok(())
     }
 }
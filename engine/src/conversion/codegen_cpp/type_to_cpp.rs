@@ -99,8 +99,15 @@ pub(crate) fn type_to_cpp(ty: &Type, cpp_name_map: &CppNameMap) -> Result<String
             get_mut_string(&typp.mutability),
             type_to_cpp(typp.elem.as_ref(), cpp_name_map)?
         )),
-        Type::Array(_)
-        | Type::BareFn(_)
+        // This is how cxx represents arrays in C++. Where the original
+        // C++ used a C array, our wrappers cast between the two; see
+        // `c_array_reference_to_cpp`.
+        Type::Array(arr) => Ok(format!(
+            "std::array<{}, {}>",
+            type_to_cpp(arr.elem.as_ref(), cpp_name_map)?,
+            arr.len.to_token_stream()
+        )),
+        Type::BareFn(_)
         | Type::Group(_)
         | Type::ImplTrait(_)
         | Type::Infer(_)
@@ -117,6 +124,33 @@ pub(crate) fn type_to_cpp(ty: &Type, cpp_name_map: &CppNameMap) -> Result<String
     }
 }
 
+/// Describes a Rust reference to an array, e.g. `&[[f32; 4]; 4]`, as the
+/// equivalent reference to a C array, e.g. `const float(&)[4][4]`.
+pub(crate) fn c_array_reference_to_cpp(
+    ty: &Type,
+    cpp_name_map: &CppNameMap,
+) -> Result<String, ConvertError> {
+    let (mutability, mut elem) = match ty {
+        Type::Reference(typr) => (&typr.mutability, typr.elem.as_ref()),
+        _ => {
+            return Err(ConvertError::UnsupportedType(
+                ty.to_token_stream().to_string(),
+            ))
+        }
+    };
+    let mut dimensions = String::new();
+    while let Type::Array(arr) = elem {
+        dimensions.push_str(&format!("[{}]", arr.len.to_token_stream()));
+        elem = arr.elem.as_ref();
+    }
+    Ok(format!(
+        "{}{}(&){}",
+        get_mut_string(mutability),
+        type_to_cpp(elem, cpp_name_map)?,
+        dimensions
+    ))
+}
+
 fn get_mut_string(mutability: &Option<Token![mut]>) -> &'static str {
     match mutability {
         None => "const ",
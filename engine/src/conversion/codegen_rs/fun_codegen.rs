@@ -19,7 +19,7 @@ use syn::{
     parse_quote,
     punctuated::Punctuated,
     token::{Comma, Unsafe},
-    Attribute, FnArg, ForeignItem, Ident, ImplItem, Item, ReturnType,
+    Attribute, FnArg, ForeignItem, Ident, ImplItem, Item, PatType, ReturnType, Type, TypeImplTrait,
 };
 
 use super::{
@@ -29,9 +29,10 @@ use super::{
 use crate::{
     conversion::{
         analysis::fun::{
+            function_wrapper::{RustConversionType, TypeConversionPolicy},
             ArgumentAnalysis, FnAnalysis, FnKind, MethodKind, RustRenameStrategy, UnsafetyNeeded,
         },
-        api::ImplBlockDetails,
+        api::{ImplBlockDetails, SubclassName},
         codegen_rs::lifetime::add_lifetime_to_all_params,
     },
     types::{Namespace, QualifiedName},
@@ -87,11 +88,27 @@ pub(super) fn gen_function(
         _ => Vec::new(),
     };
     let wrapper_unsafety = analysis.requires_unsafe.wrapper_token();
+    let ret_conversion = analysis
+        .ret_conversion
+        .as_ref()
+        .filter(|conversion| conversion.rust_work_needed());
+    // Some wrappers have a different return type from the cxx::bridge
+    // function they call.
+    let wrapper_ret_type = match ret_conversion {
+        Some(conversion) => {
+            let ty = conversion.rust_wrapper_return_type();
+            parse_quote! { -> #ty }
+        }
+        None => ret_type.clone(),
+    };
     let fn_generator = FnGenerator {
         param_details: &param_details,
+        ret_conversion,
         cxxbridge_name: &cxxbridge_name,
         rust_name: &rust_name,
         unsafety: &wrapper_unsafety,
+        call_needs_unsafe: matches!(analysis.requires_unsafe, UnsafetyNeeded::JustBridge)
+            || ret_conversion.is_some(),
         doc_attr: &doc_attr,
     };
     let mut materialization = match kind {
@@ -111,13 +128,14 @@ pub(super) fn gen_function(
             impl_for_specifics,
             trait_signature,
             method_name,
-            &ret_type,
+            &wrapper_ret_type,
         ),
     };
     let any_param_needs_rust_conversion = param_details
         .iter()
         .any(|pd| pd.conversion.rust_work_needed());
     let rust_wrapper_needed = any_param_needs_rust_conversion
+        || ret_conversion.is_some()
         || (cxxbridge_name != rust_name && matches!(kind, FnKind::Method(..)));
     if rust_wrapper_needed {
         match kind {
@@ -133,15 +151,34 @@ pub(super) fn gen_function(
                         MethodKind::MakeUnique | MethodKind::Constructor
                     ),
                     type_name,
-                    &ret_type,
+                    &wrapper_ret_type,
                 ));
             }
             _ => {
                 // Generate plain old function
-                materialization = Some(Use::Custom(fn_generator.generate_function_impl(&ret_type)));
+                materialization = Some(Use::Custom(
+                    fn_generator.generate_function_impl(&wrapper_ret_type),
+                ));
             }
         }
     }
+    // Each make_unique for a subclass's C++ peer corresponds to one of
+    // the superclass constructors, which the subclass may then be
+    // constructed with.
+    let bindgen_mod_items = match (&kind, param_details.first()) {
+        (
+            FnKind::Method(ref type_name, MethodKind::MakeUnique),
+            Some(ArgumentAnalysis {
+                conversion:
+                    TypeConversionPolicy {
+                        rust_conversion: RustConversionType::ToBoxedUpHolder(subclass),
+                        ..
+                    },
+                ..
+            }),
+        ) => vec![fn_generator.generate_peer_constructor_with_impl(subclass, type_name)],
+        _ => Vec::new(),
+    };
     if cxxbridge_name != cpp_call_name && !wrapper_function_needed {
         cpp_name_attr = Attribute::parse_outer
             .parse2(quote!(
@@ -188,7 +225,7 @@ pub(super) fn gen_function(
         extern_c_mod_items: vec![extern_c_mod_item],
         bridge_items: Vec::new(),
         global_items: Vec::new(),
-        bindgen_mod_items: Vec::new(),
+        bindgen_mod_items,
         impl_entry,
         materializations: materialization.into_iter().collect(),
         extern_rust_mod_items: Vec::new(),
@@ -199,13 +236,32 @@ pub(super) fn gen_function(
 #[derive(Clone)]
 struct FnGenerator<'a> {
     param_details: &'a [ArgumentAnalysis],
+    ret_conversion: Option<&'a TypeConversionPolicy>,
     cxxbridge_name: &'a Ident,
     rust_name: &'a str,
     unsafety: &'a Option<Unsafe>,
+    /// Whether calling the cxx::bridge function, or converting its
+    /// result, needs `unsafe` even if the wrapper itself is safe.
+    call_needs_unsafe: bool,
     doc_attr: &'a Option<Attribute>,
 }
 
 impl<'a> FnGenerator<'a> {
+    /// The body of a wrapper function which calls through to the given
+    /// cxx::bridge function, converting its return value if necessary.
+    fn generate_call(&self, bridge_fn: &Ident, arg_list: &[TokenStream]) -> TokenStream {
+        let call = quote! { cxxbridge::#bridge_fn ( #(#arg_list),* ) };
+        let call = match self.ret_conversion {
+            Some(conversion) => conversion.rust_return_conversion(call),
+            None => call,
+        };
+        if self.call_needs_unsafe && self.unsafety.is_none() {
+            quote! { unsafe { #call } }
+        } else {
+            call
+        }
+    }
+
     fn generate_arg_lists(&self, avoid_self: bool) -> (Punctuated<FnArg, Comma>, Vec<TokenStream>) {
         let mut wrapper_params: Punctuated<FnArg, Comma> = Punctuated::new();
         let mut arg_list = Vec::new();
@@ -238,12 +294,12 @@ impl<'a> FnGenerator<'a> {
         let rust_name = make_ident(self.rust_name);
         let unsafety = self.unsafety;
         let doc_attr = self.doc_attr;
-        let cxxbridge_name = self.cxxbridge_name;
+        let body = self.generate_call(self.cxxbridge_name, &arg_list);
         Box::new(ImplBlockDetails {
             item: ImplItem::Method(parse_quote! {
                 #doc_attr
                 pub #unsafety fn #rust_name #lifetime_tokens ( #wrapper_params ) #ret_type {
-                    cxxbridge::#cxxbridge_name ( #(#arg_list),* )
+                    #body
                 }
             }),
             ty: impl_block_type_name.get_final_ident(),
@@ -263,12 +319,12 @@ impl<'a> FnGenerator<'a> {
             add_explicit_lifetime_if_necessary(self.param_details, wrapper_params, ret_type);
         let doc_attr = self.doc_attr;
         let unsafety = self.unsafety;
-        let cxxbridge_name = self.cxxbridge_name;
+        let body = self.generate_call(self.cxxbridge_name, &arg_list);
         Some(Use::Custom(Box::new(parse_quote! {
             impl #lifetime_tokens #trait_signature for #impl_for_specifics {
                 #doc_attr
                 #unsafety fn #method_name ( #wrapper_params ) #ret_type {
-                    cxxbridge::#cxxbridge_name ( #(#arg_list),* )
+                    #body
                 }
             }
         })))
@@ -320,16 +376,80 @@ impl<'a> FnGenerator<'a> {
         })
     }
 
+    /// Generate an 'impl CppPeerConstructorWith' allowing a subclass to be
+    /// constructed using the superclass constructor which this make_unique
+    /// calls. The trait's `Args` are a tuple of the constructor parameters,
+    /// wrapped in an `UnsafeConstructorArgs` if make_unique is unsafe such
+    /// that the caller must promise it's safe to call.
+    fn generate_peer_constructor_with_impl(
+        &self,
+        subclass: &SubclassName,
+        cpp_type: &QualifiedName,
+    ) -> Item {
+        let (wrapper_params, _) = self.generate_arg_lists(true);
+        let mut wrapper_params: Punctuated<FnArg, Comma> =
+            wrapper_params.into_iter().skip(1).collect();
+        let any_references = self.param_details.iter().any(|pd| pd.was_reference);
+        let mut generic_params = Vec::new();
+        if any_references {
+            add_lifetime_to_all_params(&mut wrapper_params);
+            generic_params.push(quote! { 'a });
+        }
+        let mut arg_names = Vec::new();
+        let mut arg_types = Vec::new();
+        for (i, param) in wrapper_params.into_iter().enumerate() {
+            if let FnArg::Typed(PatType { pat, ty, .. }) = param {
+                // `impl Trait` can't appear within a tuple type, so
+                // such parameters become generic.
+                let ty = match *ty {
+                    Type::ImplTrait(TypeImplTrait { bounds, .. }) => {
+                        let generic = make_ident(format!("Arg{}", i));
+                        generic_params.push(quote! { #generic: #bounds });
+                        parse_quote! { #generic }
+                    }
+                    ty => ty,
+                };
+                arg_names.push(pat);
+                arg_types.push(ty);
+            }
+        }
+        let id = subclass.0.name.get_final_ident();
+        let cpp_id = cpp_type.get_final_ident();
+        let rust_name = make_ident(self.rust_name);
+        let (args_type, unpack_args, call) = if self.unsafety.is_some() {
+            (
+                quote! { autocxx::subclass::UnsafeConstructorArgs<(#(#arg_types,)*)> },
+                quote! { args.into_inner() },
+                quote! { unsafe { #cpp_id :: #rust_name(peer_holder, #(#arg_names),*) } },
+            )
+        } else {
+            (
+                quote! { (#(#arg_types,)*) },
+                quote! { args },
+                quote! { #cpp_id :: #rust_name(peer_holder, #(#arg_names),*) },
+            )
+        };
+        parse_quote! {
+            impl <#(#generic_params),*> autocxx::subclass::CppPeerConstructorWith<#cpp_id, #args_type> for super::super::super::#id {
+                fn make_peer_with(peer_holder: autocxx::subclass::CppSubclassRustPeerHolder<Self>, args: #args_type) -> cxx::UniquePtr<#cpp_id> {
+                    let (#(#arg_names,)*) = #unpack_args;
+                    #call
+                }
+            }
+        }
+    }
+
     /// Generate a function call wrapper
     fn generate_function_impl(&self, ret_type: &ReturnType) -> Box<Item> {
         let (wrapper_params, arg_list) = self.generate_arg_lists(false);
         let rust_name = make_ident(self.rust_name);
         let doc_attr = self.doc_attr;
         let unsafety = self.unsafety;
+        let body = self.generate_call(&rust_name, &arg_list);
         Box::new(Item::Fn(parse_quote! {
             #doc_attr
             pub #unsafety fn #rust_name ( #wrapper_params ) #ret_type {
-                cxxbridge::#rust_name ( #(#arg_list),* )
+                #body
             }
         }))
     }
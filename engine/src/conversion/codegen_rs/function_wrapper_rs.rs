@@ -16,7 +16,7 @@ use proc_macro2::TokenStream;
 use syn::{Pat, Type};
 
 use crate::conversion::analysis::fun::function_wrapper::{
-    RustConversionType, TypeConversionPolicy,
+    CStrLifetime, RustConversionType, TypeConversionPolicy,
 };
 use quote::quote;
 use syn::parse_quote;
@@ -32,6 +32,11 @@ impl TypeConversionPolicy {
                 }
             }
             RustConversionType::FromStr => parse_quote! { impl ToCppString },
+            RustConversionType::ToBoxedClosure(_, ref dyn_fn) => parse_quote! { Box< #dyn_fn > },
+            RustConversionType::FromCStr => parse_quote! { &std::ffi::CStr },
+            RustConversionType::ToCStr { .. } => {
+                panic!("Return value conversions have no parameter type")
+            }
         }
     }
 
@@ -45,6 +50,71 @@ impl TypeConversionPolicy {
                     Box::new(#holder_type(#var))
                 }
             }
+            RustConversionType::ToBoxedClosure(ref closure, _) => {
+                let closure_id = closure.get_final_ident();
+                quote! {
+                    Box::new(#closure_id(#var))
+                }
+            }
+            RustConversionType::FromCStr => quote! { #var .as_ptr() },
+            RustConversionType::ToCStr { .. } => {
+                panic!("Return value conversions have no parameter conversion")
+            }
+        }
+    }
+
+    /// The type returned by a Rust wrapper function where the return
+    /// value needs conversion.
+    pub(super) fn rust_wrapper_return_type(&self) -> Type {
+        match self.rust_conversion {
+            RustConversionType::ToCStr { nullable, lifetime } => {
+                let cstr: Type = match lifetime {
+                    CStrLifetime::Receiver => parse_quote! { &std::ffi::CStr },
+                    CStrLifetime::Static => parse_quote! { &'static std::ffi::CStr },
+                    CStrLifetime::Copied => parse_quote! { std::ffi::CString },
+                };
+                if nullable {
+                    parse_quote! { Option<#cstr> }
+                } else {
+                    cstr
+                }
+            }
+            _ => self.converted_rust_type(),
+        }
+    }
+
+    /// Converts the return value of a cxx bridge function. The resulting
+    /// expression must be evaluated within an `unsafe` context.
+    pub(super) fn rust_return_conversion(&self, call: TokenStream) -> TokenStream {
+        match self.rust_conversion {
+            RustConversionType::ToCStr { nullable, lifetime } => {
+                let cstr = if lifetime == CStrLifetime::Copied {
+                    quote! { std::ffi::CStr::from_ptr(ret).to_owned() }
+                } else {
+                    quote! { std::ffi::CStr::from_ptr(ret) }
+                };
+                if nullable {
+                    quote! {
+                        {
+                            let ret = #call;
+                            if ret.is_null() {
+                                None
+                            } else {
+                                Some(#cstr)
+                            }
+                        }
+                    }
+                } else {
+                    quote! {
+                        {
+                            let ret = #call;
+                            assert!(!ret.is_null(), "C++ returned a null string");
+                            #cstr
+                        }
+                    }
+                }
+            }
+            _ => call,
         }
     }
 }
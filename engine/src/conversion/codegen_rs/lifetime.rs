@@ -23,8 +23,9 @@ use syn::{
 /// Function which can add explicit lifetime parameters to function signatures
 /// where necessary, based on analysis of parameters and return types.
 /// This is necessary only in one case - where the parameter is a Pin<&mut T>
-/// and the return type is some kind of reference - because lifetime elision
-/// is not smart enough to see inside a Pin.
+/// and the return type is some kind of reference (perhaps within an
+/// `Option`) - because lifetime elision is not smart enough to see inside
+/// a Pin.
 pub(crate) fn add_explicit_lifetime_if_necessary<'r>(
     param_details: &[ArgumentAnalysis],
     mut params: Punctuated<FnArg, Comma>,
@@ -42,7 +43,7 @@ pub(crate) fn add_explicit_lifetime_if_necessary<'r>(
     }
     let new_return_type = match ret_type {
         ReturnType::Type(rarrow, boxed_type) => match boxed_type.as_ref() {
-            Type::Reference(rtr) => {
+            Type::Reference(rtr) if rtr.lifetime.is_none() => {
                 let mut new_rtr = rtr.clone();
                 new_rtr.lifetime = Some(parse_quote! { 'a });
                 Some(ReturnType::Type(
@@ -53,6 +54,7 @@ pub(crate) fn add_explicit_lifetime_if_necessary<'r>(
             Type::Path(typ) => {
                 let mut new_path = typ.clone();
                 add_lifetime_to_pinned_reference(&mut new_path.path.segments)
+                    .or_else(|_| add_lifetime_to_optional_reference(&mut new_path.path.segments))
                     .ok()
                     .map(|_| ReturnType::Type(*rarrow, Box::new(Type::Path(new_path))))
             }
@@ -67,10 +69,15 @@ pub(crate) fn add_explicit_lifetime_if_necessary<'r>(
             for mut param in params.iter_mut() {
                 match &mut param {
                     FnArg::Typed(PatType { ty, .. }) => match ty.as_mut() {
+                        // Other parameters, e.g. a `&CStr`, keep their
+                        // elided lifetimes.
                         Type::Path(TypePath {
                             path: Path { segments, .. },
                             ..
-                        }) => add_lifetime_to_pinned_reference(segments).unwrap(),
+                        }) => {
+                            let _ = add_lifetime_to_pinned_reference(segments);
+                        }
+                        Type::Reference(_) => {}
                         _ => panic!("Expected Pin<T>"),
                     },
                     _ => panic!("Unexpected fnarg"),
@@ -85,6 +92,7 @@ pub(crate) fn add_explicit_lifetime_if_necessary<'r>(
 #[derive(Debug)]
 enum AddLifetimeError {
     WasNotPin,
+    WasNotOptionalReference,
 }
 
 fn add_lifetime_to_pinned_reference(
@@ -115,6 +123,24 @@ fn add_lifetime_to_pinned_reference(
     Ok(())
 }
 
+fn add_lifetime_to_optional_reference(
+    segments: &mut Punctuated<PathSegment, syn::token::Colon2>,
+) -> Result<(), AddLifetimeError> {
+    match segments.last_mut() {
+        Some(seg) if seg.ident == "Option" => match &mut seg.arguments {
+            syn::PathArguments::AngleBracketed(aba) => match aba.args.iter_mut().next() {
+                Some(GenericArgument::Type(Type::Reference(tyr))) if tyr.lifetime.is_none() => {
+                    add_lifetime_to_reference(tyr);
+                    Ok(())
+                }
+                _ => Err(AddLifetimeError::WasNotOptionalReference),
+            },
+            _ => Err(AddLifetimeError::WasNotOptionalReference),
+        },
+        _ => Err(AddLifetimeError::WasNotOptionalReference),
+    }
+}
+
 fn add_lifetime_to_reference(tyr: &mut syn::TypeReference) {
     tyr.lifetime = Some(parse_quote! { 'a })
 }
@@ -126,7 +152,10 @@ pub(crate) fn add_lifetime_to_all_params(params: &mut Punctuated<FnArg, Comma>)
                 Type::Path(TypePath {
                     path: Path { segments, .. },
                     ..
-                }) => add_lifetime_to_pinned_reference(segments).unwrap(),
+                }) => {
+                    // Types other than Pin need no lifetime.
+                    let _ = add_lifetime_to_pinned_reference(segments);
+                }
                 Type::Reference(tyr) => add_lifetime_to_reference(tyr),
                 _ => {}
             },
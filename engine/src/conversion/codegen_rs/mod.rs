@@ -22,12 +22,13 @@ pub(crate) mod unqualify;
 
 use std::collections::{HashMap, HashSet};
 
-use autocxx_parser::IncludeCppConfig;
+use autocxx_parser::{IncludeCppConfig, SubclassErrorPolicy};
 
 use proc_macro2::{Span, TokenStream};
 use syn::{
     parse_quote, punctuated::Punctuated, token::Comma, Attribute, Expr, FnArg, ForeignItem,
     ForeignItemFn, Ident, ImplItem, Item, ItemForeignMod, ItemMod, Pat, ReturnType, TraitItem,
+    Type,
 };
 
 use crate::{
@@ -51,7 +52,10 @@ use self::{
 
 use super::{
     analysis::fun::{FnAnalysis, FnKind},
-    api::{Layout, RustSubclassFnDetails, Synthesis},
+    api::{
+        ContainerDetails, CppVisibility, Layout, RustClosureDetails, RustSubclassFnDetails,
+        Synthesis,
+    },
     codegen_cpp::type_to_cpp::{
         namespaced_name_using_original_name_map, original_name_map_from_apis, CppNameMap,
     },
@@ -131,6 +135,9 @@ struct SuperclassMethod {
     receiver_mutability: ReceiverMutability,
     requires_unsafe: bool,
     is_pure_virtual: bool,
+    /// False for protected, non-virtual methods, which subclasses may
+    /// call but not override.
+    is_virtual: bool,
 }
 
 /// Type which handles generation of Rust code.
@@ -168,19 +175,36 @@ impl<'a> RsCodeGenerator<'a> {
         let subclasses_with_a_single_trivial_constructor =
             find_trivially_constructed_subclasses(&all_apis);
         // Now let's generate the Rust code.
-        let (rs_codegen_results_and_namespaces, additional_cpp_needs): (Vec<_>, Vec<_>) = all_apis
-            .into_iter()
-            .map(|api| {
-                let more_cpp_needed = api.needs_cpp_codegen();
-                let name = api.name().clone();
-                let gen = self.generate_rs_for_api(
-                    api,
-                    &methods_by_superclass,
-                    &subclasses_with_a_single_trivial_constructor,
-                );
-                ((name, gen), more_cpp_needed)
+        // Subclasses whose C++ peers live in a namespace need those peers
+        // to be visible from that namespace in Rust, too.
+        let namespaced_subclass_peers: Vec<_> = all_apis
+            .iter()
+            .filter_map(|api| match api {
+                Api::Subclass { name, .. } if !name.cpp().get_namespace().is_empty() => {
+                    Some(name.clone())
+                }
+                _ => None,
             })
-            .unzip();
+            .collect();
+        let (mut rs_codegen_results_and_namespaces, additional_cpp_needs): (Vec<_>, Vec<_>) =
+            all_apis
+                .into_iter()
+                .map(|api| {
+                    let more_cpp_needed = api.needs_cpp_codegen();
+                    let name = api.name().clone();
+                    let gen = self.generate_rs_for_api(
+                        api,
+                        &methods_by_superclass,
+                        &subclasses_with_a_single_trivial_constructor,
+                    );
+                    ((name, gen), more_cpp_needed)
+                })
+                .unzip();
+        rs_codegen_results_and_namespaces.extend(
+            namespaced_subclass_peers
+                .into_iter()
+                .map(|sub| (sub.cpp(), Self::generate_subclass_peer_reexport(&sub))),
+        );
         // First, the hierarchy of mods containing lots of 'use' statements
         // which is the final API exposed as 'ffi'.
         let mut use_statements =
@@ -278,6 +302,7 @@ impl<'a> RsCodeGenerator<'a> {
                         param_details,
                         ..
                     },
+                fun,
                 ..
             } = api
             {
@@ -296,6 +321,26 @@ impl<'a> RsCodeGenerator<'a> {
                                 receiver_mutability: receiver_mutability.clone(),
                                 requires_unsafe: param_details.iter().any(|pd| pd.requires_unsafe),
                                 is_pure_virtual: matches!(method_kind, MethodKind::PureVirtual(..)),
+                                is_virtual: true,
+                            })
+                        }
+                    }
+                    MethodKind::Normal(receiver_mutability)
+                        if fun.cpp_vis == CppVisibility::Protected =>
+                    {
+                        if let Some(list) = results.get_mut(receiver) {
+                            list.push(SuperclassMethod {
+                                name: name.name.get_final_ident(),
+                                params: params.clone(),
+                                ret_type: ret_type.clone(),
+                                param_names: param_details
+                                    .iter()
+                                    .map(|pd| pd.name.clone())
+                                    .collect(),
+                                receiver_mutability: receiver_mutability.clone(),
+                                requires_unsafe: param_details.iter().any(|pd| pd.requires_unsafe),
+                                is_pure_virtual: false,
+                                is_virtual: false,
                             })
                         }
                     }
@@ -517,14 +562,18 @@ impl<'a> RsCodeGenerator<'a> {
             } => {
                 let doc_attr = get_doc_attr(&details.item.attrs);
                 let layout = details.layout.clone();
-                self.generate_type(
+                let mut result = self.generate_type(
                     &name,
-                    id,
+                    id.clone(),
                     analysis.kind,
                     || Some((Item::Struct(details.item), doc_attr)),
                     associated_methods,
                     layout,
-                )
+                );
+                if let Some(container) = analysis.container {
+                    Self::add_container_accessors(&name, id, *container, &mut result);
+                }
+                result
             }
             Api::Enum { item, .. } => {
                 let doc_attr = get_doc_attr(&item.attrs);
@@ -537,7 +586,10 @@ impl<'a> RsCodeGenerator<'a> {
                     None,
                 )
             }
-            Api::ForwardDeclaration { .. } | Api::ConcreteType { .. } => self.generate_type(
+            Api::ForwardDeclaration { .. }
+            | Api::ConcreteType {
+                container: None, ..
+            } => self.generate_type(
                 &name,
                 id,
                 TypeKind::Abstract,
@@ -545,6 +597,21 @@ impl<'a> RsCodeGenerator<'a> {
                 associated_methods,
                 None,
             ),
+            Api::ConcreteType {
+                container: Some(container),
+                ..
+            } => {
+                let mut result = self.generate_type(
+                    &name,
+                    id.clone(),
+                    TypeKind::Abstract,
+                    || None,
+                    associated_methods,
+                    None,
+                );
+                Self::add_container_accessors(&name, id, *container, &mut result);
+                result
+            }
             Api::CType { .. } => RsCodegenResult {
                 global_items: Vec::new(),
                 impl_entry: None,
@@ -586,27 +653,246 @@ impl<'a> RsCodeGenerator<'a> {
                 details, subclass, ..
             } => Self::generate_subclass_fn(id, *details, subclass),
             Api::Subclass {
-                name, superclass, ..
+                name, superclasses, ..
             } => {
-                let methods = associated_methods.get(&superclass);
                 let generate_peer_constructor =
                     subclasses_with_a_single_trivial_constructor.contains(&name.0.name);
-                self.generate_subclass(name, &superclass, methods, generate_peer_constructor)
+                self.generate_subclass(
+                    name,
+                    &superclasses,
+                    associated_methods,
+                    generate_peer_constructor,
+                )
             }
             Api::IgnoredItem { err, ctx, .. } => Self::generate_error_entry(err, ctx),
+            Api::RustClosure { details, .. } => Self::generate_closure(id, *details),
         }
     }
 
+    /// Generates the Rust type used to pass a closure into C++ as a
+    /// `std::function`, along with the function which C++ calls
+    /// in order to invoke it.
+    fn generate_closure(id: Ident, details: RustClosureDetails) -> RsCodegenResult {
+        let call_fn = RustClosureDetails::get_call_fn_name(&QualifiedName::new(
+            &Namespace::new(),
+            id.clone(),
+        ));
+        let dyn_fn = details.dyn_fn_type();
+        let arg_names: Vec<_> = (0..details.params.len())
+            .map(|n| make_ident(format!("arg{}", n)))
+            .collect();
+        let receiver: FnArg = parse_quote! { closure: &mut #id };
+        let params: Punctuated<FnArg, Comma> = std::iter::once(receiver)
+            .chain(
+                details
+                    .params
+                    .iter()
+                    .zip(arg_names.iter())
+                    .map(|(ty, arg_name)| parse_quote! { #arg_name: #ty }),
+            )
+            .collect();
+        let ret: ReturnType = match details.ret {
+            Some(ret) => parse_quote! { -> #ret },
+            None => ReturnType::Default,
+        };
+        let global_items = vec![
+            parse_quote! {
+                pub struct #id(pub Box<#dyn_fn>);
+            },
+            parse_quote! {
+                #[allow(non_snake_case)]
+                fn #call_fn(#params) #ret {
+                    (closure.0)(#(#arg_names),*)
+                }
+            },
+        ];
+        let params = unqualify_params(params);
+        let ret = unqualify_ret_type(ret);
+        RsCodegenResult {
+            global_items,
+            extern_rust_mod_items: vec![
+                parse_quote! {
+                    type #id;
+                },
+                parse_quote! {
+                    fn #call_fn(#params) #ret;
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    /// Generate iteration for a type which is a C++ container, plus `len`
+    /// and (for maps) `get` if it's a standard one. The iterator itself
+    /// lives in C++; see the matching C++ codegen.
+    fn add_container_accessors(
+        name: &QualifiedName,
+        id: Ident,
+        container: ContainerDetails,
+        result: &mut RsCodegenResult,
+    ) {
+        let iter_type = ContainerDetails::get_iter_type_name(name);
+        let helper = |suffix| ContainerDetails::get_helper_fn_name(name, suffix);
+        let (iter_fn, is_end_fn, advance_fn, len_fn) = (
+            helper("iter"),
+            helper("Iter_is_end"),
+            helper("Iter_advance"),
+            helper("len"),
+        );
+        let rust_iterator = ContainerDetails::get_rust_iterator_name(name);
+        let mut extern_c_mod_items: Vec<ForeignItem> = vec![
+            parse_quote! {
+                type #iter_type;
+            },
+            parse_quote! {
+                fn #iter_fn(container: &#id) -> UniquePtr<#iter_type>;
+            },
+            parse_quote! {
+                fn #is_end_fn(it: &#iter_type) -> bool;
+            },
+            parse_quote! {
+                fn #advance_fn(it: Pin<&mut #iter_type>);
+            },
+        ];
+        let mut bindgen_mod_items: Vec<Item> = vec![parse_quote! {
+            impl #id {
+                /// Returns an iterator over the contents of this container.
+                pub fn iter(&self) -> #rust_iterator<'_> {
+                    #rust_iterator {
+                        it: cxxbridge::#iter_fn(self),
+                        phantom: ::std::marker::PhantomData,
+                    }
+                }
+            }
+        }];
+        if !matches!(container, ContainerDetails::Range { .. }) {
+            extern_c_mod_items.push(parse_quote! {
+                fn #len_fn(container: &#id) -> usize;
+            });
+            bindgen_mod_items.push(parse_quote! {
+                impl #id {
+                    /// Returns the number of elements in this container.
+                    pub fn len(&self) -> usize {
+                        cxxbridge::#len_fn(self)
+                    }
+
+                    /// Returns whether this container is empty.
+                    pub fn is_empty(&self) -> bool {
+                        self.len() == 0
+                    }
+                }
+            });
+        }
+        // The references we hand out point into the container, not into
+        // the C++ iterator, so may live as long as the container borrow.
+        let (item_type, item_expr): (Type, Expr) = match container {
+            ContainerDetails::Values { element } | ContainerDetails::Range { element, .. } => {
+                let get_fn = helper("Iter_get");
+                let unqualified_element = unqualify_ret_type(parse_quote! { -> &#element });
+                extern_c_mod_items.push(parse_quote! {
+                    fn #get_fn(it: &#iter_type) #unqualified_element;
+                });
+                (
+                    parse_quote! { &'a #element },
+                    parse_quote! {
+                        unsafe { &*(cxxbridge::#get_fn(&self.it) as *const #element) }
+                    },
+                )
+            }
+            ContainerDetails::Map { key, value } => {
+                let (key_fn, value_fn, contains_fn, at_fn) = (
+                    helper("Iter_key"),
+                    helper("Iter_value"),
+                    helper("contains"),
+                    helper("at"),
+                );
+                let mut key_params: Punctuated<FnArg, Comma> = Punctuated::new();
+                key_params.push(parse_quote! { container: &#id });
+                key_params.push(parse_quote! { key: &#key });
+                let key_params = unqualify_params(key_params);
+                let key_ret = unqualify_ret_type(parse_quote! { -> &#key });
+                let value_ret = unqualify_ret_type(parse_quote! { -> &#value });
+                extern_c_mod_items.extend([
+                    parse_quote! {
+                        fn #key_fn(it: &#iter_type) #key_ret;
+                    },
+                    parse_quote! {
+                        fn #value_fn(it: &#iter_type) #value_ret;
+                    },
+                    parse_quote! {
+                        fn #contains_fn(#key_params) -> bool;
+                    },
+                    parse_quote! {
+                        fn #at_fn(#key_params) #value_ret;
+                    },
+                ]);
+                bindgen_mod_items.push(parse_quote! {
+                    impl #id {
+                        /// Looks up the value for a given key, if present.
+                        pub fn get(&self, key: &#key) -> Option<&#value> {
+                            if cxxbridge::#contains_fn(self, key) {
+                                Some(cxxbridge::#at_fn(self, key))
+                            } else {
+                                None
+                            }
+                        }
+                    }
+                });
+                (
+                    parse_quote! { (&'a #key, &'a #value) },
+                    parse_quote! {
+                        unsafe {
+                            (
+                                &*(cxxbridge::#key_fn(&self.it) as *const #key),
+                                &*(cxxbridge::#value_fn(&self.it) as *const #value),
+                            )
+                        }
+                    },
+                )
+            }
+        };
+        bindgen_mod_items.extend([
+            parse_quote! {
+                #[allow(non_camel_case_types)]
+                pub struct #rust_iterator<'a> {
+                    it: cxx::UniquePtr<cxxbridge::#iter_type>,
+                    phantom: ::std::marker::PhantomData<&'a #id>,
+                }
+            },
+            parse_quote! {
+                impl<'a> Iterator for #rust_iterator<'a> {
+                    type Item = #item_type;
+                    fn next(&mut self) -> Option<Self::Item> {
+                        if cxxbridge::#is_end_fn(&self.it) {
+                            return None;
+                        }
+                        let item = #item_expr;
+                        cxxbridge::#advance_fn(self.it.pin_mut());
+                        Some(item)
+                    }
+                }
+            },
+            parse_quote! {
+                impl<'a> IntoIterator for &'a #id {
+                    type Item = #item_type;
+                    type IntoIter = #rust_iterator<'a>;
+                    fn into_iter(self) -> Self::IntoIter {
+                        self.iter()
+                    }
+                }
+            },
+        ]);
+        result.extern_c_mod_items.extend(extern_c_mod_items);
+        result.bindgen_mod_items.extend(bindgen_mod_items);
+    }
+
     fn generate_subclass(
         &self,
         sub: SubclassName,
-        superclass: &QualifiedName,
-        methods: Option<&Vec<SuperclassMethod>>,
+        superclasses: &[QualifiedName],
+        associated_methods: &HashMap<QualifiedName, Vec<SuperclassMethod>>,
         generate_peer_constructor: bool,
     ) -> RsCodegenResult {
-        let super_name = superclass.get_final_item();
-        let super_path = superclass.to_type_path();
-        let super_cxxxbridge_id = superclass.get_final_ident();
         let id = sub.id();
         let holder = sub.holder();
         let full_cpp = sub.cpp();
@@ -617,6 +903,7 @@ impl<'a> RsCodeGenerator<'a> {
             pub use bindgen::root::#holder;
         });
         let relinquish_ownership_call = sub.cpp_remove_ownership();
+        let into_shared_call = sub.cpp_into_shared();
         let mut bindgen_mod_items = vec![
             parse_quote! {
                 pub use cxxbridge::#cpp_id;
@@ -629,6 +916,9 @@ impl<'a> RsCodeGenerator<'a> {
                     fn relinquish_ownership(&self) {
                         self.#relinquish_ownership_call();
                     }
+                    fn into_shared(me: cxx::UniquePtr<Self>) -> cxx::SharedPtr<Self> {
+                        cxxbridge::#into_shared_call(me)
+                    }
                 }
             },
         ];
@@ -637,8 +927,14 @@ impl<'a> RsCodeGenerator<'a> {
             parse_quote! {
                 fn #relinquish_ownership_call(self: &#cpp_id);
             },
+            parse_quote! {
+                fn #into_shared_call(me: UniquePtr<#cpp_id>) -> SharedPtr<#cpp_id>;
+            },
         ];
-        if let Some(methods) = methods {
+        for (superclass, methods) in superclasses
+            .iter()
+            .filter_map(|sup| associated_methods.get(sup).map(|methods| (sup, methods)))
+        {
             let supers = SubclassName::get_supers_trait_name(superclass).to_type_path();
             let methods_impls: Vec<ImplItem> = methods
                 .iter()
@@ -674,6 +970,12 @@ impl<'a> RsCodeGenerator<'a> {
                 });
             }
         }
+        let thread_safe = self.config.is_subclass_thread_safe(id.to_string().as_str());
+        if !thread_safe {
+            bindgen_mod_items.push(parse_quote! {
+                impl autocxx::subclass::CppSubclassNotThreadSafe<#cpp_id> for super::super::super::#id {}
+            });
+        }
         if generate_peer_constructor {
             bindgen_mod_items.push(parse_quote! {
                 impl autocxx::subclass::CppPeerConstructor<#cpp_id> for super::super::super::#id {
@@ -684,32 +986,59 @@ impl<'a> RsCodeGenerator<'a> {
             })
         };
 
-        // Once for each superclass, in future...
-        let as_id = make_ident(format!("As_{}", super_name));
-        extern_c_mod_items.push(parse_quote! {
-            fn #as_id(self: &#cpp_id) -> &#super_cxxxbridge_id;
-        });
-        let as_mut_id = make_ident(format!("As_{}_mut", super_name));
-        extern_c_mod_items.push(parse_quote! {
-            fn #as_mut_id(self: Pin<&mut #cpp_id>) -> Pin<&mut #super_cxxxbridge_id>;
-        });
-        bindgen_mod_items.push(parse_quote! {
-            impl AsRef<#super_path> for super::super::super::#id {
-                fn as_ref(&self) -> &cxxbridge::#super_cxxxbridge_id {
-                    use autocxx::subclass::CppSubclass;
-                    self.peer().#as_id()
+        for (idx, superclass) in superclasses.iter().enumerate() {
+            let super_name = superclass.get_final_item();
+            let super_path = superclass.to_type_path();
+            let super_cxxxbridge_id = superclass.get_final_ident();
+            let as_id = make_ident(format!("As_{}", super_name));
+            extern_c_mod_items.push(parse_quote! {
+                fn #as_id(self: &#cpp_id) -> &#super_cxxxbridge_id;
+            });
+            let as_mut_id = make_ident(format!("As_{}_mut", super_name));
+            extern_c_mod_items.push(parse_quote! {
+                fn #as_mut_id(self: Pin<&mut #cpp_id>) -> Pin<&mut #super_cxxxbridge_id>;
+            });
+            bindgen_mod_items.push(parse_quote! {
+                impl AsRef<#super_path> for super::super::super::#id {
+                    fn as_ref(&self) -> &cxxbridge::#super_cxxxbridge_id {
+                        use autocxx::subclass::CppSubclass;
+                        self.peer().#as_id()
+                    }
                 }
-            }
-        });
-        // TODO it would be nice to impl AsMut here but pin prevents us
-        bindgen_mod_items.push(parse_quote! {
-            impl super::super::super::#id {
-                pub fn pin_mut(&mut self) -> std::pin::Pin<&mut cxxbridge::#super_cxxxbridge_id> {
-                    use autocxx::subclass::CppSubclass;
-                    self.peer_mut().#as_mut_id()
+            });
+            let shared_as_id = sub.cpp_shared_as(superclass);
+            extern_c_mod_items.push(parse_quote! {
+                fn #shared_as_id(me: &SharedPtr<#cpp_id>) -> SharedPtr<#super_cxxxbridge_id>;
+            });
+            bindgen_mod_items.push(parse_quote! {
+                impl autocxx::subclass::CppPeerSharedUpcast<#super_path> for cxx::SharedPtr<#cpp_id> {
+                    fn upcast(&self) -> cxx::SharedPtr<cxxbridge::#super_cxxxbridge_id> {
+                        cxxbridge::#shared_as_id(self)
+                    }
                 }
+            });
+            // TODO it would be nice to impl AsMut here but pin prevents us.
+            // pin_mut refers to the first superclass; if there are others,
+            // each gets its own pin_mut_as_<superclass>.
+            let mut pin_mut_ids = Vec::new();
+            if idx == 0 {
+                pin_mut_ids.push(make_ident("pin_mut"));
             }
-        });
+            if superclasses.len() > 1 {
+                pin_mut_ids.push(make_ident(format!("pin_mut_as_{}", super_name)));
+            }
+            for pin_mut_id in pin_mut_ids {
+                bindgen_mod_items.push(parse_quote! {
+                    #[allow(non_snake_case)]
+                    impl super::super::super::#id {
+                        pub fn #pin_mut_id(&mut self) -> std::pin::Pin<&mut cxxbridge::#super_cxxxbridge_id> {
+                            use autocxx::subclass::CppSubclass;
+                            self.peer_mut().#as_mut_id()
+                        }
+                    }
+                });
+            }
+        }
         let remove_ownership = sub.remove_ownership();
         global_items.push(parse_quote! {
             #[allow(non_snake_case)]
@@ -717,23 +1046,72 @@ impl<'a> RsCodeGenerator<'a> {
                 Box::new(#holder(me.0.relinquish_ownership()))
             }
         });
+        let mut extern_rust_mod_items = vec![
+            parse_quote! {
+                pub type #holder;
+            },
+            parse_quote! {
+                fn #remove_ownership(me: Box<#holder>) -> Box<#holder>;
+            },
+        ];
+        if self.config.is_subclass_cpp_factory(id.to_string().as_str()) {
+            let create = sub.cpp_create();
+            // A thread-safe subclass must be created using the constructors
+            // in CppSubclassThreadSafe.
+            let (constructor_trait, constructor) = if thread_safe {
+                (
+                    make_ident("CppSubclassThreadSafe"),
+                    quote! { new_cpp_owned_thread_safe(Default::default()) },
+                )
+            } else {
+                (
+                    make_ident("CppSubclassDefault"),
+                    quote! { default_cpp_owned() },
+                )
+            };
+            global_items.push(parse_quote! {
+                #[allow(non_snake_case)]
+                pub fn #create() -> cxx::UniquePtr<cxxbridge::#cpp_id> {
+                    use autocxx::subclass::#constructor_trait;
+                    super::#id::#constructor
+                }
+            });
+            extern_rust_mod_items.push(parse_quote! {
+                fn #create() -> UniquePtr<#cpp_id>;
+            });
+        }
         RsCodegenResult {
             extern_c_mod_items,
             bridge_items: create_impl_items(&cpp_id, self.config),
             bindgen_mod_items,
-            materializations: vec![Use::Custom(Box::new(parse_quote! {
-                pub use cxxbridge::#cpp_id;
-            }))],
+            materializations: if full_cpp.get_namespace().is_empty() {
+                vec![Use::Custom(Box::new(parse_quote! {
+                    pub use cxxbridge::#cpp_id;
+                }))]
+            } else {
+                Vec::new()
+            },
             global_items,
             impl_entry: None,
-            extern_rust_mod_items: vec![
-                parse_quote! {
-                    pub type #holder;
-                },
-                parse_quote! {
-                    fn #remove_ownership(me: Box<#holder>) -> Box<#holder>;
-                },
-            ],
+            extern_rust_mod_items,
+        }
+    }
+
+    /// Makes the C++ peer of a subclass available from the namespace
+    /// into which it was generated, since the rest of its bindings
+    /// live in the root namespace.
+    fn generate_subclass_peer_reexport(sub: &SubclassName) -> RsCodegenResult {
+        let cpp_id = sub.cpp().get_final_ident();
+        RsCodegenResult {
+            extern_c_mod_items: Vec::new(),
+            extern_rust_mod_items: Vec::new(),
+            bridge_items: Vec::new(),
+            global_items: Vec::new(),
+            bindgen_mod_items: vec![parse_quote! {
+                pub use cxxbridge::#cpp_id;
+            }],
+            impl_entry: None,
+            materializations: vec![Use::UsedFromCxxBridge],
         }
     }
 
@@ -745,9 +1123,25 @@ impl<'a> RsCodeGenerator<'a> {
         let params = details.params;
         let ret = details.ret;
         let unsafe_token = get_unsafe_token(details.requires_unsafe);
-        let global_def = quote! { #unsafe_token fn #api_name(#params) #ret };
+        // cxx turns an `Err` into a C++ exception.
+        let (global_ret, ret) = if matches!(details.error_policy, SubclassErrorPolicy::Exception) {
+            let ret_ty = match &ret {
+                ReturnType::Default => parse_quote! { () },
+                ReturnType::Type(_, ty) => ty.as_ref().clone(),
+            };
+            let unqualified_ret_ty = match unqualify_ret_type(ret) {
+                ReturnType::Default => parse_quote! { () },
+                ReturnType::Type(_, ty) => *ty,
+            };
+            let global_ret: ReturnType =
+                parse_quote! { -> ::std::result::Result<#ret_ty, ::std::string::String> };
+            let ret: ReturnType = parse_quote! { -> Result<#unqualified_ret_ty> };
+            (global_ret, ret)
+        } else {
+            (ret.clone(), unqualify_ret_type(ret))
+        };
+        let global_def = quote! { #unsafe_token fn #api_name(#params) #global_ret };
         let params = unqualify_params(params);
-        let ret = unqualify_ret_type(ret);
         let method_name = details.method_name;
         let cxxbridge_decl: ForeignItemFn =
             parse_quote! { #unsafe_token fn #api_name(#params) #ret; };
@@ -768,28 +1162,55 @@ impl<'a> RsCodeGenerator<'a> {
         let deref_ty = make_ident(deref_ty);
         let deref_call = make_ident(deref_call);
         let borrow = make_ident(borrow);
-        let destroy_panic_msg = format!("Rust subclass API (method {} of subclass {} of superclass {}) called after subclass destroyed", method_name, subclass.0.name, superclass_id);
-        let reentrancy_panic_msg = format!("Rust subclass API (method {} of subclass {} of superclass {}) called whilst subclass already borrowed - likely a re-entrant call",  method_name, subclass.0.name, superclass_id);
+        let method_desc = format!(
+            "Rust subclass API (method {} of subclass {} of superclass {})",
+            method_name, subclass.0.name, superclass_id
+        );
+        let get_rust_object = if details.thread_safe {
+            quote! {
+                let arc = me.0.get_thread_safe()?;
+                let #mut_token b = arc
+                    .lock()
+                    .map_err(|_| autocxx::subclass::CppSubclassCallError::Poisoned)?;
+            }
+        } else {
+            quote! {
+                let rc = me.0.get()?;
+                let #mut_token b = rc
+                    .as_ref()
+                    .#borrow()
+                    .map_err(|_| autocxx::subclass::CppSubclassCallError::AlreadyBorrowed)?;
+            }
+        };
+        let handle_error = match details.error_policy {
+            SubclassErrorPolicy::Abort => quote! {
+                .unwrap_or_else(|err| autocxx::subclass::abort_subclass_call(#method_desc, err))
+            },
+            SubclassErrorPolicy::ReturnDefault => quote! {
+                .unwrap_or_else(|err| autocxx::subclass::default_subclass_call(#method_desc, err))
+            },
+            SubclassErrorPolicy::Exception => quote! {
+                .map_err(|err| autocxx::subclass::subclass_call_exception(#method_desc, err))
+            },
+        };
+        let global_item = parse_quote! {
+            #global_def {
+                autocxx::subclass::call_subclass_method(|| {
+                    #get_rust_object
+                    let r = std::ops::#deref_ty::#deref_call(& #mut_token b);
+                    Ok(#methods_trait :: #method_name
+                        (r,
+                        #args))
+                })
+                #handle_error
+            }
+        };
         RsCodegenResult {
             extern_c_mod_items: Vec::new(),
             bridge_items: Vec::new(),
             bindgen_mod_items: Vec::new(),
             materializations: Vec::new(),
-            global_items: vec![parse_quote! {
-                #global_def {
-                    let rc = me.0
-                        .get()
-                        .expect(#destroy_panic_msg);
-                    let #mut_token b = rc
-                        .as_ref()
-                        .#borrow()
-                        .expect(#reentrancy_panic_msg);
-                    let r = std::ops::#deref_ty::#deref_call(& #mut_token b);
-                    #methods_trait :: #method_name
-                        (r,
-                        #args)
-                }
-            }],
+            global_items: vec![global_item],
             impl_entry: None,
             extern_rust_mod_items: vec![ForeignItem::Fn(cxxbridge_decl)],
         }
@@ -909,24 +1330,29 @@ impl<'a> RsCodeGenerator<'a> {
                     if method.is_pure_virtual {
                         (
                             None,
-                            parse_quote!(
+                            Some(parse_quote!(
                                 #unsafe_token fn #id(#params) #ret_type;
-                            ),
+                            )),
                         )
                     } else {
                         let a: Option<TraitItem> = Some(parse_quote!(
                             #unsafe_token fn #super_id(#params) #ret_type;
                         ));
-                        let b: TraitItem = parse_quote!(
-                            #unsafe_token fn #id(#params) #ret_type {
-                                self.#super_id(#param_names)
-                            }
-                        );
+                        let b: Option<TraitItem> = if method.is_virtual {
+                            Some(parse_quote!(
+                                #unsafe_token fn #id(#params) #ret_type {
+                                    self.#super_id(#param_names)
+                                }
+                            ))
+                        } else {
+                            None
+                        };
                         (a, b)
                     }
                 })
                 .unzip();
             let supers: Vec<_> = supers.into_iter().flatten().collect();
+            let mains: Vec<_> = mains.into_iter().flatten().collect();
             let supers_name = SubclassName::get_supers_trait_name(name).get_final_ident();
             let methods_name = SubclassName::get_methods_trait_name(name).get_final_ident();
             if !supers.is_empty() {
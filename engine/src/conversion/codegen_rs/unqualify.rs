@@ -67,6 +67,10 @@ fn unqualify_type(typ: Type) -> Type {
             typeptr.elem = unqualify_boxed_type(typeptr.elem);
             Type::Ptr(typeptr)
         }
+        Type::Array(mut typearr) => {
+            typearr.elem = unqualify_boxed_type(typearr.elem);
+            Type::Array(typearr)
+        }
         _ => typ,
     }
 }
@@ -14,6 +14,7 @@
 
 use std::fmt::Display;
 
+use itertools::Itertools;
 use syn::Ident;
 
 use crate::types::{Namespace, QualifiedName};
@@ -21,7 +22,7 @@ use crate::types::{Namespace, QualifiedName};
 #[derive(Debug, Clone)]
 pub enum ConvertError {
     NoContent,
-    UnsafePodType(String),
+    UnsafePodType(String, String),
     UnexpectedForeignItem,
     UnexpectedOuterItem,
     UnexpectedItemInMod,
@@ -44,7 +45,7 @@ pub enum ConvertError {
     UnusedTemplateParam,
     TooManyUnderscores,
     UnknownDependentType(QualifiedName),
-    IgnoredDependent,
+    IgnoredDependent(Vec<QualifiedName>, Box<ConvertError>),
     MoveConstructorUnsupported,
     ReservedName(String),
     DuplicateCxxBridgeName,
@@ -55,6 +56,9 @@ pub enum ConvertError {
     NonPublicNestedType,
     RValueParam,
     RValueReturn,
+    StdFunctionInUnsupportedPosition,
+    Variadic,
+    StrictModeSkipped(Vec<(String, String)>),
 }
 
 fn format_maybe_identifier(id: &Option<Ident>) -> String {
@@ -68,7 +72,7 @@ impl Display for ConvertError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ConvertError::NoContent => write!(f, "The initial run of 'bindgen' did not generate any content. This might be because none of the requested items for generation could be converted.")?,
-            ConvertError::UnsafePodType(err) => write!(f, "An item was requested using 'generate_pod' which was not safe to hold by value in Rust. {}", err)?,
+            ConvertError::UnsafePodType(item, err) => write!(f, "An item ({}) was requested using 'generate_pod' which was not safe to hold by value in Rust. {}", item, err)?,
             ConvertError::UnexpectedForeignItem => write!(f, "Bindgen generated some unexpected code in a foreign mod section. You may have specified something in a 'generate' directive which is not currently compatible with autocxx.")?,
             ConvertError::UnexpectedOuterItem => write!(f, "Bindgen generated some unexpected code in its outermost mod section. You may have specified something in a 'generate' directive which is not currently compatible with autocxx.")?,
             ConvertError::UnexpectedItemInMod => write!(f, "Bindgen generated some unexpected code in an inner namespace mod. You may have specified something in a 'generate' directive which is not currently compatible with autocxx.")?,
@@ -91,7 +95,7 @@ impl Display for ConvertError {
             ConvertError::UnusedTemplateParam => write!(f, "This function or method uses a type where one of the template parameters was incomprehensible to bindgen/autocxx - probably because it uses template specialization.")?,
             ConvertError::TooManyUnderscores => write!(f, "Names containing __ are reserved by C++ so not acceptable to cxx")?,
             ConvertError::UnknownDependentType(qn) => write!(f, "This item relies on a type not known to autocxx ({})", qn.to_cpp_name())?,
-            ConvertError::IgnoredDependent => write!(f, "This item depends on some other type which autocxx could not generate.")?,
+            ConvertError::IgnoredDependent(chain, reason) => write!(f, "This item depends on some other type which autocxx could not generate. Dependency chain: {}. Reason for the last of these: {}", chain.iter().map(|qn| qn.to_cpp_name()).join(" -> "), reason)?,
             ConvertError::MoveConstructorUnsupported => write!(f, "This is a move constructor, for which we currently cannot generate bindings.")?,
             ConvertError::ReservedName(id) => write!(f, "The item name '{}' is a reserved word in Rust.", id)?,
             ConvertError::DuplicateCxxBridgeName => write!(f, "This item name is used in multiple namespaces. At present, autocxx and cxx allow only one type of a given name. This limitation will be fixed in future.")?,
@@ -102,11 +106,31 @@ impl Display for ConvertError {
             ConvertError::NonPublicNestedType => write!(f, "This type is nested within another struct/class with protected or private visibility.")?,
             ConvertError::RValueParam => write!(f, "This function takes an rvalue reference parameter (&&) which is not yet supported.")?,
             ConvertError::RValueReturn => write!(f, "This function returns an rvalue reference (&&) which is not yet supported.")?,
+            ConvertError::StdFunctionInUnsupportedPosition => write!(f, "A std::function was found somewhere other than a by-value or const reference function parameter, or its signature involves pointers or other unsupported types. Only std::function parameters are currently supported.")?,
+            ConvertError::Variadic => write!(f, "This function is variadic. Use variadic!(...) to request specific instantiations of it, each with a fixed list of extra parameter types.")?,
+            ConvertError::StrictModeSkipped(skipped) => {
+                write!(f, "strict!() was specified, but bindings could not be generated for the following items requested using generate! or generate_pod!, or their methods. Use allow_skip!(\"name\") to permit any of these to be skipped.")?;
+                for (cpp_name, reason) in skipped {
+                    write!(f, "\n  {}: {}", cpp_name, reason)?;
+                }
+            }
         }
         Ok(())
     }
 }
 
+impl ConvertError {
+    /// The item named in the `include_cpp!` directive to which this
+    /// error relates, if it relates to a specific directive.
+    pub(crate) fn directive(&self) -> Option<String> {
+        match self {
+            ConvertError::DidNotGenerateAnything(directive)
+            | ConvertError::UnsafePodType(directive, _) => Some(directive.clone()),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub(crate) enum ErrorContext {
     Item(Ident),
@@ -89,10 +89,12 @@ pub(crate) fn convert_apis<FF, SF, EF, TF, A, B: 'static>(
                 name,
                 rs_definition,
                 cpp_definition,
+                container,
             } => Ok(Box::new(std::iter::once(Api::ConcreteType {
                 name,
                 rs_definition,
                 cpp_definition,
+                container,
             }))),
             Api::ForwardDeclaration { name } => {
                 Ok(Box::new(std::iter::once(Api::ForwardDeclaration { name })))
@@ -121,9 +123,13 @@ pub(crate) fn convert_apis<FF, SF, EF, TF, A, B: 'static>(
                 subclass,
                 details,
             }))),
-            Api::Subclass { name, superclass } => Ok(Box::new(std::iter::once(Api::Subclass {
+            Api::Subclass { name, superclasses } => Ok(Box::new(std::iter::once(Api::Subclass {
                 name,
-                superclass,
+                superclasses,
+            }))),
+            Api::RustClosure { name, details } => Ok(Box::new(std::iter::once(Api::RustClosure {
+                name,
+                details,
             }))),
             Api::IgnoredItem { name, err, ctx } => {
                 Ok(Box::new(std::iter::once(Api::IgnoredItem {
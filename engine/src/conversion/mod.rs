@@ -22,6 +22,7 @@ mod convert_error;
 mod doc_attr;
 mod error_reporter;
 mod parse;
+mod report;
 mod utilities;
 
 use analysis::fun::FnAnalyzer;
@@ -29,15 +30,18 @@ use autocxx_parser::IncludeCppConfig;
 pub(crate) use codegen_cpp::CppCodeGenerator;
 pub(crate) use convert_error::ConvertError;
 use itertools::Itertools;
+pub use report::ReportItem;
 use syn::{Item, ItemMod};
 
 use crate::{CppCodegenOptions, CppFilePair, UnsafePolicy};
 
 use self::{
     analysis::{
-        abstract_types::mark_types_abstract, casts::add_casts, check_names, fun::FnPhase,
+        abstract_types::mark_types_abstract, casts::add_casts, check_names,
+        containers::mark_user_containers, fun::FnPhase,
         gc::filter_apis_by_following_edges_from_allowlist, pod::analyze_pod_apis,
-        remove_ignored::filter_apis_by_ignored_dependents, tdef::convert_typedef_targets,
+        remove_ignored::filter_apis_by_ignored_dependents, strict::check_for_skipped_items,
+        tdef::convert_typedef_targets,
     },
     api::{AnalysisPhase, Api},
     codegen_rs::RsCodeGenerator,
@@ -66,7 +70,8 @@ pub(crate) struct BridgeConverter<'a> {
 /// C++ and Rust code generation output.
 pub(crate) struct CodegenResults {
     pub(crate) rs: Vec<Item>,
-    pub(crate) cpp: Option<CppFilePair>,
+    pub(crate) cpp: Vec<CppFilePair>,
+    pub(crate) report: Vec<ReportItem>,
 }
 
 impl<'a> BridgeConverter<'a> {
@@ -156,12 +161,18 @@ impl<'a> BridgeConverter<'a> {
                 // too.
                 let analyzed_apis = filter_apis_by_ignored_dependents(analyzed_apis);
                 Self::dump_apis_with_deps("removing ignored dependents", &analyzed_apis);
+                // If the user asked us to be strict, anything they asked for
+                // which we've just had to give up on is an error.
+                check_for_skipped_items(&analyzed_apis, self.config)?;
+                // Spot types we can generate Rust iterators for.
+                let analyzed_apis = mark_user_containers(analyzed_apis);
                 // We now garbage collect the ones we don't need...
                 let mut analyzed_apis =
                     filter_apis_by_following_edges_from_allowlist(analyzed_apis, self.config);
                 // Determine what variably-sized C types (e.g. int) we need to include
                 analysis::ctypes::append_ctype_information(&mut analyzed_apis);
                 Self::dump_apis_with_deps("GC", &analyzed_apis);
+                let report = report::make_report(&analyzed_apis);
                 // And finally pass them to the code gen phases, which outputs
                 // code suitable for cxx to consume.
                 let cpp = CppCodeGenerator::generate_cpp_code(
@@ -176,7 +187,7 @@ impl<'a> BridgeConverter<'a> {
                     bindgen_mod,
                     self.config,
                 );
-                Ok(CodegenResults { rs, cpp })
+                Ok(CodegenResults { rs, cpp, report })
             }
         }
     }
@@ -33,6 +33,7 @@ use crate::{
     types::validate_ident_ok_for_cxx,
 };
 use autocxx_parser::IncludeCppConfig;
+use itertools::Itertools;
 use syn::{parse_quote, Attribute, Fields, Ident, Item, LitStr, TypePath, UseTree};
 
 use super::super::utilities::generate_utilities;
@@ -141,11 +142,33 @@ impl<'a> ParseBindgen<'a> {
     /// Some API items are not populated from bindgen output, but instead
     /// directly from items in the config.
     fn add_apis_from_config(&mut self) {
-        self.apis
-            .extend(self.config.subclasses.iter().map(|sc| Api::Subclass {
-                name: SubclassName::new(sc.subclass.clone()),
-                superclass: QualifiedName::new_from_cpp_name(&sc.superclass),
-            }));
+        // A subclass may be declared several times, once for each of
+        // its superclasses.
+        let subclasses = self
+            .config
+            .subclasses
+            .iter()
+            .map(|sc| &sc.subclass)
+            .unique();
+        self.apis.extend(subclasses.map(|subclass| {
+            Api::Subclass {
+                name: SubclassName::new(
+                    subclass.clone(),
+                    self.config
+                        .get_subclass_cpp_namespace(&subclass.to_string())
+                        .map(Namespace::from_user_input)
+                        .unwrap_or_else(Namespace::new),
+                ),
+                superclasses: self
+                    .config
+                    .subclasses
+                    .iter()
+                    .filter(|sc| &sc.subclass == subclass)
+                    .map(|sc| QualifiedName::new_from_cpp_name(&sc.superclass))
+                    .unique()
+                    .collect(),
+            }
+        }));
         self.apis
             .extend(self.config.extern_rust_funs.iter().map(|fun| {
                 let id = fun.sig.ident.clone();
@@ -102,6 +102,7 @@ impl ParseForeignMod {
                     original_name,
                     synthesized_this_type: None,
                     synthesis: None,
+                    variadic: item.sig.variadic.is_some(),
                 });
                 Ok(())
             }
@@ -0,0 +1,162 @@
+// Copyright 2021 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde_json::json;
+
+use crate::types::{make_ident, QualifiedName};
+
+use super::{
+    analysis::fun::{FnKind, FnPhase},
+    api::{Api, TypeKind},
+    convert_error::ErrorContext,
+    ConvertError,
+};
+
+/// What happened to one API which autocxx considered: whether bindings
+/// were generated for it, and if not, why not. Intended for tools which
+/// track binding coverage; see `Builder::report` or `autocxx-gen --report`.
+#[derive(Debug, Clone)]
+pub struct ReportItem {
+    /// The fully-qualified C++ name.
+    pub cpp_name: String,
+    /// The name within the generated Rust mod, if generated.
+    pub rust_name: Option<String>,
+    /// What sort of thing this is, e.g. `Struct` or `Method`.
+    pub kind: String,
+    pub generated: bool,
+    /// Why bindings weren't generated, if they weren't.
+    pub reason: Option<String>,
+    /// If bindings weren't generated because this depends on something
+    /// else which couldn't be generated, the chain of dependencies leading
+    /// to that thing, ending with it.
+    pub dependency_chain: Vec<String>,
+    /// For structs, `pod`, `non_pod` or `abstract`.
+    pub type_kind: Option<String>,
+    /// For structs which aren't POD, why they couldn't be made POD if
+    /// requested. `None` means it'd be safe to `generate_pod!` them.
+    pub non_pod_reason: Option<String>,
+    /// Whether this is a function which autocxx created, rather than
+    /// one found in the C++, e.g. `make_unique`.
+    pub synthesized: bool,
+}
+
+impl ReportItem {
+    /// This item as JSON, noting the `include_cpp!` mod in which it
+    /// was found.
+    pub fn to_json(&self, mod_name: &str) -> serde_json::Value {
+        json!({
+            "mod": mod_name,
+            "cpp_name": self.cpp_name,
+            "rust_name": self.rust_name,
+            "kind": self.kind,
+            "generated": self.generated,
+            "reason": self.reason,
+            "dependency_chain": self.dependency_chain,
+            "type_kind": self.type_kind,
+            "non_pod_reason": self.non_pod_reason,
+            "synthesized": self.synthesized,
+        })
+    }
+}
+
+/// Reports on the final set of APIs, after all analysis and garbage
+/// collection.
+pub(crate) fn make_report(apis: &[Api<FnPhase>]) -> Vec<ReportItem> {
+    apis.iter().map(report_item).collect()
+}
+
+fn report_item(api: &Api<FnPhase>) -> ReportItem {
+    let qualified_cpp_name = || {
+        QualifiedName::new(
+            api.name().get_namespace(),
+            make_ident(api.effective_cpp_name()),
+        )
+        .to_cpp_name()
+    };
+    match api {
+        Api::IgnoredItem { err, ctx, .. } => {
+            let (cpp_name, _) = api.ignored_item_names().unwrap();
+            let kind = match ctx {
+                ErrorContext::Item(_) => "Item",
+                ErrorContext::Method { .. } => "Method",
+            };
+            ReportItem {
+                cpp_name,
+                rust_name: None,
+                kind: kind.to_string(),
+                generated: false,
+                reason: Some(err.to_string()),
+                dependency_chain: match err {
+                    ConvertError::IgnoredDependent(chain, _) => {
+                        chain.iter().map(QualifiedName::to_cpp_name).collect()
+                    }
+                    _ => Vec::new(),
+                },
+                type_kind: None,
+                non_pod_reason: None,
+                synthesized: false,
+            }
+        }
+        Api::Function { fun, analysis, .. } => {
+            let (cpp_name, kind) = match &analysis.kind {
+                FnKind::Method(self_ty, _) => (
+                    format!("{}::{}", self_ty.to_cpp_name(), api.effective_cpp_name()),
+                    "Method",
+                ),
+                FnKind::TraitMethod { .. } => (qualified_cpp_name(), "TraitMethod"),
+                FnKind::Function => (qualified_cpp_name(), "Function"),
+            };
+            ReportItem {
+                cpp_name,
+                rust_name: Some(analysis.rust_name.clone()),
+                kind: kind.to_string(),
+                generated: analysis.generate_code,
+                reason: None,
+                dependency_chain: Vec::new(),
+                type_kind: None,
+                non_pod_reason: None,
+                synthesized: fun.synthesis.is_some(),
+            }
+        }
+        Api::Struct { analysis, .. } => {
+            let (type_kind, non_pod_reason) = match analysis.kind {
+                TypeKind::Pod => ("pod", None),
+                TypeKind::NonPod => ("non_pod", analysis.pod_unsafety.clone()),
+                TypeKind::Abstract => ("abstract", analysis.pod_unsafety.clone()),
+            };
+            ReportItem {
+                cpp_name: qualified_cpp_name(),
+                rust_name: Some(api.name().get_final_item().to_string()),
+                kind: api.to_string(),
+                generated: true,
+                reason: None,
+                dependency_chain: Vec::new(),
+                type_kind: Some(type_kind.to_string()),
+                non_pod_reason,
+                synthesized: false,
+            }
+        }
+        _ => ReportItem {
+            cpp_name: qualified_cpp_name(),
+            rust_name: Some(api.name().get_final_item().to_string()),
+            kind: api.to_string(),
+            generated: true,
+            reason: None,
+            dependency_chain: Vec::new(),
+            type_kind: None,
+            non_pod_reason: None,
+            synthesized: false,
+        },
+    }
+}
@@ -0,0 +1,293 @@
+// Copyright 2021 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Support for "frozen" bindings: generated Rust and C++ which has been
+//! written out ahead of time by `autocxx-gen --freeze`, such that a build
+//! can subsequently proceed without libclang. Alongside the generated
+//! code we store a [`Fingerprint`] of everything which went into it, so
+//! that we can tell when the frozen code has become stale.
+
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+const FINGERPRINT_FILE: &str = "autocxx-fingerprint.txt";
+
+/// A directory containing frozen bindings. Its layout mirrors that
+/// of the directories used by the `Builder`: generated Rust goes in `rs`,
+/// headers in `include` and C++ implementation files in `cxx`.
+pub struct FrozenBindings(PathBuf);
+
+impl FrozenBindings {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self(dir.as_ref().to_path_buf())
+    }
+
+    /// Where the generated Rust lives. Point `AUTOCXX_RS` at this
+    /// directory to have `include_cpp!` use it directly.
+    pub fn rs_dir(&self) -> PathBuf {
+        self.0.join("rs")
+    }
+
+    pub fn include_dir(&self) -> PathBuf {
+        self.0.join("include")
+    }
+
+    pub fn cxx_dir(&self) -> PathBuf {
+        self.0.join("cxx")
+    }
+
+    /// Removes any previously frozen code and recreates empty `rs`,
+    /// `include` and `cxx` directories. The `Builder` compiles every file
+    /// it finds here, so leftovers from an earlier freeze (for example of a
+    /// file with more `include_cpp!` macros) must not survive.
+    pub fn clear(&self) -> std::io::Result<()> {
+        for dir in [self.rs_dir(), self.include_dir(), self.cxx_dir()] {
+            match std::fs::remove_dir_all(&dir) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+            std::fs::create_dir_all(&dir)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_fingerprint(&self) -> Result<Fingerprint, String> {
+        let path = self.0.join(FINGERPRINT_FILE);
+        let mut contents = String::new();
+        File::open(&path)
+            .and_then(|mut f| f.read_to_string(&mut contents))
+            .map_err(|e| format!("unable to read {}: {}", path.to_string_lossy(), e))?;
+        contents.parse()
+    }
+
+    pub fn write_fingerprint(&self, fingerprint: &Fingerprint) -> std::io::Result<()> {
+        let mut f = File::create(self.0.join(FINGERPRINT_FILE))?;
+        f.write_all(fingerprint.to_string().as_bytes())
+    }
+}
+
+/// A record of the inputs to some generated bindings: the .rs file
+/// containing the `include_cpp!` macro (and thus the whole configuration),
+/// the clang arguments and version of autocxx, and each header file
+/// found within the include path. System headers are not recorded.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Fingerprint {
+    config: u64,
+    headers: Vec<(String, u64)>,
+}
+
+impl Fingerprint {
+    /// Calculates a fingerprint. `headers` should be the full paths
+    /// reported to a [`crate::RebuildDependencyRecorder`] during
+    /// generation; those outside `inc_dirs` are ignored.
+    pub fn calculate(
+        rs_file: &Path,
+        extra_clang_args: &[&str],
+        inc_dirs: &[PathBuf],
+        headers: &[String],
+    ) -> Result<Self, String> {
+        let mut headers: Vec<_> = headers
+            .iter()
+            .filter_map(|header| relative_to_inc_dirs(Path::new(header), inc_dirs))
+            .map(|relative| {
+                let hash = hash_header(&relative, inc_dirs)?;
+                Ok((relative, hash))
+            })
+            .collect::<Result<_, String>>()?;
+        headers.sort();
+        headers.dedup();
+        Ok(Self {
+            config: config_hash(rs_file, extra_clang_args)?,
+            headers,
+        })
+    }
+
+    /// Checks whether bindings generated with this fingerprint are still
+    /// valid for the given inputs, returning a description of the first
+    /// discrepancy if not.
+    pub fn check(
+        &self,
+        rs_file: &Path,
+        extra_clang_args: &[&str],
+        inc_dirs: &[PathBuf],
+    ) -> Result<(), String> {
+        if config_hash(rs_file, extra_clang_args)? != self.config {
+            return Err(format!(
+                "{}, the clang arguments or the version of autocxx have changed",
+                rs_file.to_string_lossy()
+            ));
+        }
+        for (header, hash) in &self.headers {
+            if hash_header(header, inc_dirs)? != *hash {
+                return Err(format!("header {} has changed", header));
+            }
+        }
+        Ok(())
+    }
+
+    /// The full paths of the headers recorded in this fingerprint.
+    pub fn header_paths(&self, inc_dirs: &[PathBuf]) -> Vec<PathBuf> {
+        self.headers
+            .iter()
+            .filter_map(|(header, _)| find_header(header, inc_dirs))
+            .collect()
+    }
+}
+
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "config {:016x}", self.config)?;
+        for (header, hash) in &self.headers {
+            writeln!(f, "{:016x} {}", hash, header)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_hash =
+            |hash: &str| u64::from_str_radix(hash, 16).map_err(|_| "malformed fingerprint");
+        let mut lines = s.lines();
+        let config = lines
+            .next()
+            .and_then(|line| line.strip_prefix("config "))
+            .ok_or("malformed fingerprint")
+            .and_then(parse_hash)?;
+        let headers = lines
+            .map(|line| {
+                let (hash, header) = line.split_once(' ').ok_or("malformed fingerprint")?;
+                Ok((header.to_string(), parse_hash(hash)?))
+            })
+            .collect::<Result<_, &str>>()?;
+        Ok(Self { config, headers })
+    }
+}
+
+fn relative_to_inc_dirs(header: &Path, inc_dirs: &[PathBuf]) -> Option<String> {
+    let header = header.canonicalize().ok()?;
+    inc_dirs.iter().find_map(|inc_dir| {
+        let inc_dir = inc_dir.canonicalize().ok()?;
+        header
+            .strip_prefix(inc_dir)
+            .ok()
+            .map(|relative| relative.to_string_lossy().into_owned())
+    })
+}
+
+fn find_header(relative: &str, inc_dirs: &[PathBuf]) -> Option<PathBuf> {
+    inc_dirs
+        .iter()
+        .map(|inc_dir| inc_dir.join(relative))
+        .find(|path| path.exists())
+}
+
+fn hash_header(relative: &str, inc_dirs: &[PathBuf]) -> Result<u64, String> {
+    let path = find_header(relative, inc_dirs)
+        .ok_or_else(|| format!("header {} no longer exists", relative))?;
+    hash_file(&path)
+}
+
+fn config_hash(rs_file: &Path, extra_clang_args: &[&str]) -> Result<u64, String> {
+    let mut hasher = Fnv1a::default();
+    hasher.write(env!("CARGO_PKG_VERSION").as_bytes());
+    for arg in extra_clang_args {
+        hasher.write(&[0]);
+        hasher.write(arg.as_bytes());
+    }
+    hasher.write(&[0]);
+    hasher.write(&hash_file(rs_file)?.to_le_bytes());
+    Ok(hasher.0)
+}
+
+fn hash_file(path: &Path) -> Result<u64, String> {
+    let mut contents = Vec::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut contents))
+        .map_err(|e| format!("unable to read {}: {}", path.to_string_lossy(), e))?;
+    let mut hasher = Fnv1a::default();
+    hasher.write(&contents);
+    Ok(hasher.0)
+}
+
+/// 64-bit FNV-1a. Unlike the standard library's hashers, this is
+/// guaranteed to be stable across Rust versions and platforms, which
+/// matters because fingerprints (and cache keys) are stored on disk.
+pub(crate) struct Fnv1a(pub(crate) u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Fnv1a {
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fingerprint;
+    use std::{fs::File, io::Write};
+
+    #[test]
+    fn test_fingerprint() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let inc_dir = tmp_dir.path().join("include");
+        std::fs::create_dir(&inc_dir).unwrap();
+        let header = inc_dir.join("input.h");
+        let rs_file = tmp_dir.path().join("main.rs");
+        File::create(&header).unwrap().write_all(b"int a;").unwrap();
+        File::create(&rs_file)
+            .unwrap()
+            .write_all(b"fn main() {}")
+            .unwrap();
+        let inc_dirs = vec![inc_dir];
+        let fingerprint = Fingerprint::calculate(
+            &rs_file,
+            &["-std=c++17"],
+            &inc_dirs,
+            &[
+                header.to_string_lossy().into_owned(),
+                "/usr/include/stdio.h".to_string(),
+            ],
+        )
+        .unwrap();
+        let round_tripped: Fingerprint = fingerprint.to_string().parse().unwrap();
+        assert_eq!(round_tripped, fingerprint);
+        assert_eq!(fingerprint.headers.len(), 1);
+        assert!(fingerprint
+            .check(&rs_file, &["-std=c++17"], &inc_dirs)
+            .is_ok());
+        assert!(fingerprint
+            .check(&rs_file, &["-std=c++14"], &inc_dirs)
+            .is_err());
+        File::create(&header).unwrap().write_all(b"int b;").unwrap();
+        assert!(fingerprint
+            .check(&rs_file, &["-std=c++17"], &inc_dirs)
+            .is_err());
+    }
+}
@@ -34,6 +34,7 @@ enum Behavior {
     CVariableLengthByValue,
     CVoid,
     RustContainerByValueSafe,
+    CxxFunction,
 }
 
 /// Details about known special types, mostly primitives.
@@ -72,13 +73,15 @@ impl TypeDetails {
             | Behavior::CxxString
             | Behavior::CxxContainerByValueSafe
             | Behavior::CxxContainerNotByValueSafe
-            | Behavior::RustContainerByValueSafe => {
+            | Behavior::RustContainerByValueSafe
+            | Behavior::CxxFunction => {
                 let tn = QualifiedName::new_from_cpp_name(&self.rs_name);
                 let cxx_name = tn.get_final_item();
                 let (templating, payload) = match self.behavior {
                     Behavior::CxxContainerByValueSafe
                     | Behavior::CxxContainerNotByValueSafe
-                    | Behavior::RustContainerByValueSafe => ("template<typename T> ", "T* ptr"),
+                    | Behavior::RustContainerByValueSafe
+                    | Behavior::CxxFunction => ("template<typename T> ", "T* ptr"),
                     _ => ("", "char* ptr"),
                 };
                 Some(format!(
@@ -153,7 +156,33 @@ pub enum CxxGenericType {
     Rust,
 }
 
+/// The kind of a C++ container for which we generate iterators.
+#[derive(PartialEq, Clone, Copy)]
+pub(crate) enum ContainerKind {
+    /// Contains single values, e.g. `std::set<T>`.
+    Values,
+    /// Maps keys to values, e.g. `std::map<K, V>`.
+    Map,
+}
+
 impl TypeDatabase {
+    /// Whether this is a C++ standard library container for which
+    /// we can generate Rust iterators and accessors.
+    pub(crate) fn container_kind(&self, ty: &QualifiedName) -> Option<ContainerKind> {
+        // Ignore inline namespaces such as libc++'s `std::__1`.
+        let segments: Vec<_> = ty
+            .ns_segment_iter()
+            .filter(|seg| !seg.starts_with("__"))
+            .map(|seg| seg.as_str())
+            .chain(std::iter::once(ty.get_final_item()))
+            .collect();
+        match segments.as_slice() {
+            ["std", "set" | "unordered_set" | "list" | "deque"] => Some(ContainerKind::Values),
+            ["std", "map" | "unordered_map"] => Some(ContainerKind::Map),
+            _ => None,
+        }
+    }
+
     fn get(&self, ty: &QualifiedName) -> Option<&TypeDetails> {
         // The following line is important. It says that
         // when we encounter something like 'std::unique_ptr'
@@ -200,6 +229,7 @@ impl TypeDatabase {
                         | Behavior::RustContainerByValueSafe => true,
                         Behavior::CxxString
                         | Behavior::CxxContainerNotByValueSafe
+                        | Behavior::CxxFunction
                         | Behavior::CVoid => false,
                     },
                 )
@@ -277,6 +307,14 @@ impl TypeDatabase {
         self.get(ty).is_some()
     }
 
+    /// Whether this is a `std::function`, which we represent to Rust
+    /// as a boxed closure.
+    pub(crate) fn is_std_function(&self, ty: &QualifiedName) -> bool {
+        self.get(ty)
+            .map(|x| matches!(x.behavior, Behavior::CxxFunction))
+            .unwrap_or(false)
+    }
+
     pub(crate) fn convertible_from_strs(&self, ty: &QualifiedName) -> bool {
         self.get(ty)
             .map(|x| matches!(x.behavior, Behavior::CxxString))
@@ -325,6 +363,12 @@ fn create_type_database() -> TypeDatabase {
         Behavior::CxxContainerByValueSafe,
         None,
     ));
+    db.insert(TypeDetails::new(
+        "autocxx::StdFunction",
+        "std::function",
+        Behavior::CxxFunction,
+        None,
+    ));
     db.insert(TypeDetails::new(
         "cxx::CxxString",
         "std::string",
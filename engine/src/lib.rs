@@ -21,9 +21,12 @@
 #![cfg_attr(feature = "nightly", feature(doc_cfg))]
 
 mod ast_discoverer;
+mod compile_commands;
 mod conversion;
 mod cxxbridge;
+mod frozen;
 mod known_types;
+mod listing;
 mod parse_callbacks;
 mod parse_file;
 mod rust_pretty_printer;
@@ -31,9 +34,13 @@ mod types;
 
 #[cfg(any(test, feature = "build"))]
 mod builder;
+#[cfg(any(test, feature = "build"))]
+mod cache;
 
 use autocxx_parser::{IncludeCppConfig, UnsafePolicy};
 use conversion::BridgeConverter;
+pub use conversion::ReportItem;
+use frozen::Fnv1a;
 use parse_callbacks::AutocxxParseCallbacks;
 use parse_file::CppBuildable;
 use proc_macro2::TokenStream as TokenStream2;
@@ -42,7 +49,7 @@ use std::{
     fs::File,
     io::prelude::*,
     path::Path,
-    process::{Command, Stdio},
+    process::{Command, Output, Stdio},
 };
 use tempfile::NamedTempFile;
 
@@ -65,6 +72,9 @@ use autocxx_bindgen as bindgen;
 pub use builder::{
     Builder, BuilderBuild, BuilderContext, BuilderError, BuilderResult, BuilderSuccess,
 };
+pub use compile_commands::{CompileCommandFlags, CompileCommandsError};
+pub use frozen::{Fingerprint, FrozenBindings};
+pub use listing::ListedItem;
 pub use parse_file::{parse_file, ParseError, ParsedFile};
 
 pub use cxx_gen::HEADER;
@@ -86,6 +96,11 @@ pub struct CppFilePair {
     /// The name which should be used for the header file
     /// (important as it may be `#include`d elsewhere)
     pub header_name: String,
+    /// Whether this is only ever a header, like those generated for the
+    /// C++ peers of Rust subclasses. Other pairs may lack an
+    /// implementation too, but still count towards the numbering of
+    /// `autocxx-gen`'s .cc files.
+    pub header_only: bool,
 }
 
 /// All generated C++ content which should be written to disk.
@@ -119,12 +134,24 @@ impl Display for Error {
     }
 }
 
+impl Error {
+    /// The item named in the `include_cpp!` directive to which this
+    /// error relates, if any; see [`autocxx_parser::DirectiveError`].
+    pub fn directive(&self) -> Option<String> {
+        match self {
+            Error::Conversion(err) => err.directive(),
+            _ => None,
+        }
+    }
+}
+
 /// Result type.
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 struct GenerationResults {
     item_mod: ItemMod,
-    cpp: Option<CppFilePair>,
+    cpp: Vec<CppFilePair>,
+    report: Vec<ReportItem>,
     inc_dirs: Vec<PathBuf>,
 }
 enum State {
@@ -133,8 +160,70 @@ enum State {
     Generated(Box<GenerationResults>),
 }
 
+// The `-std` here should match `CppStandard::default()`.
 const AUTOCXX_CLANG_ARGS: &[&str; 4] = &["-x", "c++", "-std=c++14", "-DBINDGEN"];
 
+/// A C++ language standard, used both when parsing C++ headers and
+/// when building the C++ side of the generated bindings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CppStandard {
+    Cpp11,
+    Cpp14,
+    Cpp17,
+    Cpp20,
+}
+
+#[allow(clippy::derivable_impls)] // nightly-only
+impl Default for CppStandard {
+    fn default() -> Self {
+        CppStandard::Cpp14
+    }
+}
+
+impl CppStandard {
+    /// The name of this standard as understood by `-std=`, e.g. `c++17`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CppStandard::Cpp11 => "c++11",
+            CppStandard::Cpp14 => "c++14",
+            CppStandard::Cpp17 => "c++17",
+            CppStandard::Cpp20 => "c++20",
+        }
+    }
+
+    /// The argument to pass to clang (or gcc) to select this standard.
+    pub fn clang_arg(&self) -> String {
+        format!("-std={}", self.name())
+    }
+
+    /// The argument to pass to MSVC to select this standard. MSVC has no
+    /// C++11 mode, so C++14 is the closest it can do.
+    pub fn msvc_arg(&self) -> &'static str {
+        match self {
+            CppStandard::Cpp11 | CppStandard::Cpp14 => "/std:c++14",
+            CppStandard::Cpp17 => "/std:c++17",
+            CppStandard::Cpp20 => "/std:c++20",
+        }
+    }
+}
+
+impl std::str::FromStr for CppStandard {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "c++11" => Ok(CppStandard::Cpp11),
+            "c++14" => Ok(CppStandard::Cpp14),
+            "c++17" => Ok(CppStandard::Cpp17),
+            "c++20" => Ok(CppStandard::Cpp20),
+            _ => Err(format!(
+                "unknown C++ standard {}: expected c++11, c++14, c++17 or c++20",
+                s
+            )),
+        }
+    }
+}
+
 /// Implement to learn of header files which get included
 /// by this build process, such that your build system can choose
 /// to rerun the build process if any such file changes in future.
@@ -339,6 +428,46 @@ impl IncludeCppEngine {
         }
     }
 
+    /// What happened to each API considered during generation. Empty
+    /// until `generate` has been called.
+    pub fn get_report(&self) -> &[ReportItem] {
+        match &self.state {
+            State::Generated(gen_results) => &gen_results.report,
+            _ => &[],
+        }
+    }
+
+    /// Each item found in the headers during `generate`, with the header
+    /// declaring it and whether bindings could be generated for it; see
+    /// [`ListedItem`]. Items from headers outside the include directories,
+    /// such as system headers, are omitted. Most useful if the config
+    /// specifies `generate_all!()`.
+    pub fn list_items(&self, extra_clang_args: &[&str]) -> Vec<ListedItem> {
+        let inc_dirs: Vec<_> = match &self.state {
+            State::Generated(_) => self.include_dirs().cloned().collect(),
+            _ => return Vec::new(),
+        };
+        // Macro definitions are included so that we can find constants.
+        let preprocessor_args: Vec<_> = extra_clang_args
+            .iter()
+            .copied()
+            .chain(std::iter::once("-dD"))
+            .collect();
+        // If preprocessing fails, we still list everything, just without
+        // knowing where it came from.
+        let preprocessed = NamedTempFile::new()
+            .ok()
+            .and_then(|mut tf| {
+                write!(tf, "{}", self.build_header()).ok()?;
+                let tp = tf.into_temp_path();
+                run_preprocessor(&tp, &inc_dirs, &preprocessor_args).ok()
+            })
+            .filter(|result| result.status.success())
+            .map(|result| String::from_utf8_lossy(&result.stdout).into_owned())
+            .unwrap_or_default();
+        listing::list_items(self.get_report(), &preprocessed, &inc_dirs)
+    }
+
     /// Returns the name of the mod which this `include_cpp!` will generate.
     /// Can and should be used to ensure multiple mods in a file don't conflict.
     pub fn get_mod_name(&self) -> String {
@@ -419,6 +548,7 @@ impl IncludeCppEngine {
         self.state = State::Generated(Box::new(GenerationResults {
             item_mod: new_bindings,
             cpp: conversion.cpp,
+            report: conversion.report,
             inc_dirs,
         }));
         Ok(())
@@ -467,6 +597,42 @@ impl IncludeCppEngine {
         }
     }
 
+    /// Hashes everything which determines the results of [`Self::generate`]
+    /// for this `include_cpp!`: its configuration, the preprocessed headers
+    /// and the arguments with which clang would be run. Preprocessing is
+    /// much cheaper than running bindgen, so this can be used to decide
+    /// whether generation can be skipped. Returns `None` if the headers
+    /// couldn't be preprocessed.
+    pub(crate) fn hash_inputs(
+        &self,
+        hasher: &mut Fnv1a,
+        inc_dirs: &[PathBuf],
+        extra_clang_args: &[&str],
+    ) -> Option<()> {
+        hasher.write(format!("{:?}", self.config).as_bytes());
+        for arg in make_clang_args(inc_dirs, extra_clang_args) {
+            hasher.write(&[0]);
+            hasher.write(arg.as_bytes());
+        }
+        let mut tf = NamedTempFile::new().ok()?;
+        write!(tf, "{}", self.build_header()).ok()?;
+        let tp = tf.into_temp_path();
+        // Without linemarkers, which would name the temporary file and
+        // so differ on every build.
+        let preprocessor_args: Vec<_> = extra_clang_args
+            .iter()
+            .copied()
+            .chain(std::iter::once("-P"))
+            .collect();
+        let result = run_preprocessor(&tp, inc_dirs, &preprocessor_args).ok()?;
+        if !result.status.success() {
+            return None;
+        }
+        hasher.write(&[0]);
+        hasher.write(&result.stdout);
+        Some(())
+    }
+
     fn make_preprocessed_file(
         &self,
         output_path: &Path,
@@ -534,11 +700,12 @@ pub fn do_cxx_cpp_generation(
             cxx_generated.header,
             cpp_codegen_options.suppress_system_headers,
         ),
-        header_name: "cxxgen.h".into(),
+        header_name: CXXGEN_H.into(),
         implementation: Some(strip_system_headers(
             cxx_generated.implementation,
             cpp_codegen_options.suppress_system_headers,
         )),
+        header_only: false,
     })
 }
 
@@ -569,9 +736,7 @@ impl CppBuildable for IncludeCppEngine {
             State::Generated(gen_results) => {
                 let rs = gen_results.item_mod.to_token_stream();
                 files.push(do_cxx_cpp_generation(rs, cpp_codegen_options)?);
-                if let Some(cpp_file_pair) = &gen_results.cpp {
-                    files.push(cpp_file_pair.clone());
-                }
+                files.extend(gen_results.cpp.iter().cloned());
             }
         };
         Ok(GeneratedCpp(files))
@@ -593,6 +758,32 @@ pub fn make_clang_args<'a>(
         .chain(extra_args.iter().map(|s| s.to_string()))
 }
 
+/// Converts a Rust target triple (as found in Cargo's `TARGET`) to the
+/// equivalent triple for clang, where they differ.
+pub fn rust_to_clang_target(rust_target: &str) -> String {
+    let (arch, rest) = rust_target.split_once('-').unwrap_or((rust_target, ""));
+    let arch = if arch == "aarch64" && rest.starts_with("apple-") {
+        "arm64"
+    } else if arch.starts_with("riscv") {
+        // Rust's names include the ISA extensions, e.g. riscv64gc.
+        arch.trim_end_matches(|c: char| c.is_ascii_alphabetic())
+    } else {
+        arch
+    };
+    // Rust distinguishes iOS simulator targets by suffix, or, for x86_64,
+    // only implicitly.
+    let rest = match rest.strip_suffix("-sim") {
+        Some(rest) => format!("{}-simulator", rest),
+        None if arch == "x86_64" && rest == "apple-ios" => "apple-ios-simulator".to_string(),
+        None => rest.to_string(),
+    };
+    if rest.is_empty() {
+        arch.to_string()
+    } else {
+        format!("{}-{}", arch, rest)
+    }
+}
+
 /// Preprocess a file using the same options
 /// as is used by autocxx. Input: listing_path, output: preprocess_path.
 pub fn preprocess(
@@ -601,17 +792,26 @@ pub fn preprocess(
     incs: &[PathBuf],
     extra_clang_args: &[&str],
 ) -> Result<(), std::io::Error> {
+    let result =
+        run_preprocessor(listing_path, incs, extra_clang_args).expect("failed to execute clang++");
+    assert!(result.status.success(), "failed to preprocess");
+    let mut file = File::create(preprocess_path)?;
+    file.write_all(&result.stdout)?;
+    Ok(())
+}
+
+fn run_preprocessor(
+    listing_path: &Path,
+    incs: &[PathBuf],
+    extra_clang_args: &[&str],
+) -> Result<Output, std::io::Error> {
     let mut cmd = Command::new(get_clang_path());
     cmd.arg("-E");
     cmd.arg("-C");
     cmd.args(make_clang_args(incs, extra_clang_args));
     cmd.arg(listing_path.to_str().unwrap());
     cmd.stderr(Stdio::inherit());
-    let result = cmd.output().expect("failed to execute clang++");
-    assert!(result.status.success(), "failed to preprocess");
-    let mut file = File::create(preprocess_path)?;
-    file.write_all(&result.stdout)?;
-    Ok(())
+    cmd.output()
 }
 
 /// Get the path to clang which is effective for any preprocessing
@@ -626,8 +826,11 @@ pub fn get_clang_path() -> String {
         .unwrap_or_else(|_| "clang++".to_string())
 }
 
+/// The name of the header generated by cxx for each `include_cpp!`.
+pub(crate) const CXXGEN_H: &str = "cxxgen.h";
+
 /// Options for C++ codegen
-#[derive(Default)]
+#[derive(Default, Debug, Clone)]
 pub struct CppCodegenOptions {
     /// Whether to avoid generating `#include <some-system-header>`.
     /// You may wish to do this to make a hermetic test case with no
@@ -0,0 +1,459 @@
+// Copyright 2021 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Support for `autocxx-gen list`: a catalogue of everything in some
+//! headers which might be named in a `generate!` directive, with the
+//! header in which it's declared and whether autocxx believes it can
+//! generate bindings for it.
+//!
+//! Whether it can be bound comes from the [`ReportItem`]s produced by
+//! generating everything. Bindgen doesn't tell us where each item came
+//! from, so for that we scan the preprocessed headers, whose linemarkers
+//! say which header each line came from, for declarations of each name.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    path::PathBuf,
+};
+
+use crate::ReportItem;
+
+/// Kinds of [`ReportItem`] corresponding to something which the user
+/// might ask for, rather than things autocxx made up along the way.
+const LISTED_KINDS: &[&str] = &[
+    "Struct",
+    "ForwardDeclaration",
+    "Enum",
+    "Typedef",
+    "Const",
+    "Function",
+    "Method",
+    "Item",
+];
+
+/// One item found in the headers.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ListedItem {
+    /// The fully-qualified C++ name.
+    pub cpp_name: String,
+    /// What sort of thing this is, e.g. `Struct` or `Function`.
+    pub kind: String,
+    /// The header in which this was declared, if we could tell.
+    pub header: Option<String>,
+    /// Whether we couldn't find the declaration itself, so `header` is
+    /// merely the first in which the name appears.
+    pub header_guessed: bool,
+    /// Whether autocxx believes it can generate bindings for this:
+    /// `POD-safe`, `non-POD: <why it can't be POD>`, `abstract`,
+    /// `bindable` or `unsupported: <why not>`.
+    pub bindability: String,
+}
+
+impl Display for ListedItem {
+    /// Tab-separated, one item per line. A guessed header is followed
+    /// by `?`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}{}\t{}",
+            self.kind,
+            self.cpp_name,
+            self.header.as_deref().unwrap_or("-"),
+            if self.header_guessed { "?" } else { "" },
+            self.bindability
+        )
+    }
+}
+
+/// Lists the items in a report which were declared within the given
+/// include directories, or whose header we couldn't find. Others, e.g.
+/// from system headers, are omitted.
+pub(crate) fn list_items(
+    report: &[ReportItem],
+    preprocessed: &str,
+    inc_dirs: &[PathBuf],
+) -> Vec<ListedItem> {
+    let locations = DeclarationLocations::scan(preprocessed);
+    let inc_dirs: Vec<_> = inc_dirs
+        .iter()
+        .filter_map(|inc_dir| inc_dir.canonicalize().ok())
+        .collect();
+    let local_headers: HashSet<_> = locations
+        .headers()
+        .filter(|header| {
+            inc_dirs.is_empty()
+                || PathBuf::from(header)
+                    .canonicalize()
+                    .map(|header| inc_dirs.iter().any(|inc_dir| header.starts_with(inc_dir)))
+                    .unwrap_or(true)
+        })
+        .collect();
+    let mut items: Vec<_> = report
+        .iter()
+        .filter(|item| LISTED_KINDS.contains(&item.kind.as_str()) && !item.synthesized)
+        .map(|item| {
+            let header = locations.header_for(declaring_ident(item), &local_headers);
+            ListedItem {
+                cpp_name: item.cpp_name.clone(),
+                kind: item.kind.clone(),
+                header: header.map(|(header, _)| header.to_string()),
+                header_guessed: matches!(header, Some((_, Evidence::Mention))),
+                bindability: bindability(item),
+            }
+        })
+        .filter(|item| match &item.header {
+            None => true,
+            Some(header) => local_headers.contains(header.as_str()),
+        })
+        .collect();
+    items.sort();
+    items.dedup();
+    items
+}
+
+/// The identifier whose declaration tells us where this item was
+/// declared. Methods are declared within their class, whose name is more
+/// distinctive than that of the method itself.
+fn declaring_ident(item: &ReportItem) -> &str {
+    let mut segments = item.cpp_name.rsplit("::");
+    let final_segment = segments.next().unwrap();
+    if item.kind == "Method" {
+        segments.next().unwrap_or(final_segment)
+    } else {
+        final_segment
+    }
+}
+
+fn bindability(item: &ReportItem) -> String {
+    let bindability = if !item.generated {
+        format!(
+            "unsupported: {}",
+            item.reason.as_deref().unwrap_or("not public")
+        )
+    } else if item.kind == "ForwardDeclaration" {
+        "non-POD: no definition is available".to_string()
+    } else {
+        match (item.type_kind.as_deref(), &item.non_pod_reason) {
+            (Some("abstract"), _) => "abstract".to_string(),
+            (Some(_), Some(reason)) => format!("non-POD: {}", reason),
+            (Some(_), None) => "POD-safe".to_string(),
+            (None, _) => "bindable".to_string(),
+        }
+    };
+    // Reasons may span lines, but each listed item must not.
+    bindability.replace('\n', " ")
+}
+
+/// How sure we are that a header declares an identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Evidence {
+    /// The identifier merely appears in it.
+    Mention,
+    /// It declares the identifier, e.g. `class A;` or `int f();`.
+    Declaration,
+    /// It defines the identifier, e.g. `class A {`, `using A = ...;` or a
+    /// `#define`.
+    Definition,
+}
+
+/// The headers in which each identifier appears in the preprocessed
+/// output, in the order in which they appear, and how sure we are that
+/// each declares it. We recognize only the common forms of declaration,
+/// such as `class|struct|enum|union <ident>`, `typedef`s, `using <ident>
+/// =` and functions and variables at namespace scope. Clang could tell us
+/// for sure (e.g. via `-Xclang -ast-dump=json`) but for realistic headers
+/// that dump runs to hundreds of megabytes, which isn't worth it for this.
+#[derive(Default)]
+struct DeclarationLocations {
+    headers_by_ident: HashMap<String, Vec<(String, Evidence)>>,
+}
+
+impl DeclarationLocations {
+    fn scan(preprocessed: &str) -> Self {
+        let mut locations = Self::default();
+        // `None` for clang's built-in definitions.
+        let mut header: Option<&str> = None;
+        let mut tokens = Vec::new();
+        for line in preprocessed.lines() {
+            if let Some(directive) = line.trim_start().strip_prefix('#') {
+                let directive = directive.trim_start();
+                let linemarker = directive.strip_prefix("line ").unwrap_or(directive);
+                if linemarker.starts_with(|c: char| c.is_ascii_digit()) {
+                    let filename = linemarker.split('"').nth(1).unwrap_or_default();
+                    header = Some(filename).filter(|f| !f.starts_with('<'));
+                } else if let (Some(definition), Some(header)) =
+                    (directive.strip_prefix("define "), header)
+                {
+                    // Macros become constants, so their names count too.
+                    if let Some(name) = self::tokens(definition).next() {
+                        locations.record(name, header, Evidence::Definition);
+                    }
+                }
+            } else if let Some(header) = header {
+                tokens.extend(self::tokens(line).map(|token| (header, token)));
+            }
+        }
+        // Whether each enclosing brace is that of a namespace (or an
+        // `extern "C"` block), rather than a class or function.
+        let mut scopes: Vec<bool> = Vec::new();
+        let mut namespace_pending = false;
+        // The depth of the current typedef, if we're in one.
+        let mut typedef_depth = None;
+        for (i, (header, token)) in tokens.iter().enumerate() {
+            match *token {
+                "namespace" | "extern" => namespace_pending = true,
+                "typedef" => typedef_depth = Some(scopes.len()),
+                "{" => scopes.push(std::mem::take(&mut namespace_pending)),
+                "}" => {
+                    scopes.pop();
+                }
+                "(" | "=" => namespace_pending = false,
+                ";" => {
+                    namespace_pending = false;
+                    if typedef_depth == Some(scopes.len()) {
+                        typedef_depth = None;
+                    }
+                }
+                _ if is_identifier(token) => {
+                    let prev = i.checked_sub(1).map(|i| tokens[i].1).unwrap_or_default();
+                    let next = tokens.get(i + 1).map(|t| t.1).unwrap_or_default();
+                    let evidence = match (prev, next) {
+                        ("class" | "struct" | "union" | "enum", ";") => Evidence::Declaration,
+                        ("class" | "struct" | "union" | "enum", "{" | ":" | "final") => {
+                            Evidence::Definition
+                        }
+                        ("using", "=") => Evidence::Definition,
+                        (_, ";" | "[") | ("*", ")") if typedef_depth == Some(scopes.len()) => {
+                            Evidence::Definition
+                        }
+                        ("=" | "::" | "namespace" | "return", _) => Evidence::Mention,
+                        (_, "(" | "=" | ";" | "[") if scopes.iter().all(|s| *s) => {
+                            Evidence::Declaration
+                        }
+                        _ => Evidence::Mention,
+                    };
+                    locations.record(token, header, evidence);
+                }
+                _ => {}
+            }
+        }
+        locations
+    }
+
+    fn record(&mut self, ident: &str, header: &str, evidence: Evidence) {
+        let headers = self.headers_by_ident.entry(ident.to_string()).or_default();
+        match headers.iter_mut().find(|(h, _)| h == header) {
+            Some((_, e)) => *e = evidence.max(*e),
+            None => headers.push((header.to_string(), evidence)),
+        }
+    }
+
+    fn headers(&self) -> impl Iterator<Item = &str> {
+        self.headers_by_ident
+            .values()
+            .flatten()
+            .map(|(header, _)| header.as_str())
+    }
+
+    /// The header declaring the given identifier, and how sure we are of
+    /// that. We prefer definitions to declarations, and either to mere
+    /// mentions; then those among `local_headers` such that a common name
+    /// used in (say) a system header doesn't hide its declaration in the
+    /// user's own headers; then the first.
+    fn header_for(&self, ident: &str, local_headers: &HashSet<&str>) -> Option<(&str, Evidence)> {
+        let headers = self.headers_by_ident.get(ident)?;
+        let best = headers.iter().map(|(_, evidence)| *evidence).max()?;
+        let mut candidates = headers.iter().filter(|(_, evidence)| *evidence == best);
+        candidates
+            .clone()
+            .find(|(header, _)| local_headers.contains(header.as_str()))
+            .or_else(|| candidates.next())
+            .map(|(header, evidence)| (header.as_str(), *evidence))
+    }
+}
+
+fn is_identifier(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_alphabetic() || c == '_')
+}
+
+/// The tokens in a line of preprocessed output: identifiers, numbers,
+/// `::` and single punctuation characters, ignoring comments and string
+/// or character literals. Block comments spanning lines aren't
+/// recognized, which at worst attributes a name to the wrong header.
+fn tokens(line: &str) -> impl Iterator<Item = &str> {
+    let mut code = line;
+    let mut tokens = Vec::new();
+    while let Some(start) = code.find(|c: char| !c.is_whitespace()) {
+        let rest = &code[start..];
+        let len = if rest.starts_with("//") {
+            rest.len()
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            comment.find("*/").map_or(rest.len(), |end| end + 4)
+        } else if let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') {
+            let mut escaped = false;
+            rest[1..]
+                .find(|c| {
+                    let end = !escaped && c == quote;
+                    escaped = !escaped && c == '\\';
+                    end
+                })
+                .map_or(rest.len(), |end| end + 2)
+        } else {
+            let len = if rest.starts_with("::") {
+                2
+            } else {
+                rest.find(|c: char| !c.is_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len())
+                    .max(rest.chars().next().unwrap().len_utf8())
+            };
+            tokens.push(&rest[..len]);
+            len
+        };
+        code = &rest[len..];
+    }
+    tokens.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{list_items, tokens, DeclarationLocations, Evidence};
+    use crate::ReportItem;
+    use std::collections::HashSet;
+
+    static PREPROCESSED: &str = r#"# 1 "/tmp/listing.h"
+# 1 "<built-in>" 1
+#define __clang__ 1
+# 1 "/usr/include/get.h" 1
+int get();
+# 1 "/src/include/fwd.h" 1
+namespace ns { class A; }
+void take(ns::A* a);
+# 1 "/src/include/a.h" 1
+#define VALUE 42
+namespace ns { // B
+class A {
+public:
+    int get() const { return x; } /* C */
+    int x;
+};
+int free_function(const char* s = "D");
+typedef int Id;
+}
+# 2 "/src/include/b.h" 1
+struct B { ns::A a; };
+struct alignas(8) Aligned {};
+"#;
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(
+            tokens(r#"int a_1 = f("x\"y", 'z') / 2; /* b */ c::d // e"#).collect::<Vec<_>>(),
+            vec!["int", "a_1", "=", "f", "(", ",", ")", "/", "2", ";", "c", "::", "d"]
+        );
+    }
+
+    #[test]
+    fn test_declaration_locations() {
+        let locations = DeclarationLocations::scan(PREPROCESSED);
+        let a = Some(("/src/include/a.h", Evidence::Definition));
+        let no_local_headers = HashSet::new();
+        // The forward declaration comes first, but the definition wins.
+        for ident in ["VALUE", "A", "Id"] {
+            assert_eq!(locations.header_for(ident, &no_local_headers), a);
+        }
+        assert_eq!(
+            locations.header_for("free_function", &no_local_headers),
+            Some(("/src/include/a.h", Evidence::Declaration))
+        );
+        assert_eq!(
+            locations.header_for("take", &no_local_headers),
+            Some(("/src/include/fwd.h", Evidence::Declaration))
+        );
+        assert_eq!(
+            locations.header_for("B", &no_local_headers),
+            Some(("/src/include/b.h", Evidence::Definition))
+        );
+        // We don't understand alignas, so can only guess.
+        assert_eq!(
+            locations.header_for("Aligned", &no_local_headers),
+            Some(("/src/include/b.h", Evidence::Mention))
+        );
+        // The method doesn't count as a declaration of the function...
+        let get = Some(("/usr/include/get.h", Evidence::Declaration));
+        assert_eq!(locations.header_for("get", &no_local_headers), get);
+        let local_headers = ["/src/include/a.h"].iter().copied().collect();
+        assert_eq!(locations.header_for("get", &local_headers), get);
+        // ... nor do members.
+        assert_eq!(
+            locations.header_for("x", &no_local_headers),
+            Some(("/src/include/a.h", Evidence::Mention))
+        );
+        assert_eq!(locations.header_for("__clang__", &no_local_headers), None);
+        assert_eq!(locations.header_for("C", &no_local_headers), None);
+        assert_eq!(locations.header_for("D", &no_local_headers), None);
+    }
+
+    #[test]
+    fn test_list_items() {
+        let item = |cpp_name: &str, kind: &str| ReportItem {
+            cpp_name: cpp_name.into(),
+            rust_name: None,
+            kind: kind.into(),
+            generated: true,
+            reason: None,
+            dependency_chain: Vec::new(),
+            type_kind: None,
+            non_pod_reason: None,
+            synthesized: false,
+        };
+        let report = vec![
+            ReportItem {
+                type_kind: Some("non_pod".into()),
+                ..item("ns::A", "Struct")
+            },
+            ReportItem {
+                type_kind: Some("non_pod".into()),
+                non_pod_reason: Some("it has\na destructor".into()),
+                ..item("B", "Struct")
+            },
+            ReportItem {
+                generated: false,
+                reason: Some("unsupported type".into()),
+                ..item("ns::A::get", "Method")
+            },
+            ReportItem {
+                synthesized: true,
+                ..item("ns::A::make_unique", "Function")
+            },
+            item("ns::free_function", "Function"),
+            item("Aligned", "Struct"),
+            item("ns::A_destructor", "ConcreteType"),
+        ];
+        let listed: Vec<_> = list_items(&report, PREPROCESSED, &[])
+            .iter()
+            .map(|item| item.to_string())
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                "Struct\tAligned\t/src/include/b.h?\tbindable",
+                "Struct\tB\t/src/include/b.h\tnon-POD: it has a destructor",
+                "Struct\tns::A\t/src/include/a.h\tPOD-safe",
+                "Method\tns::A::get\t/src/include/a.h\tunsupported: unsupported type",
+                "Function\tns::free_function\t/src/include/a.h\tbindable",
+            ]
+        );
+    }
+}
@@ -13,9 +13,10 @@
 // limitations under the License.
 
 use crate::ast_discoverer::Discoveries;
+use crate::frozen::Fnv1a;
 use crate::CppCodegenOptions;
 use crate::{
-    cxxbridge::CxxBridge, Error as EngineError, GeneratedCpp, IncludeCppEngine,
+    cxxbridge::CxxBridge, Error as EngineError, GeneratedCpp, IncludeCppEngine, ListedItem,
     RebuildDependencyRecorder,
 };
 use autocxx_parser::directives::SUBCLASS;
@@ -89,6 +90,7 @@ pub fn parse_file<P1: AsRef<Path>>(
 fn parse_file_contents(source: syn::File, auto_allowlist: bool) -> Result<ParsedFile, ParseError> {
     let mut results = Vec::new();
     let mut extra_superclasses = Vec::new();
+    let mut subclass_options = Vec::new();
     let mut discoveries = Discoveries::default();
     for item in source.items {
         results.push(match item {
@@ -129,11 +131,23 @@ fn parse_file_contents(source: syn::File, auto_allowlist: bool) -> Result<Parsed
                         let args: SubclassAttrs = is_superclass_attr
                             .parse_args()
                             .map_err(ParseError::Syntax)?;
-                        if let Some(superclass) = args.superclass {
-                            extra_superclasses.push(Subclass {
-                                superclass,
-                                subclass,
-                            })
+                        if !args.superclasses.is_empty() {
+                            extra_superclasses.extend(args.superclasses.into_iter().map(
+                                |superclass| Subclass {
+                                    superclass,
+                                    subclass: subclass.clone(),
+                                    thread_safe: args.thread_safe,
+                                    cpp_factory: args.cpp_factory,
+                                    error_policy: args.error_policy.unwrap_or_default(),
+                                    cpp_namespace: args.cpp_namespace.clone(),
+                                },
+                            ))
+                        } else if args.thread_safe
+                            || args.cpp_factory
+                            || args.error_policy.is_some()
+                            || args.cpp_namespace.is_some()
+                        {
+                            subclass_options.push((subclass, args));
                         }
                     }
                 }
@@ -151,7 +165,10 @@ fn parse_file_contents(source: syn::File, auto_allowlist: bool) -> Result<Parsed
     {
         return Err(ParseError::DiscoveredRustItemsWhenNotInAutoDiscover);
     }
-    if !extra_superclasses.is_empty() || (auto_allowlist && !discoveries.is_empty()) {
+    if !extra_superclasses.is_empty()
+        || !subclass_options.is_empty()
+        || (auto_allowlist && !discoveries.is_empty())
+    {
         let mut autocxx_seg_iterator = results.iter_mut().filter_map(|seg| match seg {
             Segment::Autocxx(engine) => Some(engine),
             _ => None,
@@ -164,6 +181,25 @@ fn parse_file_contents(source: syn::File, auto_allowlist: bool) -> Result<Parsed
                     .config_mut()
                     .subclasses
                     .append(&mut extra_superclasses);
+                // A #[subclass(thread_safe)], #[subclass(cpp_factory)],
+                // #[subclass(on_error = ...)] or #[subclass(cpp_namespace(...))]
+                // attribute may accompany a subclass! directive which
+                // doesn't say so.
+                for subclass in engine.config_mut().subclasses.iter_mut() {
+                    for (_, args) in subclass_options
+                        .iter()
+                        .filter(|(id, _)| *id == subclass.subclass)
+                    {
+                        subclass.thread_safe |= args.thread_safe;
+                        subclass.cpp_factory |= args.cpp_factory;
+                        if let Some(error_policy) = args.error_policy {
+                            subclass.error_policy = error_policy;
+                        }
+                        if let Some(cpp_namespace) = &args.cpp_namespace {
+                            subclass.cpp_namespace = Some(cpp_namespace.clone());
+                        }
+                    }
+                }
                 if auto_allowlist {
                     for cpp in discoveries.cpp_list {
                         engine
@@ -254,6 +290,22 @@ impl ParsedFile {
             .flatten()
     }
 
+    /// Behave as if every `include_cpp!` in this file contained `strict!()`.
+    pub fn set_strict(&mut self) {
+        for include_cpp in self.get_autocxxes_mut() {
+            include_cpp.config_mut().set_strict();
+        }
+    }
+
+    /// Behave as if every `include_cpp!` in this file contained
+    /// `generate_all!()`, e.g. in order to list everything which could
+    /// be generated using [`Self::list_items`].
+    pub fn set_generate_all(&mut self) {
+        for include_cpp in self.get_autocxxes_mut() {
+            include_cpp.config_mut().set_generate_all();
+        }
+    }
+
     pub fn resolve_all(
         &mut self,
         autocxx_inc: Vec<PathBuf>,
@@ -287,6 +339,60 @@ impl ParsedFile {
         }
         Ok(())
     }
+
+    /// A machine-readable report of the APIs considered by each
+    /// `include_cpp!` in this file; see [`crate::ReportItem`]. Call
+    /// [`Self::resolve_all`] first.
+    pub fn report(&self) -> Vec<serde_json::Value> {
+        self.get_rs_buildables()
+            .flat_map(|include_cpp| {
+                let mod_name = include_cpp.get_mod_name();
+                include_cpp
+                    .get_report()
+                    .iter()
+                    .map(move |item| item.to_json(&mod_name))
+            })
+            .collect()
+    }
+
+    /// Each item found in the headers by each `include_cpp!` in this
+    /// file; see [`crate::ListedItem`]. Call [`Self::resolve_all`] first.
+    pub fn list_items(&self, extra_clang_args: &[&str]) -> Vec<ListedItem> {
+        let mut items: Vec<_> = self
+            .get_rs_buildables()
+            .flat_map(|include_cpp| include_cpp.list_items(extra_clang_args))
+            .collect();
+        items.sort();
+        items.dedup();
+        items
+    }
+
+    /// A key which changes whenever the results of [`Self::resolve_all`]
+    /// and subsequent C++ generation might change, for use in caching
+    /// generated bindings. Returns `None` if the key can't be calculated
+    /// because the C++ preprocessor isn't available or fails.
+    pub fn cache_key(
+        &self,
+        autocxx_inc: &[PathBuf],
+        extra_clang_args: &[&str],
+        cpp_codegen_options: &CppCodegenOptions,
+    ) -> Option<u64> {
+        let mut hasher = Fnv1a::default();
+        hasher.write(env!("CARGO_PKG_VERSION").as_bytes());
+        hasher.write(format!("{:?}", cpp_codegen_options).as_bytes());
+        for seg in &self.0 {
+            match seg {
+                Segment::Autocxx(include_cpp) => {
+                    include_cpp.hash_inputs(&mut hasher, autocxx_inc, extra_clang_args)?
+                }
+                Segment::Cxx(cxx_bridge) => {
+                    hasher.write(cxx_bridge.to_token_stream().to_string().as_bytes())
+                }
+                Segment::Other(_) => {}
+            }
+        }
+        Some(hasher.0)
+    }
 }
 
 impl ToTokens for ParsedFile {
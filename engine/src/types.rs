@@ -50,7 +50,6 @@ impl Namespace {
         self.0.iter()
     }
 
-    #[cfg(test)]
     pub(crate) fn from_user_input(input: &str) -> Self {
         Self(Arc::new(input.split("::").map(|x| x.to_string()).collect()))
     }
@@ -18,6 +18,8 @@ use std::{ffi::OsStr, path::Path};
 
 pub type Builder = autocxx_engine::Builder<CargoBuilderContext>;
 
+pub use autocxx_engine::CppStandard;
+
 #[deprecated]
 /// Use [`Builder::new`] instead
 pub fn build<P1, I, T>(
@@ -91,6 +91,202 @@ fn test_gen_fixed_num() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn test_gen_cpp_standard() -> Result<(), Box<dyn std::error::Error>> {
+    // std::optional is only declared in C++17 and later, so this header
+    // can only be parsed if the standard reaches libclang.
+    let tmp_dir = TempDir::new("example")?;
+    let code_dir = tmp_dir.path().join("optional");
+    std::fs::create_dir(&code_dir).unwrap();
+    write_to_file(
+        &code_dir,
+        "input.h",
+        b"#include <optional>\n\
+        inline int get_value() { std::optional<int> o; return o.value_or(3); }\n",
+    );
+    write_to_file(
+        &code_dir,
+        "main.rs",
+        b"autocxx::include_cpp! {\n\
+            #include \"input.h\"\n\
+            safety!(unsafe_ffi)\n\
+            generate!(\"get_value\")\n\
+        }\n\
+        fn main() {}\n",
+    );
+    let mut cmd = Command::cargo_bin("autocxx-gen")?;
+    cmd.arg("--cpp-standard")
+        .arg("c++17")
+        .arg("--inc")
+        .arg(code_dir.to_str().unwrap())
+        .arg(code_dir.join("main.rs"))
+        .arg("--outdir")
+        .arg(tmp_dir.path().to_str().unwrap())
+        .arg("--gen-cpp")
+        .assert()
+        .success();
+    assert_contains(&tmp_dir, "gen0.cc", "get_value");
+    Ok(())
+}
+
+#[test]
+fn test_gen_numbering() -> Result<(), Box<dyn std::error::Error>> {
+    // The first include_cpp! needs no C++ of its own beyond what cxx
+    // generates, but still gets a .cc, so the second's are numbered the
+    // same regardless. Its subclass's peer header gets no .cc at all.
+    let tmp_dir = TempDir::new("example")?;
+    let code_dir = tmp_dir.path().join("numbering");
+    std::fs::create_dir(&code_dir).unwrap();
+    write_to_file(
+        &code_dir,
+        "input.h",
+        b"#include <cstdint>\n\
+        inline uint32_t get_value() { return 3; }\n\
+        inline uint32_t get_other() { return 4; }\n\
+        class Observer {\n\
+        public:\n\
+            Observer() {}\n\
+            virtual void notify() const {}\n\
+            virtual ~Observer() {}\n\
+        };\n",
+    );
+    write_to_file(
+        &code_dir,
+        "main.rs",
+        b"autocxx::include_cpp! {\n\
+            #include \"input.h\"\n\
+            safety!(unsafe_ffi)\n\
+            generate!(\"get_value\")\n\
+        }\n\
+        autocxx::include_cpp! {\n\
+            #include \"input.h\"\n\
+            name!(ffi2)\n\
+            safety!(unsafe_ffi)\n\
+            generate!(\"get_other\")\n\
+            subclass!(\"Observer\",MyObserver)\n\
+        }\n\
+        fn main() {}\n",
+    );
+    let mut cmd = Command::cargo_bin("autocxx-gen")?;
+    cmd.arg("--inc")
+        .arg(code_dir.to_str().unwrap())
+        .arg(code_dir.join("main.rs"))
+        .arg("--outdir")
+        .arg(tmp_dir.path().to_str().unwrap())
+        .arg("--gen-cpp")
+        .assert()
+        .success();
+    assert_contains(&tmp_dir, "gen0.cc", "get_value");
+    assert_exists(&tmp_dir, "gen1.cc");
+    assert_contains(&tmp_dir, "gen2.cc", "get_other");
+    assert_contains(&tmp_dir, "gen3.cc", "MyObserverCpp");
+    assert_exists(&tmp_dir, "MyObserverCpp.h");
+    assert!(!tmp_dir.path().join("gen4.cc").exists());
+    Ok(())
+}
+
+#[test]
+fn test_gen_compile_commands() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = TempDir::new("example")?;
+    let compile_commands = format!(
+        r#"[{{"directory": "{}", "command": "c++ -Idemo -DFOO=1 -std=c++17 -c demo/input.cc", "file": "demo/input.cc"}}]"#,
+        tmp_dir.path().to_str().unwrap()
+    );
+    write_to_file(
+        tmp_dir.path(),
+        "compile_commands.json",
+        compile_commands.as_bytes(),
+    );
+    base_test(&tmp_dir, |cmd| {
+        cmd.arg("--compile-commands")
+            .arg(tmp_dir.path().join("compile_commands.json"))
+            .arg("--compile-commands-for")
+            .arg(tmp_dir.path().join("demo/input.cc"));
+    })
+}
+
+#[test]
+fn test_gen_report() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = TempDir::new("example")?;
+    let report_path = tmp_dir.path().join("report.json");
+    base_test(&tmp_dir, |cmd| {
+        cmd.arg("--report").arg(report_path.to_str().unwrap());
+    })?;
+    let report = std::fs::read_to_string(report_path)?;
+    assert!(report.contains("\"cpp_name\": \"DoMath\""));
+    assert!(report.contains("\"generated\": true"));
+    Ok(())
+}
+
+#[test]
+fn test_gen_strict() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = TempDir::new("example")?;
+    base_test(&tmp_dir, |cmd| {
+        cmd.arg("--strict");
+    })
+}
+
+#[test]
+fn test_gen_list() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = TempDir::new("example")?;
+    let demo_code_dir = tmp_dir.path().join("demo");
+    std::fs::create_dir(&demo_code_dir).unwrap();
+    write_to_file(&demo_code_dir, "input.h", INPUT_H.as_bytes());
+    write_to_file(&demo_code_dir, "main.rs", MAIN_RS.as_bytes());
+    let mut cmd = Command::cargo_bin("autocxx-gen")?;
+    let output = cmd
+        .arg("list")
+        .arg("--inc")
+        .arg(demo_code_dir.to_str().unwrap())
+        .arg(demo_code_dir.join("main.rs"))
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let listing = String::from_utf8(output)?;
+    assert!(listing
+        .lines()
+        .any(|line| line.starts_with("Function\tDoMath\t") && line.contains("input.h")));
+    Ok(())
+}
+
+#[test]
+fn test_gen_depfile() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = TempDir::new("example")?;
+    let depfile_path = tmp_dir.path().join("gen.d");
+    base_test(&tmp_dir, |cmd| {
+        cmd.arg("--depfile").arg(depfile_path.to_str().unwrap());
+    })?;
+    assert_contentful(&tmp_dir, "gen.d");
+    let depfile = std::fs::read_to_string(depfile_path)?;
+    assert!(depfile.contains("gen0.cc"));
+    assert!(depfile.contains("main.rs"));
+    assert!(depfile.contains("input.h"));
+    Ok(())
+}
+
+#[test]
+fn test_gen_freeze() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = TempDir::new("example")?;
+    let freeze_dir = tmp_dir.path().join("frozen");
+    // Leftovers from an earlier freeze must not survive, else the builder
+    // would compile them too.
+    std::fs::create_dir_all(freeze_dir.join("cxx"))?;
+    std::fs::write(freeze_dir.join("cxx/gen1.cxx"), "stale")?;
+    base_test(&tmp_dir, |cmd| {
+        cmd.arg("--freeze").arg(freeze_dir.to_str().unwrap());
+    })?;
+    assert_contentful(&tmp_dir, "frozen/rs/autocxx-ffi-default-gen.rs");
+    assert_contentful(&tmp_dir, "frozen/cxx/gen0.cxx");
+    assert_contentful(&tmp_dir, "frozen/include/autocxxgen_ffi.h");
+    assert!(!freeze_dir.join("cxx/gen1.cxx").exists());
+    let fingerprint = std::fs::read_to_string(freeze_dir.join("autocxx-fingerprint.txt"))?;
+    assert!(fingerprint.starts_with("config "));
+    assert!(fingerprint.contains("input.h"));
+    Ok(())
+}
+
 #[test]
 fn test_gen_preprocess() -> Result<(), Box<dyn std::error::Error>> {
     let tmp_dir = TempDir::new("example")?;
@@ -0,0 +1,102 @@
+// Copyright 2021 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use autocxx_engine::RebuildDependencyRecorder;
+
+/// A Makefile-style `.d` file, of the sort understood by Ninja, Make
+/// and friends, listing the files on which our outputs depend.
+#[derive(Debug, Default)]
+pub(crate) struct Depfile {
+    outputs: Vec<PathBuf>,
+    dependencies: Vec<String>,
+}
+
+impl Depfile {
+    pub(crate) fn add_output(&mut self, output: &Path) {
+        if !self.outputs.iter().any(|o| o == output) {
+            self.outputs.push(output.to_path_buf());
+        }
+    }
+
+    pub(crate) fn add_dependency(&mut self, dependency: &str) {
+        if !self.dependencies.iter().any(|d| d == dependency) {
+            self.dependencies.push(dependency.to_string());
+        }
+    }
+
+    pub(crate) fn dependencies(&self) -> &[String] {
+        &self.dependencies
+    }
+
+    fn contents(&self) -> String {
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|o| escape(&o.to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let dependencies = self
+            .dependencies
+            .iter()
+            .map(|d| format!(" \\\n  {}", escape(d)))
+            .collect::<String>();
+        format!("{}:{}\n", outputs, dependencies)
+    }
+
+    pub(crate) fn write(&self, path: &Path) -> std::io::Result<()> {
+        let mut f = File::create(path)?;
+        f.write_all(self.contents().as_bytes())
+    }
+}
+
+fn escape(filename: &str) -> String {
+    filename.replace(' ', "\\ ").replace('$', "$$")
+}
+
+/// Records the headers reported by the engine into a shared [`Depfile`].
+#[derive(Debug)]
+pub(crate) struct DepfileRecorder(pub(crate) Arc<Mutex<Depfile>>);
+
+impl RebuildDependencyRecorder for DepfileRecorder {
+    fn record_header_file_dependency(&self, filename: &str) {
+        self.0.lock().unwrap().add_dependency(filename);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Depfile;
+    use std::path::Path;
+
+    #[test]
+    fn test_depfile_contents() {
+        let mut depfile = Depfile::default();
+        depfile.add_output(Path::new("out/gen0.cc"));
+        depfile.add_output(Path::new("out/autocxxgen_ffi.h"));
+        depfile.add_dependency("src/main.rs");
+        depfile.add_dependency("/usr/include/my header.h");
+        depfile.add_dependency("src/main.rs");
+        assert_eq!(
+            depfile.contents(),
+            "out/gen0.cc out/autocxxgen_ffi.h: \\\n  src/main.rs \\\n  /usr/include/my\\ header.h\n"
+        );
+    }
+}
@@ -14,13 +14,19 @@
 
 #[cfg(test)]
 mod cmd_test;
+mod depfile;
 
-use autocxx_engine::{parse_file, CppCodegenOptions};
-use clap::{crate_authors, crate_version, App, Arg, ArgGroup};
+use autocxx_engine::{
+    parse_file, rust_to_clang_target, CompileCommandFlags, CppCodegenOptions, CppStandard,
+    Fingerprint, FrozenBindings, RebuildDependencyRecorder,
+};
+use clap::{crate_authors, crate_version, App, AppSettings, Arg, ArgGroup, SubCommand};
+use depfile::{Depfile, DepfileRecorder};
 use proc_macro2::TokenStream;
 use quote::ToTokens;
 use std::io::{Read, Write};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::{fs::File, path::Path};
 
 pub(crate) static BLANK: &str = "// Blank autocxx placeholder";
@@ -69,19 +75,38 @@ a) Have exactly one include_cpp directive in each source .rs file
 b) Set AUTOCXX_RS_FILE when using autocxx_macro.
 c) Teach your build system always that the outputs of this tool
    are always guaranteed to be gen0.include.rs, gen0.cc and gen1.cc.
+
+Finally, --freeze DIR writes all the generated Rust and C++, together
+with a fingerprint of the .rs file, headers and clang arguments, into DIR.
+The frozen bindings can be checked in, and later consumed without libclang
+either by autocxx_build (see Builder::frozen_bindings) or by setting
+AUTOCXX_RS to DIR/rs and building DIR/cxx/*.cxx with DIR/include on the
+include path. Builder::frozen_bindings checks that the fingerprint still
+matches; if you consume the files directly, that's up to you.
+
+Alternatively, 'autocxx-gen list' doesn't generate anything, but prints
+everything found in the headers on the include path, one item per line:
+its kind, fully-qualified C++ name, the header in which it's declared
+(followed by '?' if autocxx couldn't find the declaration, and so has
+guessed the first header mentioning it) and whether autocxx believes it
+can generate bindings for it (POD-safe, non-POD, abstract, bindable, or
+unsupported along with the reason). Any generate! directives are ignored;
+everything is considered. This is useful for deciding what to put in your
+include_cpp! directive, or to spot C++ which autocxx doesn't yet support.
 ";
 
 fn main() {
-    let matches = App::new("autocxx-gen")
+    let app_matches = App::new("autocxx-gen")
         .version(crate_version!())
         .author(crate_authors!())
         .about("Generates bindings files from Rust files that contain include_cpp! macros")
         .long_about(LONG_HELP)
-        .arg(
-            Arg::with_name("INPUT")
-                .help("Sets the input .rs file to use")
-                .required(true)
-                .index(1),
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .args(&input_args())
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("Instead of generating anything, prints every item in the headers with the header declaring it and whether bindings could be generated for it")
+                .args(&input_args()),
         )
         .arg(
             Arg::with_name("outdir")
@@ -92,16 +117,6 @@ fn main() {
                 .takes_value(true)
                 .required(true),
         )
-        .arg(
-            Arg::with_name("inc")
-                .short("I")
-                .long("inc")
-                .multiple(true)
-                .number_of_values(1)
-                .value_name("INCLUDE DIRS")
-                .help("include path")
-                .takes_value(true),
-        )
         .arg(
             Arg::with_name("cpp-extension")
                 .long("cpp-extension")
@@ -131,6 +146,7 @@ fn main() {
             .arg("gen-cpp")
             .arg("gen-rs-complete")
             .arg("gen-rs-include")
+            .arg("freeze")
         )
         .arg(
             Arg::with_name("cxx-gen")
@@ -156,6 +172,11 @@ fn main() {
                 .long("auto-allowlist")
                 .help("Dynamically construct allowlist from real uses of APIs.")
         )
+        .arg(
+            Arg::with_name("strict")
+                .long("strict")
+                .help("Fail if anything requested with generate! or generate_pod!, or any of its methods, can't be bound, as if strict!() were specified.")
+        )
         .arg(
             Arg::with_name("suppress-system-headers")
                 .long("suppress-system-headers")
@@ -183,40 +204,112 @@ fn main() {
                 .takes_value(true),
         )
         .arg(
-            Arg::with_name("clang-args")
-                .last(true)
-                .multiple(true)
-                .help("Extra arguments to pass to Clang"),
+            Arg::with_name("depfile")
+                .long("depfile")
+                .value_name("DEPFILE")
+                .help("A .d file to write, listing the .rs and header files on which the generated files depend")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("freeze")
+                .long("freeze")
+                .value_name("DIR")
+                .help("write all generated Rust and C++, plus a fingerprint of the inputs, to DIR for later use by Builder::frozen_bindings without libclang")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("report")
+                .long("report")
+                .value_name("JSON")
+                .help("write a JSON report listing every API considered, whether bindings were generated for it, and if not, why not")
+                .takes_value(true),
         )
         .get_matches();
 
     env_logger::builder().init();
-    let mut parsed_file = parse_file(
-        matches.value_of("INPUT").unwrap(),
-        matches.is_present("auto-allowlist"),
-    )
-    .expect("Unable to parse Rust file and interpret autocxx macro");
-    let incs = matches
+    let (matches, listing) = match app_matches.subcommand_matches("list") {
+        Some(list_matches) => (list_matches, true),
+        None => (&app_matches, false),
+    };
+    let input = matches.value_of("INPUT").unwrap();
+    // Freezing needs to know the headers too, so it uses the same recorder.
+    let depfile = (matches.is_present("depfile") || matches.is_present("freeze")).then(|| {
+        let mut depfile = Depfile::default();
+        depfile.add_dependency(input);
+        Arc::new(Mutex::new(depfile))
+    });
+    let mut parsed_file = parse_file(input, matches.is_present("auto-allowlist"))
+        .expect("Unable to parse Rust file and interpret autocxx macro");
+    if matches.is_present("strict") {
+        parsed_file.set_strict();
+    }
+    if listing {
+        parsed_file.set_generate_all();
+    }
+    let compile_command_flags = matches
+        .value_of_os("compile-commands")
+        .map(|compile_commands| {
+            CompileCommandFlags::from_compile_commands(
+                Path::new(compile_commands),
+                Path::new(matches.value_of_os("compile-commands-for").unwrap()),
+            )
+            .expect("Unable to use compile commands")
+        })
+        .unwrap_or_default();
+    let incs: Vec<_> = matches
         .values_of("inc")
         .unwrap_or_default()
         .map(PathBuf::from)
+        .chain(compile_command_flags.include_dirs.iter().cloned())
         .collect();
-    let extra_clang_args: Vec<_> = matches
-        .values_of("clang-args")
-        .unwrap_or_default()
+    // The standard goes first, such that clang-args may still override it.
+    let std_arg = matches
+        .value_of("cpp-standard")
+        .map(|std| std.parse::<CppStandard>().unwrap())
+        .or(compile_command_flags.cpp_standard)
+        .map(|std| std.clang_arg());
+    let target_args: Vec<_> = matches
+        .value_of("target")
+        .iter()
+        .map(|target| format!("--target={}", rust_to_clang_target(target)))
+        .chain(
+            matches
+                .value_of("sysroot")
+                .map(|sysroot| format!("--sysroot={}", sysroot)),
+        )
+        .collect();
+    let define_args = compile_command_flags.define_args();
+    let extra_clang_args: Vec<_> = std_arg
+        .iter()
+        .chain(target_args.iter())
+        .chain(define_args.iter())
+        .map(|s| &s[..])
+        .chain(matches.values_of("clang-args").unwrap_or_default())
         .collect();
     let suppress_system_headers = matches.is_present("suppress-system-headers");
     let mut cpp_codegen_options = CppCodegenOptions::default();
     cpp_codegen_options.suppress_system_headers = suppress_system_headers;
-    cpp_codegen_options.cxx_impl_annotations = get_option_string("cxx-impl-annotations", &matches);
-    cpp_codegen_options.path_to_cxx_h = get_option_string("cxx-h-path", &matches);
-    cpp_codegen_options.path_to_cxxgen_h = get_option_string("cxxgen-h-path", &matches);
-    // In future, we should provide an option to write a .d file here
-    // by passing a callback into the dep_recorder parameter here.
-    // https://github.com/google/autocxx/issues/56
+    cpp_codegen_options.cxx_impl_annotations = get_option_string("cxx-impl-annotations", matches);
+    cpp_codegen_options.path_to_cxx_h = get_option_string("cxx-h-path", matches);
+    cpp_codegen_options.path_to_cxxgen_h = get_option_string("cxxgen-h-path", matches);
+    let dep_recorder = depfile.as_ref().map(|depfile| {
+        Box::new(DepfileRecorder(depfile.clone())) as Box<dyn RebuildDependencyRecorder>
+    });
     parsed_file
-        .resolve_all(incs, &extra_clang_args, None, &cpp_codegen_options)
+        .resolve_all(
+            incs.clone(),
+            &extra_clang_args,
+            dep_recorder,
+            &cpp_codegen_options,
+        )
         .expect("Unable to resolve macro");
+    if listing {
+        for item in parsed_file.list_items(&extra_clang_args) {
+            println!("{}", item);
+        }
+        return;
+    }
+    let mut written = Vec::new();
     let outdir: PathBuf = matches.value_of_os("outdir").unwrap().into();
     let desired_number = matches
         .value_of("generate-exact")
@@ -229,22 +322,30 @@ fn main() {
                 .generate_h_and_cxx(&cpp_codegen_options)
                 .expect("Unable to generate header and C++ code");
             for pair in generations.0 {
-                let cppname = format!("gen{}.{}", counter, cpp);
-                write_to_file(&outdir, cppname, &pair.implementation.unwrap_or_default());
-                write_to_file(&outdir, pair.header_name, &pair.header);
-                counter += 1;
+                // Write a .cc for each pair, even if it's empty, so that the
+                // numbering doesn't depend on which have implementations.
+                if !pair.header_only {
+                    let cppname = format!("gen{}.{}", counter, cpp);
+                    written.push(write_to_file(
+                        &outdir,
+                        cppname,
+                        &pair.implementation.unwrap_or_default(),
+                    ));
+                    counter += 1;
+                }
+                written.push(write_to_file(&outdir, pair.header_name, &pair.header));
             }
         }
-        write_placeholders(&outdir, counter, desired_number, cpp);
+        write_placeholders(&outdir, counter, desired_number, cpp, &mut written);
     }
     if matches.is_present("gen-rs-complete") {
         let mut ts = TokenStream::new();
         parsed_file.to_tokens(&mut ts);
-        write_to_file(
+        written.push(write_to_file(
             &outdir,
             "gen.complete.rs".to_string(),
             ts.to_string().as_bytes(),
-        );
+        ));
     }
     if matches.is_present("gen-rs-include") {
         let autocxxes = parsed_file.get_rs_buildables();
@@ -256,11 +357,126 @@ fn main() {
             } else {
                 include_cxx.get_rs_filename()
             };
-            write_to_file(&outdir, fname, ts.to_string().as_bytes());
+            written.push(write_to_file(&outdir, fname, ts.to_string().as_bytes()));
             counter += 1;
         }
-        write_placeholders(&outdir, counter, desired_number, "include.rs");
+        write_placeholders(&outdir, counter, desired_number, "include.rs", &mut written);
+    }
+    if let Some(report_path) = matches.value_of_os("report") {
+        let report = serde_json::to_vec_pretty(&parsed_file.report()).unwrap();
+        let report_path = Path::new(report_path);
+        written.push(write_to_file(
+            report_path.parent().unwrap_or_else(|| Path::new("")),
+            report_path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned(),
+            &report,
+        ));
     }
+    if let Some(freeze_dir) = matches.value_of_os("freeze") {
+        let frozen = FrozenBindings::new(freeze_dir);
+        frozen
+            .clear()
+            .expect("Unable to create frozen bindings directory");
+        let mut counter = 0usize;
+        for include_cxx in parsed_file.get_cpp_buildables() {
+            let generations = include_cxx
+                .generate_h_and_cxx(&cpp_codegen_options)
+                .expect("Unable to generate header and C++ code");
+            for pair in generations.0 {
+                if let Some(implementation) = &pair.implementation {
+                    let cxxname = format!("gen{}.cxx", counter);
+                    written.push(write_to_file(&frozen.cxx_dir(), cxxname, implementation));
+                    counter += 1;
+                }
+                written.push(write_to_file(
+                    &frozen.include_dir(),
+                    pair.header_name,
+                    &pair.header,
+                ));
+            }
+        }
+        for include_cxx in parsed_file.get_rs_buildables() {
+            let ts = include_cxx.generate_rs();
+            written.push(write_to_file(
+                &frozen.rs_dir(),
+                include_cxx.get_rs_filename(),
+                ts.to_string().as_bytes(),
+            ));
+        }
+        let fingerprint = Fingerprint::calculate(
+            Path::new(input),
+            &extra_clang_args,
+            &incs,
+            depfile.as_ref().unwrap().lock().unwrap().dependencies(),
+        )
+        .expect("Unable to fingerprint inputs");
+        frozen
+            .write_fingerprint(&fingerprint)
+            .expect("Unable to write fingerprint");
+    }
+    if let (Some(depfile), Some(depfile_path)) = (depfile, matches.value_of_os("depfile")) {
+        let mut depfile = depfile.lock().unwrap();
+        for output in &written {
+            depfile.add_output(output);
+        }
+        depfile
+            .write(Path::new(depfile_path))
+            .expect("Unable to write depfile");
+    }
+}
+
+/// Arguments describing the .rs file and how to parse its headers, common
+/// to generation and `list`.
+fn input_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("INPUT")
+            .help("Sets the input .rs file to use")
+            .required(true)
+            .index(1),
+        Arg::with_name("inc")
+            .short("I")
+            .long("inc")
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("INCLUDE DIRS")
+            .help("include path")
+            .takes_value(true),
+        Arg::with_name("compile-commands")
+            .long("compile-commands")
+            .value_name("JSON")
+            .help("a compile_commands.json from which to take include directories, defines and the C++ standard")
+            .takes_value(true)
+            .requires("compile-commands-for"),
+        Arg::with_name("compile-commands-for")
+            .long("compile-commands-for")
+            .value_name("FILE")
+            .help("the translation unit whose entry in --compile-commands should be used")
+            .takes_value(true)
+            .requires("compile-commands"),
+        Arg::with_name("target")
+            .long("target")
+            .value_name("TRIPLE")
+            .help("Rust target triple for which to generate bindings, when cross-compiling, so that C++ type layouts match the target")
+            .takes_value(true),
+        Arg::with_name("sysroot")
+            .long("sysroot")
+            .value_name("PATH")
+            .help("sysroot in which clang should look for the target's headers")
+            .takes_value(true),
+        Arg::with_name("cpp-standard")
+            .long("cpp-standard")
+            .value_name("STANDARD")
+            .possible_values(&["c++11", "c++14", "c++17", "c++20"])
+            .help("C++ standard with which to parse headers, e.g. c++17. Defaults to c++14")
+            .takes_value(true),
+        Arg::with_name("clang-args")
+            .last(true)
+            .multiple(true)
+            .help("Extra arguments to pass to Clang"),
+    ]
 }
 
 fn get_option_string(option: &str, matches: &clap::ArgMatches) -> Option<String> {
@@ -273,6 +489,7 @@ fn write_placeholders(
     mut counter: usize,
     desired_number: Option<usize>,
     extension: &str,
+    written: &mut Vec<PathBuf>,
 ) {
     if let Some(desired_number) = desired_number {
         if counter > desired_number {
@@ -280,13 +497,13 @@ fn write_placeholders(
         }
         while counter < desired_number {
             let fname = format!("gen{}.{}", counter, extension);
-            write_to_file(outdir, fname, BLANK.as_bytes());
+            written.push(write_to_file(outdir, fname, BLANK.as_bytes()));
             counter += 1;
         }
     }
 }
 
-fn write_to_file(dir: &Path, filename: String, content: &[u8]) {
+fn write_to_file(dir: &Path, filename: String, content: &[u8]) -> PathBuf {
     let path = dir.join(filename);
     {
         let f = File::open(&path);
@@ -294,10 +511,11 @@ fn write_to_file(dir: &Path, filename: String, content: &[u8]) {
             let mut existing_content = Vec::new();
             let r = f.read_to_end(&mut existing_content);
             if r.is_ok() && existing_content == content {
-                return; // don't change timestamp on existing file unnecessarily
+                return path; // don't change timestamp on existing file unnecessarily
             }
         }
     }
     let mut f = File::create(&path).expect("Unable to create file");
     f.write_all(content).expect("Unable to write file");
+    path
 }
@@ -94,6 +94,37 @@ impl LinkableTryBuilder {
             test_cases.pass(rs_path)
         })
     }
+
+    /// Builds a Rust file which is expected not to compile, and returns
+    /// the errors reported by rustc. Any files in `generated_rs_dir` whose
+    /// names contain `pattern` are made available to `include_cpp!`.
+    fn build_expect_fail<P1: AsRef<Path>, P2: AsRef<Path> + RefUnwindSafe>(
+        &self,
+        generated_rs_dir: &P1,
+        pattern: &str,
+        rs_path: &P2,
+    ) -> String {
+        self.move_items_into_temp_dir(generated_rs_dir, pattern);
+        let temp_path = self.temp_dir.path().to_str().unwrap();
+        std::env::set_var("RUSTFLAGS", format!("-L {}", temp_path));
+        std::env::set_var("AUTOCXX_RS", temp_path);
+        // Have trybuild record the errors alongside the .rs file, rather
+        // than compare them with an existing record.
+        std::env::set_var("TRYBUILD", "overwrite");
+        let r = std::panic::catch_unwind(|| {
+            let test_cases = trybuild::TestCases::new();
+            test_cases.compile_fail(rs_path)
+        });
+        std::env::remove_var("TRYBUILD");
+        for item in std::fs::read_dir(self.temp_dir.path()).unwrap() {
+            let item = item.unwrap();
+            if item.file_name().into_string().unwrap().contains(pattern) {
+                std::fs::remove_file(item.path()).unwrap();
+            }
+        }
+        r.expect("Unexpected success");
+        std::fs::read_to_string(rs_path.as_ref().with_extension("stderr")).unwrap()
+    }
 }
 
 fn write_to_file(tdir: &TempDir, filename: &str, content: &str) -> PathBuf {
@@ -204,7 +235,7 @@ pub(crate) fn run_test_expect_fail_ex(
     builder_modifier: Option<BuilderModifier>,
     code_checker: Option<CodeChecker>,
     extra_rust: Option<TokenStream>,
-) {
+) -> TestError {
     do_run_test(
         cxx_code,
         header_code,
@@ -214,7 +245,25 @@ pub(crate) fn run_test_expect_fail_ex(
         code_checker,
         extra_rust,
     )
-    .expect_err("Unexpected success");
+    .expect_err("Unexpected success")
+}
+
+/// Runs autocxx on `rust_code`, expecting code generation to fail, then
+/// compiles the Rust regardless (as an IDE might) and returns the errors
+/// reported by rustc.
+pub(crate) fn run_test_expect_macro_fail(header_code: &str, rust_code: &str) -> String {
+    let tdir = tempdir().unwrap();
+    write_to_file(&tdir, "input.h", &format!("#pragma once\n{}", header_code));
+    let rs_path = write_to_file(&tdir, "input.rs", rust_code);
+    let gen_dir = tdir.path().join("target");
+    Builder::<TestBuilderContext>::new(&rs_path, [tdir.path()])
+        .custom_gendir(gen_dir.clone())
+        .build()
+        .expect_err("Unexpected success");
+    get_builder()
+        .lock()
+        .unwrap()
+        .build_expect_fail(&gen_dir.join("rs"), ".rs.error", &rs_path)
 }
 
 /// In the future maybe the tests will distinguish the exact type of failure expected.
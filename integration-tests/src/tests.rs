@@ -15,13 +15,16 @@
 use crate::test_utils::{
     directives_from_lists, do_run_test_manual, make_clang_arg_adder, make_error_finder,
     make_string_finder, run_test, run_test_ex, run_test_expect_fail, run_test_expect_fail_ex,
-    CppMatcher, EnableAutodiscover, NoSystemHeadersChecker, SetSuppressSystemHeaders,
+    run_test_expect_macro_fail, CppMatcher, EnableAutodiscover, NoSystemHeadersChecker,
+    SetSuppressSystemHeaders, TestBuilderContext, TestError,
 };
+use autocxx_engine::Builder;
 use indoc::indoc;
 use itertools::Itertools;
 use proc_macro2::Span;
 use quote::quote;
 use syn::Token;
+use tempfile::tempdir;
 use test_log::test;
 
 #[test]
@@ -542,6 +545,66 @@ fn test_take_pod_by_ref_and_ptr() {
     run_test(cxx, hdr, rs, &["take_bob_ref", "take_bob_ptr"], &["Bob"]);
 }
 
+#[test]
+fn test_pod_with_array_field() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        struct Bob {
+            uint32_t a[4];
+            uint32_t b[2][2];
+        };
+        inline uint32_t take_bob(const Bob& bob) {
+            return bob.a[3] + bob.b[1][0];
+        }
+    "};
+    let rs = quote! {
+        let a = ffi::Bob { a: [1, 2, 3, 4], b: [[5, 6], [7, 8]] };
+        assert_eq!(ffi::take_bob(&a), 11);
+    };
+    run_test("", hdr, rs, &["take_bob"], &["Bob"]);
+}
+
+#[test]
+fn test_take_array_ref() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        struct Point {
+            uint32_t x;
+            uint32_t y;
+        };
+        inline uint32_t sum(const uint32_t (&values)[4]) {
+            return values[0] + values[1] + values[2] + values[3];
+        }
+        inline uint32_t trace(const uint32_t (&m)[2][2]) {
+            return m[0][0] + m[1][1];
+        }
+        inline void double_all(uint32_t (&values)[3]) {
+            for (auto& value : values) {
+                value *= 2;
+            }
+        }
+        inline uint32_t sum_x(const Point (&points)[2]) {
+            return points[0].x + points[1].x;
+        }
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::sum(&[1, 2, 3, 4]), 10);
+        assert_eq!(ffi::trace(&[[1, 2], [3, 4]]), 5);
+        let mut values = [1, 2, 3];
+        ffi::double_all(&mut values);
+        assert_eq!(values, [2, 4, 6]);
+        let points = [ffi::Point { x: 1, y: 2 }, ffi::Point { x: 3, y: 4 }];
+        assert_eq!(ffi::sum_x(&points), 4);
+    };
+    run_test(
+        "",
+        hdr,
+        rs,
+        &["sum", "trace", "double_all", "sum_x"],
+        &["Point"],
+    );
+}
+
 #[test]
 fn test_return_pod_by_ref_and_ptr() {
     let hdr = indoc! {"
@@ -4350,6 +4413,39 @@ fn test_double_underscores_ignored() {
     run_test("", hdr, rs, &["B"], &[]);
 }
 
+#[test]
+fn test_ignored_dependent_chain() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    struct __FOO {
+        uint32_t a;
+    };
+    struct B {
+        B() :a(1) {}
+        uint32_t take_foo(__FOO a) const {
+            return 3;
+        }
+        uint32_t get_a() const { return 2; }
+        uint32_t a;
+    };
+    "};
+    let rs = quote! {
+        let b = ffi::B::make_unique();
+        assert_eq!(b.get_a(), 2);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! { generate!("B") },
+        None,
+        Some(make_string_finder(
+            ["take_foo", "Dependency chain: __FOO", "Names containing __"].to_vec(),
+        )),
+        None,
+    );
+}
+
 #[test]
 fn test_double_underscore_typedef_ignored() {
     let hdr = indoc! {"
@@ -5075,6 +5171,45 @@ fn test_error_generated_for_array_dependent_method() {
     );
 }
 
+#[test]
+fn test_error_reported_at_directive() {
+    let hdr = indoc! {"
+        inline void present() {}
+    "};
+    let rs = indoc! {r#"
+        use autocxx::prelude::*;
+        include_cpp! {
+            #include "input.h"
+            safety!(unsafe_ffi)
+            generate!("present")
+            generate!("absent")
+        }
+        fn main() {}
+    "#};
+    let stderr = run_test_expect_macro_fail(hdr, rs);
+    assert!(
+        stderr.contains("'absent' did not result in any code being generated"),
+        "{}",
+        stderr
+    );
+    // The error should underline exactly the string literal in the
+    // offending directive.
+    let lines: Vec<_> = stderr.lines().collect();
+    let line = lines
+        .iter()
+        .position(|line| line.ends_with(r#"generate!("absent")"#))
+        .unwrap_or_else(|| panic!("{}", stderr));
+    let literal_start = lines[line].find(r#""absent""#).unwrap();
+    let underline = lines[line + 1].trim_end();
+    assert_eq!(underline.find('^'), Some(literal_start), "{}", stderr);
+    assert_eq!(
+        underline.len() - literal_start,
+        r#""absent""#.len(),
+        "{}",
+        stderr
+    );
+}
+
 #[test]
 fn test_keyword_function() {
     let hdr = indoc! {"
@@ -5248,6 +5383,121 @@ fn test_include_cpp_in_path() {
     do_run_test_manual("", hdr, rs, None, None).unwrap();
 }
 
+#[test]
+fn test_cache_bindings_hit() {
+    let tdir = tempdir().unwrap();
+    std::fs::write(
+        tdir.path().join("input.h"),
+        "#pragma once\ninline int give_int() { return 5; }\n",
+    )
+    .unwrap();
+    let hexathorpe = Token![#](Span::call_site());
+    let rs = quote! {
+        autocxx::include_cpp! {
+            #hexathorpe include "input.h"
+            safety!(unsafe_ffi)
+            generate!("give_int")
+        }
+    };
+    let rs_path = tdir.path().join("input.rs");
+    std::fs::write(&rs_path, rs.to_string()).unwrap();
+    let gendir = tdir.path().join("target");
+    let cache_dir = gendir.join("cache");
+    let build = || {
+        Builder::<TestBuilderContext>::new(&rs_path, [tdir.path()])
+            .custom_gendir(gendir.clone())
+            .cache_bindings(true)
+            .build_listing_files()
+            .unwrap()
+            .1
+    };
+    let cache_entries = || {
+        std::fs::read_dir(&cache_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>()
+    };
+    let generated_rs = build();
+    let entries = cache_entries();
+    assert_eq!(entries.len(), 1);
+    // Mark the cached bindings such that we can tell whether the second
+    // build used them.
+    let cached_rs = entries[0]
+        .join("rs")
+        .join(generated_rs[0].file_name().unwrap());
+    let marked = format!(
+        "{}\n// from the cache\n",
+        std::fs::read_to_string(&cached_rs).unwrap()
+    );
+    std::fs::write(&cached_rs, marked).unwrap();
+    let generated_rs = build();
+    assert_eq!(cache_entries().len(), 1);
+    assert!(std::fs::read_to_string(&generated_rs[0])
+        .unwrap()
+        .contains("// from the cache"));
+}
+
+#[test]
+fn test_multiple_rs_files() {
+    let tdir = tempdir().unwrap();
+    std::fs::write(
+        tdir.path().join("input.h"),
+        indoc! {"
+            #pragma once
+            #include <string>
+            inline std::string get_a() { return \"a\"; }
+            inline std::string get_b() { return \"b\"; }
+        "},
+    )
+    .unwrap();
+    let hexathorpe = Token![#](Span::call_site());
+    let rs_paths: Vec<_> = ["a", "b"]
+        .iter()
+        .map(|suffix| {
+            let mod_name = syn::Ident::new(&format!("ffi_{}", suffix), Span::call_site());
+            let function = format!("get_{}", suffix);
+            let rs = quote! {
+                autocxx::include_cpp! {
+                    #hexathorpe include "input.h"
+                    safety!(unsafe_ffi)
+                    name!(#mod_name)
+                    generate!(#function)
+                }
+            };
+            let rs_path = tdir.path().join(format!("{}.rs", suffix));
+            std::fs::write(&rs_path, rs.to_string()).unwrap();
+            rs_path
+        })
+        .collect();
+    let gendir = tdir.path().join("target");
+    let mut build = Builder::<TestBuilderContext>::new_multi(&rs_paths, [tdir.path()])
+        .custom_gendir(gendir.clone())
+        .build_listing_files()
+        .unwrap();
+    assert_eq!(build.1.len(), 2);
+    let incdir = gendir.join("include");
+    for (idx, suffix) in ["a", "b"].iter().enumerate() {
+        let cxxgen_h =
+            std::fs::read_to_string(incdir.join(format!("file{}/cxxgen.h", idx))).unwrap();
+        assert!(cxxgen_h.contains(&format!("get_{}", suffix)));
+        let autocxxgen_h =
+            std::fs::read_to_string(incdir.join(format!("autocxxgen_ffi_{}.h", suffix))).unwrap();
+        assert!(autocxxgen_h.contains(&format!("#include \"file{}/cxxgen.h\"", idx)));
+    }
+    assert!(!incdir.join("cxxgen.h").exists());
+    // Each file's C++ must compile against its own cxxgen.h.
+    let target = rust_info::get().target_triple.unwrap();
+    build
+        .0
+        .out_dir(&gendir)
+        .host(&target)
+        .target(&target)
+        .opt_level(1)
+        .flag("-std=c++14")
+        .try_compile("autocxx-multi")
+        .unwrap();
+}
+
 #[test]
 fn test_bitset() {
     let hdr = indoc! {"
@@ -5922,6 +6172,321 @@ fn test_box_via_extern_rust_in_mod() {
     );
 }
 
+#[test]
+fn test_std_function_param() {
+    let hdr = indoc! {"
+        #include <functional>
+        #include <cstdint>
+        inline uint32_t call_it(std::function<uint32_t(uint32_t)> f) {
+            return f(3) + f(4);
+        }
+    "};
+    let rs = quote! {
+        let counter = std::rc::Rc::new(std::cell::Cell::new(0u32));
+        let counter2 = counter.clone();
+        assert_eq!(
+            ffi::call_it(Box::new(move |a| {
+                counter2.set(counter2.get() + 1);
+                a * 2
+            })),
+            14
+        );
+        assert_eq!(counter.get(), 2);
+    };
+    run_test("", hdr, rs, &["call_it"], &[]);
+}
+
+#[test]
+fn test_std_function_const_ref_param() {
+    let hdr = indoc! {"
+        #include <functional>
+        #include <cstdint>
+        inline void call_it(const std::function<void(uint32_t)>& f) {
+            f(3);
+        }
+    "};
+    let rs = quote! {
+        let seen = std::rc::Rc::new(std::cell::Cell::new(0u32));
+        let seen2 = seen.clone();
+        ffi::call_it(Box::new(move |a| seen2.set(a)));
+        assert_eq!(seen.get(), 3);
+    };
+    run_test("", hdr, rs, &["call_it"], &[]);
+}
+
+#[test]
+fn test_std_map_iteration() {
+    let hdr = indoc! {"
+        #include <map>
+        #include <cstdint>
+        inline std::map<uint32_t, uint32_t> make_map() {
+            std::map<uint32_t, uint32_t> m;
+            m[2] = 20;
+            m[1] = 10;
+            return m;
+        }
+    "};
+    let rs = quote! {
+        let m = ffi::make_map();
+        assert_eq!(m.len(), 2);
+        assert!(!m.is_empty());
+        assert_eq!(m.get(&1), Some(&10));
+        assert_eq!(m.get(&3), None);
+        let mut pairs = Vec::new();
+        for (k, v) in m.as_ref().unwrap() {
+            pairs.push((*k, *v));
+        }
+        assert_eq!(pairs, vec![(1, 10), (2, 20)]);
+    };
+    run_test("", hdr, rs, &["make_map"], &[]);
+}
+
+#[test]
+fn test_std_set_iteration() {
+    let hdr = indoc! {"
+        #include <set>
+        #include <cstdint>
+        inline std::set<uint32_t> make_set() {
+            return std::set<uint32_t> { 3, 1, 2 };
+        }
+    "};
+    let rs = quote! {
+        let s = ffi::make_set();
+        assert_eq!(s.len(), 3);
+        let contents: Vec<u32> = s.iter().cloned().collect();
+        assert_eq!(contents, vec![1, 2, 3]);
+    };
+    run_test("", hdr, rs, &["make_set"], &[]);
+}
+
+#[test]
+fn test_variadic_instantiations() {
+    let hdr = indoc! {"
+        #include <cstdarg>
+        #include <cstdio>
+        #include <cstdint>
+        inline uint32_t format_len(const char* fmt, ...) {
+            char buf[256];
+            va_list args;
+            va_start(args, fmt);
+            int len = vsnprintf(buf, sizeof(buf), fmt, args);
+            va_end(args);
+            return len;
+        }
+    "};
+    let rs = quote! {
+        let fmt = std::ffi::CString::new("%d %s").unwrap();
+        assert_eq!(unsafe { ffi::format_len(fmt.as_ptr(), 12, "hello") }, 8);
+        let fmt = std::ffi::CString::new("%.1f").unwrap();
+        assert_eq!(unsafe { ffi::format_len1(fmt.as_ptr(), 1.5) }, 3);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            variadic!("format_len", (i32, &str))
+            variadic!("format_len", (f64))
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_c_str_params_and_returns() {
+    let hdr = indoc! {"
+        #include <cstring>
+        #include <cstdint>
+        inline uint32_t c_strlen(const char* s) {
+            return strlen(s);
+        }
+        inline uint32_t str_len(const char* s) {
+            return strlen(s);
+        }
+        inline const char* greeting(bool present) {
+            return present ? \"hello\" : nullptr;
+        }
+        inline const char* farewell() {
+            return \"goodbye\";
+        }
+        inline const char* version() {
+            return \"1.0\";
+        }
+        class Person {
+        public:
+            Person() : name(\"Alice\") {}
+            static const char* species(bool known) {
+                return known ? \"human\" : nullptr;
+            }
+            const char* get_name() const { return name; }
+            const char* rename(const char* new_name) {
+                name = new_name;
+                return name;
+            }
+        private:
+            const char* name;
+        };
+    "};
+    let rs = quote! {
+        let s = std::ffi::CString::new("four").unwrap();
+        assert_eq!(ffi::c_strlen(&s), 4);
+        assert_eq!(ffi::str_len("hello"), 5);
+        let greeting: Option<std::ffi::CString> = ffi::greeting(true);
+        assert_eq!(greeting.unwrap().to_str().unwrap(), "hello");
+        assert!(ffi::greeting(false).is_none());
+        let farewell: std::ffi::CString = ffi::farewell();
+        assert_eq!(farewell.to_str().unwrap(), "goodbye");
+        let version: &'static std::ffi::CStr = ffi::version();
+        assert_eq!(version.to_str().unwrap(), "1.0");
+        let species: Option<&'static std::ffi::CStr> = ffi::Person::species(true);
+        assert_eq!(species.unwrap().to_str().unwrap(), "human");
+        assert!(ffi::Person::species(false).is_none());
+        let mut p = ffi::Person::make_unique();
+        let name: Option<&std::ffi::CStr> = p.get_name();
+        assert_eq!(name.unwrap().to_str().unwrap(), "Alice");
+        let new_name = std::ffi::CString::new("Bob").unwrap();
+        let name: &std::ffi::CStr = p.pin_mut().rename(&new_name);
+        assert_eq!(name.to_str().unwrap(), "Bob");
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("c_strlen")
+            generate!("str_len")
+            generate!("greeting")
+            generate!("farewell")
+            generate!("version")
+            generate!("Person")
+            safety!(unsafe_ffi)
+            c_str!("c_strlen")
+            c_str!("str_len", str)
+            c_str!("greeting")
+            c_str!("farewell", nonnull)
+            c_str!("version", static, nonnull)
+            c_str!("Person::species", static)
+            c_str!("Person::get_name")
+            c_str!("Person::rename", nonnull)
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_strict_skipped_method() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <string>
+        struct A {
+            A() {}
+            uint32_t get() const { return 3; }
+            void take(std::string&&) {}
+        };
+    "};
+    let err = run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {},
+        quote! {
+            generate!("A")
+            strict!()
+        },
+        None,
+        None,
+        None,
+    );
+    match err {
+        TestError::AutoCxx(err) => {
+            let msg = err.to_string();
+            assert!(msg.contains("strict!()"), "{}", msg);
+            assert!(
+                msg.contains("A::take: This function takes an rvalue reference parameter"),
+                "{}",
+                msg
+            );
+            assert!(!msg.contains("A::get"), "{}", msg);
+        }
+        _ => panic!("Expected an autocxx error, got {:?}", err),
+    }
+}
+
+#[test]
+fn test_strict_allow_skip() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <string>
+        struct A {
+            A() {}
+            uint32_t get() const { return 3; }
+            void take(std::string&&) {}
+        };
+    "};
+    let rs = quote! {
+        assert_eq!(ffi::A::make_unique().get(), 3);
+    };
+    run_test_ex(
+        "",
+        hdr,
+        rs,
+        quote! {
+            generate!("A")
+            strict!()
+            allow_skip!("A::take")
+        },
+        None,
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_custom_container_iteration() {
+    let hdr = indoc! {"
+        #include <cstdint>
+        #include <vector>
+        class Buffer {
+        public:
+            Buffer() : data{4, 5, 6} {}
+            const uint32_t* begin() const { return data.data(); }
+            const uint32_t* end() const { return data.data() + data.size(); }
+        private:
+            std::vector<uint32_t> data;
+        };
+        class Names {
+        public:
+            using value_type = uint32_t;
+            struct Iter {
+                const uint32_t* p;
+                const uint32_t& operator*() const { return *p; }
+                Iter& operator++() { ++p; return *this; }
+                bool operator==(const Iter& other) const { return p == other.p; }
+            };
+            Names() : data{7, 8} {}
+            Iter begin() const { return Iter { data.data() }; }
+            Iter end() const { return Iter { data.data() + data.size() }; }
+        private:
+            std::vector<uint32_t> data;
+        };
+    "};
+    let rs = quote! {
+        let buffer = ffi::Buffer::make_unique();
+        let contents: Vec<u32> = buffer.iter().cloned().collect();
+        assert_eq!(contents, vec![4, 5, 6]);
+        let names = ffi::Names::make_unique();
+        let mut contents = Vec::new();
+        for n in names.as_ref().unwrap() {
+            contents.push(*n);
+        }
+        assert_eq!(contents, vec![7, 8]);
+    };
+    run_test("", hdr, rs, &["Buffer", "Names"], &[]);
+}
+
 #[test]
 fn test_extern_rust_fn() {
     let hdr = indoc! {"
@@ -6013,39 +6578,57 @@ fn test_pv_subclass_mut() {
 }
 
 #[test]
-fn test_pv_subclass_const() {
+fn test_pv_subclass_multiple_superclasses() {
     let hdr = indoc! {"
     #include <cstdint>
 
     class Observer {
     public:
         Observer() {}
-        virtual void foo() const = 0;
+        virtual uint32_t foo() const = 0;
         virtual ~Observer() {}
     };
-    inline void bar() {}
+    class Listener {
+    public:
+        Listener() {}
+        virtual uint32_t bar() const { return 1; }
+        virtual ~Listener() {}
+    };
+    inline uint32_t call_foo(const Observer& obs) { return obs.foo(); }
+    inline uint32_t call_bar(const Listener& lis) { return lis.bar(); }
     "};
     run_test_ex(
         "",
         hdr,
         quote! {
-            MyObserver::new_rust_owned(MyObserver { a: 3, cpp_peer: Default::default() });
+            let obs = MyObserver::new_rust_owned(MyObserver { a: 3, cpp_peer: Default::default() });
+            assert_eq!(ffi::call_foo(obs.borrow().as_ref()), 3);
+            let listener: &ffi::Listener = obs.borrow().as_ref();
+            assert_eq!(ffi::call_bar(listener), 4);
         },
         quote! {
-            generate!("bar")
+            generate!("call_foo")
+            generate!("call_bar")
             subclass!("Observer",MyObserver)
+            subclass!("Listener",MyObserver)
         },
         None,
         None,
         Some(quote! {
             use autocxx::subclass::CppSubclass;
-            use ffi::Observer_methods;
+            use ffi::{Listener_methods, Listener_supers, Observer_methods};
             #[autocxx::subclass::subclass]
             pub struct MyObserver {
                 a: u32
             }
             impl Observer_methods for MyObserver {
-                fn foo(&self) {
+                fn foo(&self) -> u32 {
+                    self.a
+                }
+            }
+            impl Listener_methods for MyObserver {
+                fn bar(&self) -> u32 {
+                    self.a + self.bar_super()
                 }
             }
         }),
@@ -6053,34 +6636,335 @@ fn test_pv_subclass_const() {
 }
 
 #[test]
-fn test_pv_subclass_calls_impossible() {
+fn test_pv_subclass_error_policies() {
     let hdr = indoc! {"
     #include <cstdint>
+    #include <exception>
 
     class Observer {
     public:
         Observer() {}
-        virtual void foo() const = 0;
+        virtual uint32_t foo() const = 0;
         virtual ~Observer() {}
     };
-    inline void bar() {}
+    inline uint32_t call_foo(const Observer& obs) {
+        try {
+            return obs.foo();
+        } catch (const std::exception&) {
+            return 99;
+        }
+    }
     "};
-    run_test_expect_fail_ex(
+    run_test_ex(
         "",
         hdr,
         quote! {
-            MyObserver::new_rust_owned(MyObserver { a: 3, cpp_peer: Default::default() });
+            let obs = MyDefaultingObserver::new_rust_owned(MyDefaultingObserver::default());
+            assert_eq!(ffi::call_foo(obs.borrow().as_ref()), 0);
+            let obs = MyThrowingObserver::new_rust_owned(MyThrowingObserver::default());
+            assert_eq!(ffi::call_foo(obs.borrow().as_ref()), 99);
+            let obs = MyThrowingObserver::new_cpp_owned(MyThrowingObserver::default());
+            assert_eq!(ffi::call_foo(obs.as_ref().unwrap().As_Observer()), 99);
         },
         quote! {
-            generate!("bar")
-            subclass!("Observer",MyObserver)
+            generate!("call_foo")
+            subclass!("Observer",MyDefaultingObserver,on_error = return_default)
+            subclass!("Observer",MyThrowingObserver,on_error = exception)
         },
         None,
         None,
         Some(quote! {
-            use autocxx::subclass::CppSubclass;
-            use ffi::Observer_methods;
-            #[autocxx::subclass::subclass]
+            use autocxx::subclass::prelude::*;
+            #[subclass]
+            #[derive(Default)]
+            pub struct MyDefaultingObserver;
+            impl ffi::Observer_methods for MyDefaultingObserver {
+                fn foo(&self) -> u32 {
+                    panic!("foo failed")
+                }
+            }
+            #[subclass]
+            #[derive(Default)]
+            pub struct MyThrowingObserver;
+            impl ffi::Observer_methods for MyThrowingObserver {
+                fn foo(&self) -> u32 {
+                    panic!("foo failed")
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_pv_subclass_cpp_namespace() {
+    let hdr = indoc! {"
+    #include <cstdint>
+
+    namespace a {
+    class Observer {
+    public:
+        Observer() {}
+        virtual uint32_t foo() const = 0;
+        virtual ~Observer() {}
+    };
+    inline uint32_t call_foo(const Observer& obs) {
+        return obs.foo();
+    }
+    }
+    "};
+    run_test_ex(
+        "",
+        hdr,
+        quote! {
+            let obs: cxx::UniquePtr<ffi::my::peers::MyObserverCpp> =
+                MyObserver::new_cpp_owned(MyObserver::default());
+            assert_eq!(ffi::a::call_foo(obs.as_ref().unwrap().As_Observer()), 4);
+        },
+        quote! {
+            generate!("a::call_foo")
+            subclass!("a::Observer",MyObserver,cpp_namespace = "my::peers")
+        },
+        None,
+        None,
+        Some(quote! {
+            use autocxx::subclass::prelude::*;
+            #[subclass(cpp_namespace("my::peers"))]
+            #[derive(Default)]
+            pub struct MyObserver;
+            impl ffi::a::Observer_methods for MyObserver {
+                fn foo(&self) -> u32 {
+                    4
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_pv_subclass_cpp_factory() {
+    let hdr = indoc! {"
+    #include <cstdint>
+
+    class Observer {
+    public:
+        Observer() {}
+        virtual uint32_t foo() const = 0;
+        virtual ~Observer() {}
+    };
+    inline uint32_t call_foo(const Observer& obs) {
+        return obs.foo();
+    }
+    uint32_t make_and_call();
+    "};
+    let cxx = indoc! {"
+    #include \"MyObserverCpp.h\"
+    uint32_t make_and_call() {
+        auto obs = MyObserverCpp::create();
+        return call_foo(obs->As_Observer());
+    }
+    "};
+    run_test_ex(
+        cxx,
+        hdr,
+        quote! {
+            assert_eq!(ffi::make_and_call(), 4);
+        },
+        quote! {
+            generate!("make_and_call")
+            subclass!("Observer",MyObserver,cpp_factory)
+        },
+        None,
+        None,
+        Some(quote! {
+            use autocxx::subclass::prelude::*;
+            #[subclass(cpp_factory)]
+            #[derive(Default)]
+            pub struct MyObserver;
+            impl ffi::Observer_methods for MyObserver {
+                fn foo(&self) -> u32 {
+                    4
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_pv_subclass_cpp_factory_namespace() {
+    let hdr = indoc! {"
+    #include <cstdint>
+
+    class Observer {
+    public:
+        Observer() {}
+        virtual uint32_t foo() const = 0;
+        virtual ~Observer() {}
+    };
+    inline uint32_t call_foo(const Observer& obs) {
+        return obs.foo();
+    }
+    uint32_t make_and_call();
+    "};
+    let cxx = indoc! {"
+    #include \"my.peers.MyObserverCpp.h\"
+    uint32_t make_and_call() {
+        auto obs = my::peers::MyObserverCpp::create();
+        return call_foo(obs->As_Observer());
+    }
+    "};
+    run_test_ex(
+        cxx,
+        hdr,
+        quote! {
+            assert_eq!(ffi::make_and_call(), 4);
+        },
+        quote! {
+            generate!("make_and_call")
+            subclass!("Observer",MyObserver,cpp_namespace = "my::peers",cpp_factory)
+        },
+        None,
+        None,
+        Some(quote! {
+            use autocxx::subclass::prelude::*;
+            #[subclass(cpp_namespace("my::peers"), cpp_factory)]
+            #[derive(Default)]
+            pub struct MyObserver;
+            impl ffi::Observer_methods for MyObserver {
+                fn foo(&self) -> u32 {
+                    4
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_pv_subclass_thread_safe() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    #include <thread>
+
+    class Observer {
+    public:
+        Observer() {}
+        virtual void foo() = 0;
+        virtual ~Observer() {}
+    };
+    inline void call_on_other_thread(Observer& obs) {
+        std::thread t([&obs]() { obs.foo(); });
+        t.join();
+    }
+    "};
+    run_test_ex(
+        "",
+        hdr,
+        quote! {
+            let obs = MyObserver::new_rust_owned_thread_safe(
+                MyObserver { a: 3, cpp_peer: Default::default() }
+            );
+            // Don't hold the lock while C++ calls back into the object
+            // from its own thread.
+            let peer: *mut ffi::Observer = unsafe {
+                obs.lock().unwrap().pin_mut().get_unchecked_mut()
+            };
+            ffi::call_on_other_thread(unsafe { std::pin::Pin::new_unchecked(&mut *peer) });
+            assert_eq!(obs.lock().unwrap().a, 4);
+            let handle = std::thread::spawn(move || obs.lock().unwrap().a);
+            assert_eq!(handle.join().unwrap(), 4);
+        },
+        quote! {
+            generate!("call_on_other_thread")
+            subclass!("Observer",MyObserver,thread_safe)
+        },
+        None,
+        None,
+        Some(quote! {
+            use autocxx::subclass::CppSubclassThreadSafe;
+            use ffi::Observer_methods;
+            #[autocxx::subclass::subclass(thread_safe)]
+            pub struct MyObserver {
+                a: u32
+            }
+            // Observer has no state of its own.
+            unsafe impl Send for ffi::MyObserverCpp {}
+            impl Observer_methods for MyObserver {
+                fn foo(&mut self) {
+                    self.a += 1;
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_pv_subclass_const() {
+    let hdr = indoc! {"
+    #include <cstdint>
+
+    class Observer {
+    public:
+        Observer() {}
+        virtual void foo() const = 0;
+        virtual ~Observer() {}
+    };
+    inline void bar() {}
+    "};
+    run_test_ex(
+        "",
+        hdr,
+        quote! {
+            MyObserver::new_rust_owned(MyObserver { a: 3, cpp_peer: Default::default() });
+        },
+        quote! {
+            generate!("bar")
+            subclass!("Observer",MyObserver)
+        },
+        None,
+        None,
+        Some(quote! {
+            use autocxx::subclass::CppSubclass;
+            use ffi::Observer_methods;
+            #[autocxx::subclass::subclass]
+            pub struct MyObserver {
+                a: u32
+            }
+            impl Observer_methods for MyObserver {
+                fn foo(&self) {
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_pv_subclass_calls_impossible() {
+    let hdr = indoc! {"
+    #include <cstdint>
+
+    class Observer {
+    public:
+        Observer() {}
+        virtual void foo() const = 0;
+        virtual ~Observer() {}
+    };
+    inline void bar() {}
+    "};
+    run_test_expect_fail_ex(
+        "",
+        hdr,
+        quote! {
+            MyObserver::new_rust_owned(MyObserver { a: 3, cpp_peer: Default::default() });
+        },
+        quote! {
+            generate!("bar")
+            subclass!("Observer",MyObserver)
+        },
+        None,
+        None,
+        Some(quote! {
+            use autocxx::subclass::CppSubclass;
+            use ffi::Observer_methods;
+            #[autocxx::subclass::subclass]
             pub struct MyObserver {
                 a: u32
             }
@@ -6430,6 +7314,211 @@ fn test_pv_protected_method() {
     );
 }
 
+#[test]
+fn test_pv_protected_nonvirtual_method() {
+    let hdr = indoc! {"
+    #include <cstdint>
+
+    class Observer {
+    public:
+        Observer() : total(0) {}
+        virtual uint32_t foo(uint32_t a) { return add(a); }
+        uint32_t get_total() const { return total; }
+        virtual ~Observer() {}
+    protected:
+        uint32_t add(uint32_t a) { total += a; return total; }
+        uint32_t doubled(uint32_t a) const { return a * 2; }
+    private:
+        uint32_t total;
+    };
+    "};
+    run_test_ex(
+        "",
+        hdr,
+        quote! {
+            let obs = MyObserver::new_rust_owned(MyObserver { cpp_peer: Default::default() });
+            assert_eq!(obs.borrow_mut().foo(3), 6);
+            assert_eq!(obs.borrow().as_ref().get_total(), 6);
+        },
+        quote! {
+            subclass!("Observer",MyObserver)
+        },
+        None,
+        None,
+        Some(quote! {
+            use autocxx::subclass::CppSubclass;
+            use ffi::{Observer_methods, Observer_supers};
+            #[autocxx::subclass::subclass]
+            pub struct MyObserver {}
+            impl Observer_methods for MyObserver {
+                fn foo(&mut self, a: u32) -> u32 {
+                    let a = self.doubled_super(a);
+                    self.add_super(a)
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_pv_subclass_shared_owned() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    #include <memory>
+    #include <vector>
+
+    class Listener {
+    public:
+        Listener() {}
+        virtual uint32_t notify(uint32_t a) const = 0;
+        virtual ~Listener() {}
+    };
+
+    class Notifier {
+    public:
+        void add_listener(std::shared_ptr<Listener> l) { listeners.push_back(l); }
+        uint32_t notify_all(uint32_t a) const {
+            uint32_t total = 0;
+            for (auto& l : listeners) {
+                total += l->notify(a);
+            }
+            return total;
+        }
+        void clear() { listeners.clear(); }
+    private:
+        std::vector<std::shared_ptr<Listener>> listeners;
+    };
+    "};
+    run_test_ex(
+        "",
+        hdr,
+        quote! {
+            let mut notifier = ffi::Notifier::make_unique();
+            let listener = MyListener::new_shared_owned(MyListener { offset: 2, cpp_peer: Default::default() });
+            notifier.pin_mut().add_listener(listener.upcast());
+            notifier.pin_mut().add_listener(listener.upcast());
+            drop(listener);
+            assert_eq!(notifier.notify_all(3), 10);
+            notifier.pin_mut().clear();
+        },
+        quote! {
+            generate!("Notifier")
+            subclass!("Listener",MyListener)
+        },
+        None,
+        None,
+        Some(quote! {
+            use autocxx::subclass::prelude::*;
+            #[subclass]
+            pub struct MyListener {
+                offset: u32,
+            }
+            impl ffi::Listener_methods for MyListener {
+                fn notify(&self, a: u32) -> u32 {
+                    a + self.offset
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_pv_subclass_thread_safe_shared_owned() {
+    let hdr = indoc! {"
+    #include <cstdint>
+    #include <memory>
+    #include <thread>
+
+    class Listener {
+    public:
+        Listener() {}
+        virtual uint32_t notify(uint32_t a) const = 0;
+        virtual ~Listener() {}
+    };
+    inline uint32_t notify_on_other_thread(std::shared_ptr<Listener> l, uint32_t a) {
+        uint32_t result = 0;
+        std::thread t([l, a, &result]() { result = l->notify(a); });
+        t.join();
+        return result;
+    }
+    "};
+    run_test_ex(
+        "",
+        hdr,
+        quote! {
+            let listener = MyListener::new_shared_owned_thread_safe(MyListener { offset: 2, cpp_peer: Default::default() });
+            assert_eq!(ffi::notify_on_other_thread(listener.upcast(), 3), 5);
+        },
+        quote! {
+            generate!("notify_on_other_thread")
+            subclass!("Listener",MyListener,thread_safe)
+        },
+        None,
+        None,
+        Some(quote! {
+            use autocxx::subclass::prelude::*;
+            #[subclass(thread_safe)]
+            pub struct MyListener {
+                offset: u32,
+            }
+            impl ffi::Listener_methods for MyListener {
+                fn notify(&self, a: u32) -> u32 {
+                    a + self.offset
+                }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_pv_subclass_thread_safe_cpp_factory() {
+    let hdr = indoc! {"
+    #include <cstdint>
+
+    class Observer {
+    public:
+        Observer() {}
+        virtual uint32_t foo() const = 0;
+        virtual ~Observer() {}
+    };
+    inline uint32_t call_foo(const Observer& obs) {
+        return obs.foo();
+    }
+    uint32_t make_and_call();
+    "};
+    let cxx = indoc! {"
+    #include \"MyObserverCpp.h\"
+    uint32_t make_and_call() {
+        auto obs = MyObserverCpp::create();
+        return call_foo(obs->As_Observer());
+    }
+    "};
+    run_test_ex(
+        cxx,
+        hdr,
+        quote! {
+            assert_eq!(ffi::make_and_call(), 4);
+        },
+        quote! {
+            generate!("make_and_call")
+            subclass!("Observer",MyObserver,thread_safe,cpp_factory)
+        },
+        None,
+        None,
+        Some(quote! {
+            use autocxx::subclass::prelude::*;
+            #[subclass(thread_safe, cpp_factory)]
+            #[derive(Default)]
+            pub struct MyObserver;
+            impl ffi::Observer_methods for MyObserver {
+                fn foo(&self) -> u32 {
+                    4
+                }
+            }
+        }),
+    );
+}
+
 #[test]
 fn test_pv_subclass_allocation_not_self_owned() {
     let hdr = indoc! {"
@@ -7085,6 +8174,73 @@ fn test_pv_subclass_constructors() {
     );
 }
 
+#[test]
+fn test_pv_subclass_constructors_with_args() {
+    let hdr = indoc! {"
+    #include <cstdint>
+
+    struct Foo {
+        uint32_t a;
+    };
+
+    class Observer {
+    public:
+        Observer(uint32_t v) : val(v) {}
+        Observer(const Foo& foo, uint32_t v) : val(foo.a + v) {}
+        Observer(const uint32_t* v) : val(*v) {}
+        uint32_t get_val() const { return val; }
+        virtual uint32_t foo() const { return 0; }
+        virtual ~Observer() {}
+    private:
+        uint32_t val;
+    };
+    inline uint32_t call_foo(const Observer& obs) { return obs.foo(); }
+    "};
+    run_test_ex(
+        "",
+        hdr,
+        quote! {
+            let obs = MyObserver::new_rust_owned_with(MyObserver::default(), (3u32,));
+            assert_eq!(obs.borrow().as_ref().get_val(), 3);
+            assert_eq!(ffi::call_foo(obs.borrow().as_ref()), 4);
+            let foo = ffi::Foo { a: 10 };
+            let obs = MyObserver::new_rust_owned_with(MyObserver::default(), (&foo, 2u32));
+            assert_eq!(obs.borrow().as_ref().get_val(), 12);
+            let obs = MyObserver::new_cpp_owned_with(MyObserver::default(), (5u32,));
+            assert_eq!(obs.get_val(), 5);
+            let v = 7u32;
+            let args = unsafe { UnsafeConstructorArgs::new((&v as *const u32,)) };
+            let obs = MyObserver::new_rust_owned_with(MyObserver::default(), args);
+            assert_eq!(obs.borrow().as_ref().get_val(), 7);
+            let obs = MyObserver::default_rust_owned();
+            assert_eq!(obs.borrow().as_ref().get_val(), 1);
+        },
+        quote! {
+            generate!("call_foo")
+            generate_pod!("Foo")
+            subclass!("Observer",MyObserver)
+        },
+        None,
+        None,
+        Some(quote! {
+            use autocxx::subclass::prelude::*;
+            #[subclass]
+            #[derive(Default)]
+            pub struct MyObserver;
+            impl ffi::Observer_methods for MyObserver {
+                fn foo(&self) -> u32 {
+                    self.peer().get_val() + 1
+                }
+            }
+            impl CppPeerConstructor<ffi::MyObserverCpp> for MyObserver {
+                fn make_peer(&mut self, peer_holder: CppSubclassRustPeerHolder<Self>) -> cxx::UniquePtr<ffi::MyObserverCpp> {
+                    Self::make_peer_with(peer_holder, (1u32,))
+                }
+            }
+        }),
+    );
+}
+
 #[test]
 fn test_pv_subclass_fancy_constructor() {
     let hdr = indoc! {"
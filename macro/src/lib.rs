@@ -25,6 +25,9 @@ use syn::{parse_macro_input, parse_quote, Fields, Item, ItemStruct, Visibility};
 #[proc_macro]
 pub fn include_cpp_impl(input: TokenStream) -> TokenStream {
     let include_cpp = parse_macro_input!(input as IncludeCpp);
+    if let Some(err) = include_cpp.directive_error() {
+        abort!(err);
+    }
     TokenStream::from(include_cpp.generate_rs())
 }
 
@@ -40,10 +43,19 @@ pub fn subclass(attr: TokenStream, item: TokenStream) -> TokenStream {
         use syn::spanned::Spanned;
         abort!(s.vis.span(), "Rust subclasses of C++ types must by public");
     }
+    let subclass_attrs: SubclassAttrs = syn::parse(attr)
+        .unwrap_or_else(|_| abort!(Span::call_site(), "Unable to parse attributes"));
     let id = &s.ident;
     let cpp_ident = Ident::new(&format!("{}Cpp", id), Span::call_site());
+    // The C++ peer is found in the ffi mod for its C++ namespace.
+    let cpp_namespace = subclass_attrs
+        .cpp_namespace
+        .iter()
+        .flat_map(|ns| ns.split("::"))
+        .map(|seg| Ident::new(seg, Span::call_site()));
+    let cpp_path = quote! { ffi:: #(#cpp_namespace::)* #cpp_ident };
     let input = quote! {
-        cpp_peer: autocxx::subclass::CppSubclassCppPeerHolder<ffi:: #cpp_ident>
+        cpp_peer: autocxx::subclass::CppSubclassCppPeerHolder<#cpp_path>
     };
     let parser = syn::Field::parse_named;
     let new_field = parser.parse2(input).unwrap();
@@ -59,11 +71,16 @@ pub fn subclass(attr: TokenStream, item: TokenStream) -> TokenStream {
         }),
         _ => abort!(Span::call_site(), "Expect a struct with named fields - use struct A{} or struct A; as opposed to struct A()"),
     };
-    let subclass_attrs: SubclassAttrs = syn::parse(attr)
-        .unwrap_or_else(|_| abort!(Span::call_site(), "Unable to parse attributes"));
     let self_owned_bit = if subclass_attrs.self_owned {
         Some(quote! {
-            impl autocxx::subclass::CppSubclassSelfOwned<ffi::#cpp_ident> for #id {}
+            impl autocxx::subclass::CppSubclassSelfOwned<#cpp_path> for #id {}
+        })
+    } else {
+        None
+    };
+    let thread_safe_bit = if subclass_attrs.thread_safe {
+        Some(quote! {
+            impl autocxx::subclass::CppSubclassThreadSafe<#cpp_path> for #id {}
         })
     } else {
         None
@@ -71,16 +88,17 @@ pub fn subclass(attr: TokenStream, item: TokenStream) -> TokenStream {
     let toks = quote! {
         #s
 
-        impl autocxx::subclass::CppSubclass<ffi::#cpp_ident> for #id {
-            fn peer_holder_mut(&mut self) -> &mut autocxx::subclass::CppSubclassCppPeerHolder<ffi::#cpp_ident> {
+        impl autocxx::subclass::CppSubclass<#cpp_path> for #id {
+            fn peer_holder_mut(&mut self) -> &mut autocxx::subclass::CppSubclassCppPeerHolder<#cpp_path> {
                 &mut self.cpp_peer
             }
-            fn peer_holder(&self) -> &autocxx::subclass::CppSubclassCppPeerHolder<ffi::#cpp_ident> {
+            fn peer_holder(&self) -> &autocxx::subclass::CppSubclassCppPeerHolder<#cpp_path> {
                 &self.cpp_peer
             }
         }
 
         #self_owned_bit
+        #thread_safe_bit
     };
     toks.into()
 }
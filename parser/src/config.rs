@@ -18,12 +18,12 @@ use proc_macro2::Span;
 use quote::ToTokens;
 use syn::{
     parse::{Parse, ParseStream},
-    LitStr, Signature, Token,
+    LitStr, Signature, Token, Type,
 };
 use syn::{Ident, Result as ParseResult};
 
 use crate::{
-    directives::{EXTERN_RUST_TYPE, SUBCLASS},
+    directives::{C_STR, EXTERN_RUST_TYPE, SUBCLASS, VARIADIC},
     RustPath,
 };
 
@@ -122,6 +122,65 @@ impl Default for Allowlist {
 pub struct Subclass {
     pub superclass: String,
     pub subclass: Ident,
+    /// Whether the Rust side is held in an `Arc<Mutex<_>>` so that
+    /// C++ may call into it from any thread.
+    pub thread_safe: bool,
+    pub error_policy: SubclassErrorPolicy,
+    /// The C++ namespace in which the generated C++ peer class should
+    /// live, if not the root namespace.
+    pub cpp_namespace: Option<String>,
+    /// Whether C++ should be able to construct this subclass itself,
+    /// using its `Default` implementation.
+    pub cpp_factory: bool,
+}
+
+/// What a Rust subclass should do when a call from C++ can't be
+/// completed, because the Rust object has already been dropped, is
+/// already borrowed (typically due to a re-entrant call) or panics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubclassErrorPolicy {
+    /// Print an explanation and abort the process.
+    Abort,
+    /// Print an explanation and return `Default::default()` to C++.
+    ReturnDefault,
+    /// Throw a C++ exception.
+    Exception,
+}
+
+#[allow(clippy::derivable_impls)] // nightly-only
+impl Default for SubclassErrorPolicy {
+    fn default() -> Self {
+        SubclassErrorPolicy::Abort
+    }
+}
+
+impl Parse for SubclassErrorPolicy {
+    fn parse(input: ParseStream) -> ParseResult<Self> {
+        let policy: Ident = input.parse()?;
+        if policy == "abort" {
+            Ok(SubclassErrorPolicy::Abort)
+        } else if policy == "return_default" {
+            Ok(SubclassErrorPolicy::ReturnDefault)
+        } else if policy == "exception" {
+            Ok(SubclassErrorPolicy::Exception)
+        } else {
+            Err(syn::Error::new(
+                policy.span(),
+                "expected abort, return_default or exception",
+            ))
+        }
+    }
+}
+
+#[cfg(feature = "reproduction_case")]
+impl ToTokens for SubclassErrorPolicy {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        tokens.extend(match self {
+            SubclassErrorPolicy::Abort => quote! { abort },
+            SubclassErrorPolicy::ReturnDefault => quote! { return_default },
+            SubclassErrorPolicy::Exception => quote! { exception },
+        })
+    }
 }
 
 pub struct RustFun {
@@ -138,6 +197,138 @@ impl std::fmt::Debug for RustFun {
     }
 }
 
+/// A fixed-arity instantiation of a C variadic function, requested
+/// using `variadic!`. `extra_params` are the types of the arguments
+/// which will be passed in place of the `...`.
+pub struct Variadic {
+    pub function: String,
+    pub extra_params: Vec<Type>,
+}
+
+impl Parse for Variadic {
+    fn parse(input: ParseStream) -> ParseResult<Self> {
+        let function: LitStr = input.parse()?;
+        input.parse::<syn::token::Comma>()?;
+        let extra_params = match input.parse::<Type>()? {
+            Type::Tuple(tuple) => tuple.elems.into_iter().collect(),
+            Type::Paren(paren) => vec![*paren.elem],
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "expected a parenthesized list of parameter types",
+                ))
+            }
+        };
+        Ok(Variadic {
+            function: function.value(),
+            extra_params,
+        })
+    }
+}
+
+impl std::fmt::Debug for Variadic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Variadic")
+            .field("function", &self.function)
+            .field(
+                "extra_params",
+                &self
+                    .extra_params
+                    .iter()
+                    .map(|ty| ty.to_token_stream().to_string())
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// How `const char*` parameters should appear in Rust, per `c_str!`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CStrParamPolicy {
+    /// `&CStr`, which is already NUL-terminated.
+    CStr,
+    /// `&str`, which C++ copies into a NUL-terminated string.
+    Str,
+}
+
+/// How `const char*` return values should appear in Rust, per `c_str!`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CStrReturnPolicy {
+    /// `Option<&CStr>`, which is `None` if C++ returns `nullptr`.
+    Nullable,
+    /// `&CStr`; C++ promises never to return `nullptr`.
+    NonNull,
+}
+
+/// A request to represent `const char*`s in a given function's
+/// signature as Rust strings, using `c_str!`.
+#[derive(Debug)]
+pub struct CStrPolicy {
+    pub function: String,
+    pub params: CStrParamPolicy,
+    pub returns: CStrReturnPolicy,
+    /// The user promises that any string returned lives forever, so it
+    /// may be returned as a `&'static CStr`.
+    pub is_static: bool,
+}
+
+impl Parse for CStrPolicy {
+    fn parse(input: ParseStream) -> ParseResult<Self> {
+        let function: LitStr = input.parse()?;
+        let mut params = CStrParamPolicy::CStr;
+        let mut returns = CStrReturnPolicy::Nullable;
+        let mut is_static = false;
+        while input.parse::<Option<syn::token::Comma>>()?.is_some() {
+            if input.parse::<Option<syn::token::Static>>()?.is_some() {
+                is_static = true;
+                continue;
+            }
+            let option: Ident = input.parse()?;
+            if option == "cstr" {
+                params = CStrParamPolicy::CStr;
+            } else if option == "str" {
+                params = CStrParamPolicy::Str;
+            } else if option == "nullable" {
+                returns = CStrReturnPolicy::Nullable;
+            } else if option == "nonnull" {
+                returns = CStrReturnPolicy::NonNull;
+            } else {
+                return Err(syn::Error::new(
+                    option.span(),
+                    "expected cstr, str, nullable, nonnull or static",
+                ));
+            }
+        }
+        Ok(CStrPolicy {
+            function: function.value(),
+            params,
+            returns,
+            is_static,
+        })
+    }
+}
+
+#[cfg(feature = "reproduction_case")]
+impl ToTokens for CStrPolicy {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let function = &self.function;
+        let params = match self.params {
+            CStrParamPolicy::CStr => quote! { cstr },
+            CStrParamPolicy::Str => quote! { str },
+        };
+        let returns = match self.returns {
+            CStrReturnPolicy::Nullable => quote! { nullable },
+            CStrReturnPolicy::NonNull => quote! { nonnull },
+        };
+        let is_static = if self.is_static {
+            Some(quote! { , static })
+        } else {
+            None
+        };
+        tokens.extend(quote! { #function, #params, #returns #is_static });
+    }
+}
+
 #[derive(Debug)]
 pub struct IncludeCppConfig {
     pub inclusions: Vec<String>,
@@ -152,6 +343,11 @@ pub struct IncludeCppConfig {
     pub rust_types: Vec<RustPath>,
     pub subclasses: Vec<Subclass>,
     pub extern_rust_funs: Vec<RustFun>,
+    pub variadics: Vec<Variadic>,
+    pub c_str_policies: Vec<CStrPolicy>,
+    strict: bool,
+    allowed_skips: Vec<String>,
+    directive_spans: Vec<(String, Span)>,
 }
 
 impl Parse for IncludeCppConfig {
@@ -173,6 +369,11 @@ impl Parse for IncludeCppConfig {
         let mut mod_name = None;
         let mut subclasses = Vec::new();
         let mut extern_rust_funs = Vec::new();
+        let mut variadics = Vec::new();
+        let mut c_str_policies = Vec::new();
+        let mut strict = false;
+        let mut allowed_skips = Vec::new();
+        let mut directive_spans = Vec::new();
 
         while !input.is_empty() {
             let has_hexathorpe = input.parse::<Option<syn::token::Pound>>()?.is_some();
@@ -189,22 +390,26 @@ impl Parse for IncludeCppConfig {
                     let args;
                     syn::parenthesized!(args in input);
                     let generate: syn::LitStr = args.parse()?;
+                    directive_spans.push((generate.value(), generate.span()));
                     allowlist.push(generate)?;
                 } else if ident == "generate_pod" {
                     let args;
                     syn::parenthesized!(args in input);
                     let generate_pod: syn::LitStr = args.parse()?;
+                    directive_spans.push((generate_pod.value(), generate_pod.span()));
                     pod_requests.push(generate_pod.value());
                     allowlist.push(generate_pod)?;
                 } else if ident == "pod" {
                     let args;
                     syn::parenthesized!(args in input);
                     let pod: syn::LitStr = args.parse()?;
+                    directive_spans.push((pod.value(), pod.span()));
                     pod_requests.push(pod.value());
                 } else if ident == "block" {
                     let args;
                     syn::parenthesized!(args in input);
                     let generate: syn::LitStr = args.parse()?;
+                    directive_spans.push((generate.value(), generate.span()));
                     blocklist.push(generate.value());
                 } else if ident == "rust_type" || ident == EXTERN_RUST_TYPE {
                     let args;
@@ -217,9 +422,37 @@ impl Parse for IncludeCppConfig {
                     let superclass: syn::LitStr = args.parse()?;
                     args.parse::<syn::token::Comma>()?;
                     let subclass: syn::Ident = args.parse()?;
+                    let mut thread_safe = false;
+                    let mut error_policy = SubclassErrorPolicy::default();
+                    let mut cpp_namespace = None;
+                    let mut cpp_factory = false;
+                    while args.parse::<Option<syn::token::Comma>>()?.is_some() {
+                        let option: syn::Ident = args.parse()?;
+                        if option == "thread_safe" {
+                            thread_safe = true;
+                        } else if option == "cpp_factory" {
+                            cpp_factory = true;
+                        } else if option == "on_error" {
+                            args.parse::<Token![=]>()?;
+                            error_policy = args.parse()?;
+                        } else if option == "cpp_namespace" {
+                            args.parse::<Token![=]>()?;
+                            let ns: syn::LitStr = args.parse()?;
+                            cpp_namespace = Some(ns.value());
+                        } else {
+                            return Err(syn::Error::new(
+                                option.span(),
+                                "expected thread_safe, cpp_factory, on_error or cpp_namespace",
+                            ));
+                        }
+                    }
                     subclasses.push(Subclass {
                         superclass: superclass.value(),
                         subclass,
+                        thread_safe,
+                        error_policy,
+                        cpp_namespace,
+                        cpp_factory,
                     });
                 } else if ident == "parse_only" {
                     parse_only = true;
@@ -249,6 +482,22 @@ impl Parse for IncludeCppConfig {
                     args.parse::<syn::token::Comma>()?;
                     let sig: syn::Signature = args.parse()?;
                     extern_rust_funs.push(RustFun { path, sig });
+                } else if ident == VARIADIC {
+                    let args;
+                    syn::parenthesized!(args in input);
+                    variadics.push(args.parse()?);
+                } else if ident == C_STR {
+                    let args;
+                    syn::parenthesized!(args in input);
+                    c_str_policies.push(args.parse()?);
+                } else if ident == "strict" {
+                    strict = true;
+                    swallow_parentheses(&input, &ident)?;
+                } else if ident == "allow_skip" {
+                    let args;
+                    syn::parenthesized!(args in input);
+                    let allow_skip: syn::LitStr = args.parse()?;
+                    allowed_skips.push(allow_skip.value());
                 } else {
                     return Err(syn::Error::new(
                         ident.span(),
@@ -274,6 +523,11 @@ impl Parse for IncludeCppConfig {
             mod_name,
             subclasses,
             extern_rust_funs,
+            variadics,
+            c_str_policies,
+            strict,
+            allowed_skips,
+            directive_spans,
         })
     }
 }
@@ -319,6 +573,38 @@ impl IncludeCppConfig {
         }
     }
 
+    /// Whether it's an error for anything in the [`Self::must_generate_list`]
+    /// (or any of its methods) to be skipped.
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn set_strict(&mut self) {
+        self.strict = true;
+    }
+
+    /// Generate everything found in the headers, as if `generate_all!()`
+    /// had been specified instead of any `generate!` directives.
+    pub fn set_generate_all(&mut self) {
+        self.allowlist = Allowlist::All;
+    }
+
+    /// Whether `allow_skip!` permits the given item to be skipped
+    /// even in strict mode.
+    pub fn is_skip_allowed(&self, cpp_name: &str) -> bool {
+        self.allowed_skips.iter().any(|item| item == cpp_name)
+    }
+
+    /// The location of the string literal naming this item in a
+    /// `generate!`, `generate_pod!`, `pod!` or `block!` directive, such
+    /// that errors concerning the directive can be reported there.
+    pub fn get_directive_span(&self, item: &str) -> Option<Span> {
+        self.directive_spans
+            .iter()
+            .find(|(directive, _)| directive == item)
+            .map(|(_, span)| *span)
+    }
+
     /// The allowlist of items to be passed into bindgen, if any.
     pub fn bindgen_allowlist(&self) -> Option<Box<dyn Iterator<Item = String> + '_>> {
         match &self.allowlist {
@@ -329,6 +615,7 @@ impl IncludeCppConfig {
                     .chain(self.pod_requests.iter())
                     .cloned()
                     .chain(self.active_utilities())
+                    .chain(self.variadics.iter().map(|v| v.function.clone()))
                     .chain(self.subclasses.iter().flat_map(|sc| {
                         [
                             format!("{}Cpp", sc.subclass),
@@ -402,6 +689,24 @@ impl IncludeCppConfig {
             .any(|id| id == possible_fun)
     }
 
+    /// The instantiations requested for a given variadic function.
+    pub fn get_variadic_instantiations<'a>(
+        &'a self,
+        cpp_name: &'a str,
+    ) -> impl Iterator<Item = &'a Variadic> + 'a {
+        self.variadics
+            .iter()
+            .filter(move |v| v.function == cpp_name)
+    }
+
+    /// How to represent `const char*`s in the signature of a given
+    /// function, if the user has asked for anything other than raw pointers.
+    pub fn get_c_str_policy(&self, cpp_name: &str) -> Option<&CStrPolicy> {
+        self.c_str_policies
+            .iter()
+            .find(|policy| policy.function == cpp_name)
+    }
+
     pub fn superclasses(&self) -> impl Iterator<Item = &String> {
         let mut uniquified = HashSet::new();
         uniquified.extend(self.subclasses.iter().map(|sc| &sc.superclass));
@@ -414,10 +719,46 @@ impl IncludeCppConfig {
             .any(|sc| format!("{}Holder", sc.subclass) == id)
     }
 
-    fn is_subclass_cpp(&self, id: &str) -> bool {
+    /// Whether the given Rust subclass was declared `thread_safe`.
+    pub fn is_subclass_thread_safe(&self, subclass: &str) -> bool {
+        self.subclasses
+            .iter()
+            .any(|sc| sc.thread_safe && sc.subclass == subclass)
+    }
+
+    /// Whether the given Rust subclass was declared `cpp_factory`.
+    pub fn is_subclass_cpp_factory(&self, subclass: &str) -> bool {
+        self.subclasses
+            .iter()
+            .any(|sc| sc.cpp_factory && sc.subclass == subclass)
+    }
+
+    /// What the given Rust subclass should do if a call from C++ fails.
+    pub fn get_subclass_error_policy(&self, subclass: &str) -> SubclassErrorPolicy {
+        self.subclasses
+            .iter()
+            .find(|sc| sc.subclass == subclass)
+            .map(|sc| sc.error_policy)
+            .unwrap_or_default()
+    }
+
+    /// The C++ namespace into which the given Rust subclass's C++ peer
+    /// should be generated, if any.
+    pub fn get_subclass_cpp_namespace(&self, subclass: &str) -> Option<&str> {
         self.subclasses
             .iter()
-            .any(|sc| format!("{}Cpp", sc.subclass) == id)
+            .filter(|sc| sc.subclass == subclass)
+            .find_map(|sc| sc.cpp_namespace.as_deref())
+    }
+
+    fn is_subclass_cpp(&self, id: &str) -> bool {
+        self.subclasses.iter().any(|sc| {
+            let cpp = format!("{}Cpp", sc.subclass);
+            match &sc.cpp_namespace {
+                Some(ns) => cpp == id || format!("{}::{}", ns, cpp) == id,
+                None => cpp == id,
+            }
+        })
     }
 
     /// Return the filename to which generated .rs should be written.
@@ -477,6 +818,12 @@ impl ToTokens for IncludeCppConfig {
         if self.exclude_utilities {
             tokens.extend(quote! { exclude_utilities!() });
         }
+        if self.strict {
+            tokens.extend(quote! { strict!() });
+        }
+        for i in &self.allowed_skips {
+            tokens.extend(quote! { allow_skip!(#i) });
+        }
         for i in &self.pod_requests {
             tokens.extend(quote! { pod!(#i) });
         }
@@ -506,14 +853,41 @@ impl ToTokens for IncludeCppConfig {
         for i in &self.subclasses {
             let superclass = &i.superclass;
             let subclass = &i.subclass;
-            tokens.extend(quote! { subclass!(#superclass,#subclass) });
+            let thread_safe = if i.thread_safe {
+                Some(quote! { ,thread_safe })
+            } else {
+                None
+            };
+            let cpp_factory = if i.cpp_factory {
+                Some(quote! { ,cpp_factory })
+            } else {
+                None
+            };
+            let error_policy = &i.error_policy;
+            let cpp_namespace = i
+                .cpp_namespace
+                .as_ref()
+                .map(|ns| quote! { ,cpp_namespace = #ns });
+            tokens.extend(quote! {
+                subclass!(#superclass,#subclass #thread_safe #cpp_factory,on_error = #error_policy #cpp_namespace)
+            });
+        }
+        for i in &self.variadics {
+            let function = &i.function;
+            let extra_params = &i.extra_params;
+            tokens.extend(quote! { variadic!(#function, (#(#extra_params,)*)) });
+        }
+        for i in &self.c_str_policies {
+            tokens.extend(quote! { c_str!(#i) });
         }
     }
 }
 
 #[cfg(test)]
 mod parse_tests {
-    use crate::config::UnsafePolicy;
+    use crate::config::{
+        CStrParamPolicy, CStrPolicy, CStrReturnPolicy, IncludeCppConfig, UnsafePolicy, Variadic,
+    };
     use syn::parse_quote;
     #[test]
     fn test_safety_unsafe() {
@@ -531,9 +905,64 @@ mod parse_tests {
         assert_eq!(us, UnsafePolicy::AllFunctionsSafe)
     }
 
+    #[test]
+    fn test_variadic() {
+        let v: Variadic = parse_quote! {
+            "log_printf", (i32, &str)
+        };
+        assert_eq!(v.function, "log_printf");
+        assert_eq!(v.extra_params.len(), 2);
+        let v: Variadic = parse_quote! {
+            "log_printf", (i32)
+        };
+        assert_eq!(v.extra_params.len(), 1);
+        let v: Variadic = parse_quote! {
+            "log_printf", ()
+        };
+        assert!(v.extra_params.is_empty());
+    }
+
+    #[test]
+    fn test_c_str_policy() {
+        let p: CStrPolicy = parse_quote! {
+            "log_message"
+        };
+        assert_eq!(p.function, "log_message");
+        assert_eq!(p.params, CStrParamPolicy::CStr);
+        assert_eq!(p.returns, CStrReturnPolicy::Nullable);
+        assert!(!p.is_static);
+        let p: CStrPolicy = parse_quote! {
+            "get_name", str, nonnull
+        };
+        assert_eq!(p.params, CStrParamPolicy::Str);
+        assert_eq!(p.returns, CStrReturnPolicy::NonNull);
+        assert!(!p.is_static);
+        let p: CStrPolicy = parse_quote! {
+            "version", static, nonnull
+        };
+        assert_eq!(p.returns, CStrReturnPolicy::NonNull);
+        assert!(p.is_static);
+    }
+
     #[test]
     fn test_safety_safe() {
         let us: UnsafePolicy = parse_quote! {};
         assert_eq!(us, UnsafePolicy::AllFunctionsUnsafe)
     }
+
+    #[test]
+    fn test_strict() {
+        let config: IncludeCppConfig = parse_quote! {
+            generate!("A")
+        };
+        assert!(!config.is_strict());
+        let config: IncludeCppConfig = parse_quote! {
+            generate!("A")
+            strict!()
+            allow_skip!("A::foo")
+        };
+        assert!(config.is_strict());
+        assert!(config.is_skip_allowed("A::foo"));
+        assert!(!config.is_skip_allowed("A::bar"));
+    }
 }
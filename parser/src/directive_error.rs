@@ -0,0 +1,108 @@
+// Copyright 2021 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use crate::IncludeCppConfig;
+
+/// An error which arose during code generation, concerning some particular
+/// directive within an `include_cpp!` - for instance, a `generate!` which
+/// didn't result in anything being generated.
+///
+/// Code generation happens in a build script, which can't point at the
+/// offending directive. So it records the error in a file alongside
+/// where the generated .rs would have gone, and the `include_cpp!` macro
+/// (for whom the generated .rs is otherwise opaque) reads it back and
+/// reports it at the right place. That's mostly of use to IDEs, which
+/// expand macros even if the build script failed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DirectiveError {
+    /// The item named in the directive, e.g. `ns::Foo`.
+    pub item: String,
+    pub message: String,
+}
+
+impl DirectiveError {
+    /// The file in which any error is recorded, given the path
+    /// of the generated .rs file.
+    pub fn path_for_rs_file(rs_file: &Path) -> PathBuf {
+        let mut path = rs_file.as_os_str().to_os_string();
+        path.push(".error");
+        PathBuf::from(path)
+    }
+
+    pub fn read(rs_file: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(Self::path_for_rs_file(rs_file)).ok()?;
+        let (item, message) = contents.split_once('\n')?;
+        Some(Self {
+            item: item.to_string(),
+            message: message.to_string(),
+        })
+    }
+
+    pub fn write(&self, rs_file: &Path) -> std::io::Result<()> {
+        std::fs::write(Self::path_for_rs_file(rs_file), self.to_string())
+    }
+
+    /// Removes any record of an error, since generation succeeded.
+    pub fn remove(rs_file: &Path) {
+        let _ = std::fs::remove_file(Self::path_for_rs_file(rs_file));
+    }
+
+    /// This error, located at the directive in question, if it's
+    /// present in the given config.
+    pub fn to_syn_error(&self, config: &IncludeCppConfig) -> Option<syn::Error> {
+        config
+            .get_directive_span(&self.item)
+            .map(|span| syn::Error::new(span, &self.message))
+    }
+}
+
+impl Display for DirectiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\n{}", self.item, self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DirectiveError;
+    use crate::IncludeCppConfig;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_directive_error() {
+        let tmp_dir = std::env::temp_dir().join(format!("autocxx-test-{}", std::process::id()));
+        std::fs::create_dir_all(&tmp_dir).unwrap();
+        let rs_file = tmp_dir.join("autocxx-ffi-default-gen.rs");
+        assert!(DirectiveError::read(&rs_file).is_none());
+        let err = DirectiveError {
+            item: "Foo".into(),
+            message: "did not generate anything\nat all".into(),
+        };
+        err.write(&rs_file).unwrap();
+        assert_eq!(DirectiveError::read(&rs_file), Some(err));
+        let config: IncludeCppConfig = parse_quote! {
+            generate!("Foo")
+        };
+        let read = DirectiveError::read(&rs_file).unwrap();
+        assert!(read.to_syn_error(&config).is_some());
+        DirectiveError::remove(&rs_file);
+        assert!(DirectiveError::read(&rs_file).is_none());
+        std::fs::remove_dir(&tmp_dir).unwrap();
+    }
+}
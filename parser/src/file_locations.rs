@@ -95,6 +95,18 @@ impl FileLocationStrategy {
         }
     }
 
+    /// The full path of a given generated Rust file, if it can be
+    /// determined. Unlike [`Self::make_include`] this needs to know
+    /// `OUT_DIR` (if that's where generated code goes) so may not
+    /// work in every environment.
+    pub fn get_rs_file(&self, fname: &str) -> Option<PathBuf> {
+        match self {
+            FileLocationStrategy::FromAutocxxRsFile(fname) => Some(fname.clone()),
+            FileLocationStrategy::UnknownMaybeFromOutdir => None,
+            _ => Some(self.get_rs_dir().join(fname)),
+        }
+    }
+
     fn get_gen_dir(&self, suffix: &str) -> PathBuf {
         let root = match self {
             FileLocationStrategy::Custom(gen_dir)
@@ -125,6 +137,11 @@ impl FileLocationStrategy {
         self.get_gen_dir("cxx")
     }
 
+    /// Location to cache generated code between builds.
+    pub fn get_cache_dir(&self) -> PathBuf {
+        self.get_gen_dir("cache")
+    }
+
     /// From a build script, inform cargo how to set environment variables
     /// to make them available to the procedural macro.
     pub fn set_cargo_env_vars_for_build(&self) {
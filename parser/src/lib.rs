@@ -13,11 +13,16 @@
 // limitations under the License.
 
 mod config;
+mod directive_error;
 pub mod file_locations;
 mod path;
 mod subclass_attrs;
 
-pub use config::{IncludeCppConfig, RustFun, Subclass, UnsafePolicy};
+pub use config::{
+    CStrParamPolicy, CStrPolicy, CStrReturnPolicy, IncludeCppConfig, RustFun, Subclass,
+    SubclassErrorPolicy, UnsafePolicy, Variadic,
+};
+pub use directive_error::DirectiveError;
 use file_locations::FileLocationStrategy;
 pub use path::RustPath;
 use proc_macro2::TokenStream as TokenStream2;
@@ -35,6 +40,8 @@ pub mod directives {
     pub static EXTERN_RUST_TYPE: &str = "extern_rust_type";
     pub static EXTERN_RUST_FUN: &str = "extern_rust_fun";
     pub static SUBCLASS: &str = "subclass";
+    pub static VARIADIC: &str = "variadic";
+    pub static C_STR: &str = "c_str";
 }
 
 /// Core of the autocxx engine. See `generate` for most details
@@ -63,6 +70,16 @@ impl IncludeCpp {
         FileLocationStrategy::new().make_include(&self.config.get_rs_filename())
     }
 
+    /// Any error concerning one of our directives which was recorded
+    /// when the bindings were generated; see [`DirectiveError`].
+    pub fn directive_error(&self) -> Option<syn::Error> {
+        if self.config.parse_only {
+            return None;
+        }
+        let rs_file = FileLocationStrategy::new().get_rs_file(&self.config.get_rs_filename())?;
+        DirectiveError::read(&rs_file)?.to_syn_error(&self.config)
+    }
+
     pub fn get_config(&self) -> &IncludeCppConfig {
         &self.config
     }
@@ -18,12 +18,22 @@ use syn::Result as ParseResult;
 use syn::{
     parse::{Parse, ParseStream},
     token::Comma,
+    Token,
 };
 
+use crate::SubclassErrorPolicy;
+
 #[derive(Default)]
 pub struct SubclassAttrs {
     pub self_owned: bool,
-    pub superclass: Option<String>,
+    pub thread_safe: bool,
+    pub cpp_factory: bool,
+    pub error_policy: Option<SubclassErrorPolicy>,
+    /// The C++ namespace for the generated C++ peer class.
+    pub cpp_namespace: Option<String>,
+    /// Superclasses, in the order they should be inherited. Constructors
+    /// are taken from the first.
+    pub superclasses: Vec<String>,
 }
 
 impl Parse for SubclassAttrs {
@@ -33,22 +43,34 @@ impl Parse for SubclassAttrs {
         while id.is_some() {
             match id {
                 Some(id) if id == "self_owned" => me.self_owned = true,
+                Some(id) if id == "thread_safe" => me.thread_safe = true,
+                Some(id) if id == "cpp_factory" => me.cpp_factory = true,
+                Some(id) if id == "on_error" => {
+                    input.parse::<Token![=]>()?;
+                    me.error_policy = Some(input.parse()?);
+                }
+                Some(id) if id == "cpp_namespace" => {
+                    let args;
+                    syn::parenthesized!(args in input);
+                    let ns: syn::LitStr = args.parse()?;
+                    me.cpp_namespace = Some(ns.value());
+                }
                 Some(id) if id == "superclass" => {
                     let args;
                     syn::parenthesized!(args in input);
                     let superclass: syn::LitStr = args.parse()?;
-                    if me.superclass.is_some() {
+                    if me.superclasses.contains(&superclass.value()) {
                         return Err(syn::Error::new_spanned(
-                            id.into_token_stream(),
-                            "Expected single superclass specification",
+                            superclass.into_token_stream(),
+                            "Superclass specified more than once",
                         ));
                     }
-                    me.superclass = Some(superclass.value());
+                    me.superclasses.push(superclass.value());
                 }
                 Some(id) => {
                     return Err(syn::Error::new_spanned(
                         id.into_token_stream(),
-                        "Expected self_owned or superclass",
+                        "Expected self_owned, thread_safe, cpp_factory, on_error, cpp_namespace or superclass",
                     ))
                 }
                 None => {}
@@ -62,3 +84,39 @@ impl Parse for SubclassAttrs {
         Ok(me)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::SubclassAttrs;
+    use crate::SubclassErrorPolicy;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_multiple_superclasses() {
+        let attrs: SubclassAttrs = parse_quote! {
+            superclass("A"), superclass("B"), thread_safe
+        };
+        assert_eq!(attrs.superclasses, vec!["A".to_string(), "B".to_string()]);
+        assert!(attrs.thread_safe);
+        assert!(!attrs.self_owned);
+    }
+
+    #[test]
+    fn test_error_policy() {
+        let attrs: SubclassAttrs = parse_quote! {
+            self_owned, on_error = return_default
+        };
+        assert_eq!(attrs.error_policy, Some(SubclassErrorPolicy::ReturnDefault));
+        assert!(attrs.self_owned);
+        assert!(syn::parse_str::<SubclassAttrs>("on_error = explode").is_err());
+    }
+
+    #[test]
+    fn test_cpp_namespace() {
+        let attrs: SubclassAttrs = parse_quote! {
+            superclass("a::Base"), cpp_namespace("my::peers")
+        };
+        assert_eq!(attrs.superclasses, vec!["a::Base".to_string()]);
+        assert_eq!(attrs.cpp_namespace.as_deref(), Some("my::peers"));
+    }
+}
@@ -372,6 +372,19 @@ use autocxx_engine::IncludeCppEngine;
 /// string on the stack, and is generally incompatible with the
 /// [cxx::UniquePtr]-based approaches we use here.
 ///
+/// C-style `const char*` strings are raw pointers, so by default functions
+/// taking or returning them are `unsafe`. If you know a function treats
+/// them as NUL-terminated strings, use [c_str] to get `&CStr` or `&str`
+/// parameters and `&CStr` or `CString` return values instead.
+///
+/// ## Arrays
+///
+/// Fixed-size C arrays within POD structs appear as Rust arrays, so
+/// `int values[16]` becomes `values: [c_int; 16]`. Functions taking a
+/// reference to a C array, such as `const float (&m)[4][4]`, accept
+/// `&[[f32; 4]; 4]` (or `&mut` for non-const references). Array elements
+/// may be POD structs or further arrays.
+///
 /// ## Preprocessor symbols
 ///
 /// `#define` and other preprocessor symbols will appear as constants.
@@ -441,6 +454,31 @@ use autocxx_engine::IncludeCppEngine;
 /// this will be improved in future. At present such types have a name
 /// `AutocxxConcrete{n}` but this may change in future.
 ///
+/// Where such a type is a `std::map`, `std::unordered_map`, `std::set`,
+/// `std::unordered_set`, `std::list` or `std::deque`, and its element types
+/// are themselves things which cxx can refer to, it also gets `len()`,
+/// `is_empty()` and `iter()` methods, and `&T` implements [`IntoIterator`].
+/// Maps iterate over `(&K, &V)` pairs and additionally have
+/// `get(&K) -> Option<&V>`.
+///
+/// Other types you `generate!` which have `const` `begin()` and `end()`
+/// methods also get `iter()`, and `&T` implements [`IntoIterator`],
+/// provided autocxx can tell what they yield: either `begin()` returns a
+/// pointer, or the type has a `value_type` member typedef. `begin()` and
+/// `end()` must themselves be methods autocxx can generate, and
+/// dereferencing the C++ iterator must give a reference into the container.
+///
+/// ## Callbacks
+///
+/// A function which takes a `std::function<R(Args...)>` parameter, either by
+/// value or by const reference, accepts a `Box<dyn FnMut(Args...) -> R>`
+/// in Rust. The generated C++ creates a `std::function` which owns the
+/// boxed closure and calls back into Rust whenever it's invoked. The
+/// closure's parameters and return type must themselves be things which can
+/// be passed by value or by reference, and `std::function`s can't yet be
+/// returned from functions or accepted by virtual methods which you
+/// override in Rust subclasses.
+///
 /// ## Exceptions
 ///
 /// Exceptions are not supported. If your C++ code is compiled with exceptions,
@@ -589,6 +627,33 @@ macro_rules! block {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Make it an error for autocxx to skip anything you've explicitly
+/// asked for. Normally, if some method of a type you [generate] can't
+/// be bound (perhaps because it takes a parameter of an unsupported
+/// type) it's simply omitted, with an explanation in the generated
+/// documentation. With `strict!()`, any such omission from an item
+/// named in [generate] or [generate_pod] instead fails the build,
+/// listing everything which was skipped and why. Items found only
+/// via [generate_all] are not affected.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! strict {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Permit a particular item to be skipped even in [strict] mode,
+/// e.g. `allow_skip!("ns::Foo::bar")`. The name should be given
+/// exactly as it appears in the error reported by [strict].
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! allow_skip {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 /// The name of the mod to be generated with the FFI code.
 /// The default is `ffi`.
 ///
@@ -668,6 +733,51 @@ macro_rules! subclass {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Generate a fixed-arity instantiation of a C variadic function.
+/// cxx can't call variadic functions directly, so for each
+/// instantiation we generate a C++ wrapper which takes the given
+/// parameter types in place of the `...`. For example,
+/// `variadic!("log_printf", (i32, &str))` for a function
+/// `void log_printf(const char* fmt, ...)` results in a function
+/// taking a format string, an `i32` and a `&str`. Any `&str`s are passed
+/// to C++ as NUL-terminated `const char*`s. If you request several
+/// instantiations of the same function, they're named in the same
+/// way as overloads: `log_printf`, `log_printf1` and so on.
+///
+/// This also implies a [generate] directive for the function.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! variadic {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Represent the `const char*`s in a function's signature as Rust
+/// strings rather than raw pointers. Name the function as
+/// `"namespace::function"` or `"Class::method"`, then optionally:
+/// * `cstr` (the default) to take `&CStr` parameters, or `str` to take
+///   `&str` parameters, which C++ will copy into NUL-terminated strings.
+/// * `nullable` (the default) to return an `Option`, or `nonnull` to
+///   panic if C++ returns `nullptr`.
+/// * `static` to promise that returned strings live forever, e.g. because
+///   they're string literals, so they can be returned as
+///   `&'static CStr` without copying.
+///
+/// For example, `c_str!("get_name", str, nonnull)`. Without `static`,
+/// strings returned by methods are a `&CStr` borrowing from `self`, so
+/// it's up to you to ensure the C++ keeps them valid at least as long as
+/// the object. Those returned by other functions are copied into a
+/// `CString`.
+/// Virtual functions are not affected.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! c_str {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! usage {
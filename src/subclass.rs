@@ -17,11 +17,17 @@
 
 use std::{
     cell::RefCell,
+    fmt::Display,
+    panic::AssertUnwindSafe,
     pin::Pin,
     rc::{Rc, Weak},
+    sync::{Arc, Mutex},
 };
 
-use cxx::{memory::UniquePtrTarget, UniquePtr};
+use cxx::{
+    memory::{SharedPtrTarget, UniquePtrTarget},
+    SharedPtr, UniquePtr,
+};
 
 /// Deprecated - use [`subclass`] instead.
 #[deprecated]
@@ -56,6 +62,44 @@ pub use autocxx_macro::subclass as is_subclass;
 ///   [`CppSubclass`] for the struct, so it's
 ///   generally easier to use the former option.
 ///
+/// A subclass may have several C++ superclasses: repeat
+/// `superclass("...")` in the attribute, or the [include_cpp]
+/// directive, once for each. The C++ peer inherits from all of them, in
+/// the order given, and gets its constructors from the first. You'll
+/// get an `AsRef` implementation for each superclass and, alongside
+/// `pin_mut` (for the first superclass), a `pin_mut_as_<superclass>`
+/// method for each. Implement the `<superclass>_methods` trait for each
+/// superclass whose virtual methods you want to override.
+///
+/// If C++ may call into your subclass from threads other than the one
+/// which created it, use `#[subclass(thread_safe)]` (or
+/// `subclass!("MyCppSuperclass",Bar,thread_safe)`) and create instances
+/// using [`CppSubclassThreadSafe`]. To move it between threads in Rust,
+/// you'll also need to promise that the C++ peer, including its
+/// superclass's state, may be sent to another thread, by writing
+/// `unsafe impl Send for ffi::BarCpp {}`.
+///
+/// The C++ peer class, `<subclass>Cpp`, is generated into the root
+/// namespace unless you ask otherwise with
+/// `#[subclass(cpp_namespace("my::peers"))]` (or
+/// `subclass!("MyCppSuperclass",Bar,cpp_namespace = "my::peers")`).
+/// C++ code can then refer to it as `my::peers::BarCpp`, and Rust code
+/// as `ffi::my::peers::BarCpp`.
+///
+/// Hand-written C++ may use the C++ peer by including `<subclass>Cpp.h`
+/// (or, for a peer in a namespace, e.g. `my.peers.<subclass>Cpp.h`),
+/// which autocxx generates alongside its other headers. If your subclass
+/// implements `Default`, you can also let C++ create instances of it,
+/// owned by C++, by declaring it `#[subclass(cpp_factory)]` (or
+/// `subclass!("MyCppSuperclass",Bar,cpp_factory)`). C++ can then call
+/// `BarCpp::create()`, which returns a `std::unique_ptr<BarCpp>`.
+///
+/// If a call from C++ into your subclass can't be completed - because it
+/// panics, is re-entrant, or your subclass has been dropped - the process
+/// aborts. Use `#[subclass(on_error = return_default)]` to instead return
+/// `Default::default()` to C++, or `#[subclass(on_error = exception)]` to
+/// throw a C++ exception. See [`CppSubclassCallError`].
+///
 /// See [`CppSubclass`] for information about the
 /// multiple steps you need to take to be able to make Rust
 /// subclasses of a C++ class.
@@ -69,27 +113,55 @@ pub use autocxx_macro::subclass;
 /// ```
 pub mod prelude {
     pub use super::{
-        is_subclass, subclass, CppPeerConstructor, CppSubclass, CppSubclassDefault,
-        CppSubclassRustPeerHolder, CppSubclassSelfOwned, CppSubclassSelfOwnedDefault,
+        is_subclass, subclass, CppPeerConstructor, CppPeerConstructorWith, CppPeerSharedUpcast,
+        CppSubclass, CppSubclassDefault, CppSubclassNotThreadSafe, CppSubclassRustPeerHolder,
+        CppSubclassSelfOwned, CppSubclassSelfOwnedDefault, CppSubclassThreadSafe,
+        UnsafeConstructorArgs,
     };
 }
 
 #[doc(hidden)]
-pub trait CppSubclassCppPeer: UniquePtrTarget {
+pub trait CppSubclassCppPeer: UniquePtrTarget + SharedPtrTarget + Sized {
     fn relinquish_ownership(&self);
+    fn into_shared(me: UniquePtr<Self>) -> SharedPtr<Self>;
+}
+
+/// Converts a [`SharedPtr`] to the C++ peer of a subclass into a [`SharedPtr`]
+/// to one of its C++ superclasses. This is implemented for you for each
+/// superclass, and allows subclasses created using
+/// [`CppSubclass::new_shared_owned`] to be passed to C++ APIs which
+/// take a `std::shared_ptr` to the superclass.
+pub trait CppPeerSharedUpcast<Super: SharedPtrTarget> {
+    /// Returns a [`SharedPtr`] to the superclass which shares ownership
+    /// with this one.
+    fn upcast(&self) -> SharedPtr<Super>;
 }
 
 #[doc(hidden)]
 pub enum CppSubclassRustPeerHolder<T> {
     Owned(Rc<RefCell<T>>),
     Unowned(Weak<RefCell<T>>),
+    OwnedThreadSafe(Arc<Mutex<T>>),
+    UnownedThreadSafe(std::sync::Weak<Mutex<T>>),
 }
 
 impl<T> CppSubclassRustPeerHolder<T> {
-    pub fn get(&self) -> Option<Rc<RefCell<T>>> {
+    pub fn get(&self) -> Result<Rc<RefCell<T>>, CppSubclassCallError> {
         match self {
-            CppSubclassRustPeerHolder::Owned(strong) => Some(strong.clone()),
-            CppSubclassRustPeerHolder::Unowned(weak) => weak.upgrade(),
+            CppSubclassRustPeerHolder::Owned(strong) => Ok(strong.clone()),
+            CppSubclassRustPeerHolder::Unowned(weak) => {
+                weak.upgrade().ok_or(CppSubclassCallError::Dropped)
+            }
+            _ => Err(CppSubclassCallError::WrongConstructor),
+        }
+    }
+    pub fn get_thread_safe(&self) -> Result<Arc<Mutex<T>>, CppSubclassCallError> {
+        match self {
+            CppSubclassRustPeerHolder::OwnedThreadSafe(strong) => Ok(strong.clone()),
+            CppSubclassRustPeerHolder::UnownedThreadSafe(weak) => {
+                weak.upgrade().ok_or(CppSubclassCallError::Dropped)
+            }
+            _ => Err(CppSubclassCallError::WrongConstructor),
         }
     }
     pub fn relinquish_ownership(self) -> Self {
@@ -97,6 +169,9 @@ impl<T> CppSubclassRustPeerHolder<T> {
             CppSubclassRustPeerHolder::Owned(strong) => {
                 CppSubclassRustPeerHolder::Unowned(Rc::downgrade(&strong))
             }
+            CppSubclassRustPeerHolder::OwnedThreadSafe(strong) => {
+                CppSubclassRustPeerHolder::UnownedThreadSafe(Arc::downgrade(&strong))
+            }
             _ => self,
         }
     }
@@ -109,6 +184,10 @@ pub enum CppSubclassCppPeerHolder<CppPeer: CppSubclassCppPeer> {
     Unowned(*mut CppPeer),
 }
 
+// The raw pointer is to our own C++ peer, which is as sendable as the
+// peer itself.
+unsafe impl<CppPeer: CppSubclassCppPeer + Send> Send for CppSubclassCppPeerHolder<CppPeer> {}
+
 impl<CppPeer: CppSubclassCppPeer> Default for CppSubclassCppPeerHolder<CppPeer> {
     fn default() -> Self {
         CppSubclassCppPeerHolder::Empty
@@ -164,6 +243,141 @@ where
     me
 }
 
+fn make_cpp_owned_peer<CppPeer, PeerConstructor, Subclass>(
+    me: Subclass,
+    peer_constructor: PeerConstructor,
+) -> UniquePtr<CppPeer>
+where
+    CppPeer: CppSubclassCppPeer,
+    Subclass: CppSubclass<CppPeer>,
+    PeerConstructor:
+        FnOnce(&mut Subclass, CppSubclassRustPeerHolder<Subclass>) -> UniquePtr<CppPeer>,
+{
+    let me = Rc::new(RefCell::new(me));
+    let holder = CppSubclassRustPeerHolder::Owned(me.clone());
+    let mut borrowed = me.as_ref().borrow_mut();
+    let mut cpp_side = peer_constructor(&mut borrowed, holder);
+    borrowed.peer_holder_mut().set_unowned(&mut cpp_side);
+    cpp_side
+}
+
+fn make_cpp_owned_peer_thread_safe<CppPeer, PeerConstructor, Subclass>(
+    me: Subclass,
+    peer_constructor: PeerConstructor,
+) -> UniquePtr<CppPeer>
+where
+    CppPeer: CppSubclassCppPeer,
+    Subclass: CppSubclass<CppPeer>,
+    PeerConstructor:
+        FnOnce(&mut Subclass, CppSubclassRustPeerHolder<Subclass>) -> UniquePtr<CppPeer>,
+{
+    let me = Arc::new(Mutex::new(me));
+    let holder = CppSubclassRustPeerHolder::OwnedThreadSafe(me.clone());
+    let mut locked = me.lock().unwrap();
+    let mut cpp_side = peer_constructor(&mut locked, holder);
+    locked.peer_holder_mut().set_unowned(&mut cpp_side);
+    cpp_side
+}
+
+fn make_owning_peer_thread_safe<CppPeer, PeerConstructor, Subclass, PeerBoxer>(
+    me: Subclass,
+    peer_constructor: PeerConstructor,
+    peer_boxer: PeerBoxer,
+) -> Arc<Mutex<Subclass>>
+where
+    CppPeer: CppSubclassCppPeer,
+    Subclass: CppSubclass<CppPeer>,
+    PeerConstructor:
+        FnOnce(&mut Subclass, CppSubclassRustPeerHolder<Subclass>) -> UniquePtr<CppPeer>,
+    PeerBoxer: FnOnce(Arc<Mutex<Subclass>>) -> CppSubclassRustPeerHolder<Subclass>,
+{
+    let me = Arc::new(Mutex::new(me));
+    let holder = peer_boxer(me.clone());
+    let mut locked = me.lock().unwrap();
+    let cpp_side = peer_constructor(&mut locked, holder);
+    locked.peer_holder_mut().set_owned(cpp_side);
+    drop(locked);
+    me
+}
+
+/// Reasons why a call from C++ into a method of a Rust subclass couldn't
+/// be completed. What happens next depends upon the `on_error` policy
+/// given for the subclass:
+/// * `abort` (the default) prints the error and aborts the process;
+/// * `return_default` prints the error and returns `Default::default()`
+///   to C++, so every overridden method must return a type which
+///   implements [`Default`];
+/// * `exception` throws a C++ `rust::Error` exception containing the
+///   error message.
+#[derive(Debug)]
+pub enum CppSubclassCallError {
+    /// The Rust object had already been dropped.
+    Dropped,
+    /// The Rust object was already borrowed, most likely because this
+    /// is a re-entrant call.
+    AlreadyBorrowed,
+    /// A previous call into this thread-safe subclass panicked.
+    Poisoned,
+    /// The subclass was created using the constructors in
+    /// [`CppSubclassThreadSafe`] but isn't declared `thread_safe`.
+    WrongConstructor,
+    /// The Rust method panicked, with the given message.
+    Panicked(String),
+}
+
+impl Display for CppSubclassCallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CppSubclassCallError::Dropped => write!(f, "called after subclass destroyed"),
+            CppSubclassCallError::AlreadyBorrowed => write!(
+                f,
+                "called whilst subclass already borrowed - likely a re-entrant call"
+            ),
+            CppSubclassCallError::Poisoned => {
+                write!(f, "called after a previous call panicked")
+            }
+            CppSubclassCallError::WrongConstructor => write!(
+                f,
+                "called on a subclass whose constructor doesn't match whether it's declared thread_safe"
+            ),
+            CppSubclassCallError::Panicked(msg) => write!(f, "panicked: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CppSubclassCallError {}
+
+#[doc(hidden)]
+pub fn call_subclass_method<R>(
+    f: impl FnOnce() -> Result<R, CppSubclassCallError>,
+) -> Result<R, CppSubclassCallError> {
+    std::panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let msg = payload
+            .downcast_ref::<&str>()
+            .map(|msg| msg.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(CppSubclassCallError::Panicked(msg))
+    })
+}
+
+#[doc(hidden)]
+pub fn abort_subclass_call(method: &str, err: CppSubclassCallError) -> ! {
+    eprintln!("{} {}", method, err);
+    std::process::abort()
+}
+
+#[doc(hidden)]
+pub fn default_subclass_call<R: Default>(method: &str, err: CppSubclassCallError) -> R {
+    eprintln!("{} {}; returning default value", method, err);
+    R::default()
+}
+
+#[doc(hidden)]
+pub fn subclass_call_exception(method: &str, err: CppSubclassCallError) -> String {
+    format!("{} {}", method, err)
+}
+
 /// A trait to be implemented by a subclass which knows how to construct
 /// its C++ peer object. Specifically, the implementation here will
 /// arrange to call one or other of the `make_unique` methods to be
@@ -172,7 +386,11 @@ where
 /// automatically for you. If there are multiple constructors, or
 /// a single constructor which takes parameters, you'll need to implement
 /// this trait for your subclass in order to call the correct
-/// constructor.
+/// constructor. You may also then use the `_with` constructors such as
+/// [`CppSubclass::new_rust_owned_with`], which choose the superclass
+/// constructor according to the arguments given; your implementation of
+/// this trait can itself use one of those superclass constructors via
+/// [`CppPeerConstructorWith::make_peer_with`].
 pub trait CppPeerConstructor<CppPeer: CppSubclassCppPeer>: Sized {
     /// Create the C++ peer. This method will be automatically generated
     /// for you *except* in cases where the superclass has multiple constructors,
@@ -183,6 +401,54 @@ pub trait CppPeerConstructor<CppPeer: CppSubclassCppPeer>: Sized {
     fn make_peer(&mut self, peer_holder: CppSubclassRustPeerHolder<Self>) -> UniquePtr<CppPeer>;
 }
 
+/// A trait which knows how to construct the C++ peer object using a
+/// particular superclass constructor. autocxx implements this for your
+/// subclass once for each constructor of its (first) superclass, where
+/// `Args` is a tuple of the parameters to that constructor. For example,
+/// if the superclass has constructors `Observer()` and
+/// `Observer(const Foo&, uint32_t)`, this will be implemented for `()`
+/// and for `(&Foo, u32)`.
+///
+/// Where autocxx considers a constructor unsafe to call, e.g. because it
+/// takes raw pointers, `Args` is instead an [`UnsafeConstructorArgs`]
+/// wrapping that tuple.
+///
+/// You won't normally need to use this directly: instead call
+/// [`CppSubclass::new_rust_owned_with`], [`CppSubclass::new_cpp_owned_with`]
+/// or [`CppSubclassSelfOwned::new_self_owned_with`].
+pub trait CppPeerConstructorWith<CppPeer: CppSubclassCppPeer, Args>: Sized {
+    /// Create the C++ peer using the superclass constructor which
+    /// accepts `args`.
+    fn make_peer_with(
+        peer_holder: CppSubclassRustPeerHolder<Self>,
+        args: Args,
+    ) -> UniquePtr<CppPeer>;
+}
+
+/// Arguments for a superclass constructor which autocxx considers unsafe
+/// to call, for use with the `_with` constructors such as
+/// [`CppSubclass::new_rust_owned_with`]. For example,
+/// `MyObserver::new_rust_owned_with(me, unsafe { UnsafeConstructorArgs::new((ptr,)) })`.
+pub struct UnsafeConstructorArgs<Args>(Args);
+
+impl<Args> UnsafeConstructorArgs<Args> {
+    /// Wraps a tuple of arguments for a superclass constructor.
+    ///
+    /// # Safety
+    ///
+    /// Calling the superclass constructor with these arguments must be
+    /// safe, just as if you were calling the unsafe `make_unique` function
+    /// for that constructor yourself.
+    pub unsafe fn new(args: Args) -> Self {
+        Self(args)
+    }
+
+    /// Returns the wrapped arguments.
+    pub fn into_inner(self) -> Args {
+        self.0
+    }
+}
+
 /// A subclass of a C++ type.
 ///
 /// To create a Rust subclass of a C++ class, you must do these things:
@@ -203,7 +469,9 @@ pub trait CppPeerConstructor<CppPeer: CppSubclassCppPeer>: Sized {
 ///   but only if autocxx determines that there are multiple possible superclass
 ///   constructors so you need to call one explicitly (or if there's a single
 ///   non-trivial superclass constructor.) autocxx will implemente this trait
-///   for you if there's no ambiguity.
+///   for you if there's no ambiguity. You can then also use
+///   [`CppSubclass::new_rust_owned_with`] and friends, passing a tuple of
+///   arguments for the superclass constructor you want.
 ///
 /// # How to access your Rust structure from outside
 ///
@@ -235,6 +503,8 @@ pub trait CppPeerConstructor<CppPeer: CppSubclassCppPeer>: Sized {
 ///    [`UniquePtr`] held elsewhere in C++. That C++ peer then owns
 ///    this Rust-side object via a strong [`Rc`] reference. This is the
 ///    ownership relationship set up by [`CppSubclass::new_cpp_owned`].
+///    [`CppSubclass::new_shared_owned`] does the same, but the C++ peer
+///    is owned by a [`SharedPtr`] instead.
 /// 2. The object pair is owned by Rust. Specifically, by a strong
 ///    [`Rc`] reference to this Rust-side object. In turn, the Rust-side object
 ///    owns the C++-side peer via a [`UniquePtr`]. This is what's set up by
@@ -253,7 +523,12 @@ pub trait CppPeerConstructor<CppPeer: CppSubclassCppPeer>: Sized {
 ///   (non-const) virtual method is called on your type, which then causes you
 ///   to call back into C++, which results in a _second_ call into a (non-const)
 ///   virtual method, we will try to create two mutable references to your
-///   subclass which isn't allowed in Rust and will therefore panic.
+///   subclass which isn't allowed in Rust. By default this aborts the
+///   process, as does a panic within your method or a call after your
+///   subclass has been dropped. Use `#[subclass(on_error = return_default)]`
+///   or `#[subclass(on_error = exception)]` (or the same options to
+///   `subclass!`) to handle these errors differently; see
+///   [`CppSubclassCallError`].
 ///
 ///   A future version of autocxx may provide the option of treating all
 ///   non-const methods (in C++) as const methods on the Rust side, which will
@@ -262,12 +537,16 @@ pub trait CppPeerConstructor<CppPeer: CppSubclassCppPeer>: Sized {
 ///   with existing C++ interfaces. If you need this, indicate support on
 ///   [this issue](https://github.com/google/autocxx/issues/622).
 ///
-/// * *Thread safety*. The subclass object is not thread-safe and shouldn't
-///   be passed to different threads in C++. A future version of this code
-///   will give the option to use `Arc` and `Mutex` internally rather than
-///   `Rc` and `RefCell`, solving this problem.
+/// * *Thread safety*. By default, the subclass object is not thread-safe and
+///   shouldn't be passed to different threads in C++. If C++ may call it
+///   from other threads, declare it as `#[subclass(thread_safe)]` and
+///   see [`CppSubclassThreadSafe`].
 ///
 /// * *Protected methods.* We don't do anything clever here - they're public.
+///   Protected virtual methods may be overridden like any other. Protected
+///   non-virtual methods can't be overridden, but can be called from your
+///   subclass using the `_super` method of the same name in the
+///   `Superclass_supers` trait.
 ///
 /// * *Non-trivial class hierarchies*. We don't yet consider virtual methods
 ///   on base classes of base classes. This is a temporary limitation,
@@ -298,25 +577,71 @@ pub trait CppSubclass<CppPeer: CppSubclassCppPeer>: CppPeerConstructor<CppPeer>
     /// Creates a new instance of this subclass. This instance is owned by the
     /// returned [`cxx::UniquePtr`] and thus would typically be returned immediately
     /// to C++ such that it can be owned on the C++ side.
-    fn new_cpp_owned(me: Self) -> UniquePtr<CppPeer> {
-        let me = Rc::new(RefCell::new(me));
-        let holder = CppSubclassRustPeerHolder::Owned(me.clone());
-        let mut borrowed = me.as_ref().borrow_mut();
-        let mut cpp_side = borrowed.make_peer(holder);
-        borrowed.peer_holder_mut().set_unowned(&mut cpp_side);
-        cpp_side
+    fn new_cpp_owned(me: Self) -> UniquePtr<CppPeer>
+    where
+        Self: CppSubclassNotThreadSafe<CppPeer>,
+    {
+        make_cpp_owned_peer(me, |obj, holder| obj.make_peer(holder))
     }
 
     /// Creates a new instance of this subclass. This instance is not owned
     /// by C++, and therefore will be deleted when it goes out of scope in
     /// Rust.
-    fn new_rust_owned(me: Self) -> Rc<RefCell<Self>> {
+    fn new_rust_owned(me: Self) -> Rc<RefCell<Self>>
+    where
+        Self: CppSubclassNotThreadSafe<CppPeer>,
+    {
         make_owning_peer(
             me,
             |obj, holder| obj.make_peer(holder),
             |me| CppSubclassRustPeerHolder::Unowned(Rc::downgrade(&me)),
         )
     }
+
+    /// Creates a new instance of this subclass owned by the returned
+    /// [`cxx::SharedPtr`]. As with [`CppSubclass::new_cpp_owned`], the C++
+    /// peer owns this Rust object, so both are destroyed once the last
+    /// `std::shared_ptr` to the C++ peer goes away. Use
+    /// [`CppPeerSharedUpcast::upcast`] to obtain a [`SharedPtr`] to
+    /// the superclass.
+    fn new_shared_owned(me: Self) -> SharedPtr<CppPeer>
+    where
+        Self: CppSubclassNotThreadSafe<CppPeer>,
+    {
+        CppPeer::into_shared(Self::new_cpp_owned(me))
+    }
+
+    /// As [`CppSubclass::new_cpp_owned`], but constructs the C++ superclass
+    /// using the constructor which accepts `args`, a tuple of arguments.
+    fn new_cpp_owned_with<Args>(me: Self, args: Args) -> UniquePtr<CppPeer>
+    where
+        Self: CppSubclassNotThreadSafe<CppPeer> + CppPeerConstructorWith<CppPeer, Args>,
+    {
+        make_cpp_owned_peer(me, |_, holder| Self::make_peer_with(holder, args))
+    }
+
+    /// As [`CppSubclass::new_shared_owned`], but constructs the C++ superclass
+    /// using the constructor which accepts `args`, a tuple of arguments.
+    fn new_shared_owned_with<Args>(me: Self, args: Args) -> SharedPtr<CppPeer>
+    where
+        Self: CppSubclassNotThreadSafe<CppPeer> + CppPeerConstructorWith<CppPeer, Args>,
+    {
+        CppPeer::into_shared(Self::new_cpp_owned_with(me, args))
+    }
+
+    /// As [`CppSubclass::new_rust_owned`], but constructs the C++ superclass
+    /// using the constructor which accepts `args`, a tuple of arguments.
+    /// For example, `MyObserver::new_rust_owned_with(me, (&foo, 3u32))`.
+    fn new_rust_owned_with<Args>(me: Self, args: Args) -> Rc<RefCell<Self>>
+    where
+        Self: CppSubclassNotThreadSafe<CppPeer> + CppPeerConstructorWith<CppPeer, Args>,
+    {
+        make_owning_peer(
+            me,
+            |_, holder| Self::make_peer_with(holder, args),
+            |me| CppSubclassRustPeerHolder::Unowned(Rc::downgrade(&me)),
+        )
+    }
 }
 
 /// Trait to be implemented by subclasses which are self-owned, i.e. not owned
@@ -330,7 +655,10 @@ pub trait CppSubclassSelfOwned<CppPeer: CppSubclassCppPeer>: CppSubclass<CppPeer
     /// use [`CppSubclassSelfOwned::delete_self`].
     /// The return value may be useful to register this, etc. but can ultimately
     /// be discarded without destroying this object.
-    fn new_self_owned(me: Self) -> Rc<RefCell<Self>> {
+    fn new_self_owned(me: Self) -> Rc<RefCell<Self>>
+    where
+        Self: CppSubclassNotThreadSafe<CppPeer>,
+    {
         make_owning_peer(
             me,
             |obj, holder| obj.make_peer(holder),
@@ -338,6 +666,20 @@ pub trait CppSubclassSelfOwned<CppPeer: CppSubclassCppPeer>: CppSubclass<CppPeer
         )
     }
 
+    /// As [`CppSubclassSelfOwned::new_self_owned`], but constructs the C++
+    /// superclass using the constructor which accepts `args`, a tuple of
+    /// arguments.
+    fn new_self_owned_with<Args>(me: Self, args: Args) -> Rc<RefCell<Self>>
+    where
+        Self: CppSubclassNotThreadSafe<CppPeer> + CppPeerConstructorWith<CppPeer, Args>,
+    {
+        make_owning_peer(
+            me,
+            |_, holder| Self::make_peer_with(holder, args),
+            CppSubclassRustPeerHolder::Owned,
+        )
+    }
+
     /// Relinquishes ownership from the C++ side. If there are no outstanding
     /// references from the Rust side, this will result in the destruction
     /// of this subclass instance.
@@ -346,6 +688,117 @@ pub trait CppSubclassSelfOwned<CppPeer: CppSubclassCppPeer>: CppSubclass<CppPeer
     }
 }
 
+/// Implemented by autocxx for each subclass which isn't declared
+/// `thread_safe`. The constructors in [`CppSubclass`],
+/// [`CppSubclassSelfOwned`] and friends, which hold the Rust object in an
+/// [`Rc`]`<`[`RefCell`]`>`, require this, so that a thread-safe subclass
+/// can only be created using the constructors in [`CppSubclassThreadSafe`].
+pub trait CppSubclassNotThreadSafe<CppPeer: CppSubclassCppPeer>: CppSubclass<CppPeer> {}
+
+/// Constructors for subclasses declared as `#[subclass(thread_safe)]`,
+/// which C++ may call from any thread. Such subclasses must be created
+/// using these constructors, which hold the Rust object in an
+/// [`Arc`]`<`[`Mutex`]`>` rather than an [`Rc`]`<`[`RefCell`]`>`.
+/// Each call from C++ locks the mutex, so calls are serialized.
+///
+/// Re-entrant calls (a virtual method call which results in a second
+/// virtual method call into the same object) will deadlock rather than
+/// panic, so you must avoid them. The constructors in [`CppSubclass`]
+/// require [`CppSubclassNotThreadSafe`], so can't be used for a
+/// thread-safe subclass.
+///
+/// The mutex guards only the Rust object. autocxx knows nothing of the
+/// state of the C++ superclass, so doesn't make the C++ peer [`Send`];
+/// if it's safe to send to another thread, write
+/// `unsafe impl Send for ffi::<subclass>Cpp {}` yourself.
+///
+/// The ownership models are the same as those described for
+/// [`CppSubclass`].
+pub trait CppSubclassThreadSafe<CppPeer: CppSubclassCppPeer>: CppSubclass<CppPeer> {
+    /// Creates a new instance of this subclass, owned by the returned
+    /// [`cxx::UniquePtr`]. See [`CppSubclass::new_cpp_owned`].
+    fn new_cpp_owned_thread_safe(me: Self) -> UniquePtr<CppPeer> {
+        make_cpp_owned_peer_thread_safe(me, |obj, holder| obj.make_peer(holder))
+    }
+
+    /// Creates a new instance of this subclass, owned by Rust.
+    /// See [`CppSubclass::new_rust_owned`].
+    fn new_rust_owned_thread_safe(me: Self) -> Arc<Mutex<Self>> {
+        make_owning_peer_thread_safe(
+            me,
+            |obj, holder| obj.make_peer(holder),
+            |me| CppSubclassRustPeerHolder::UnownedThreadSafe(Arc::downgrade(&me)),
+        )
+    }
+
+    /// Creates a new instance of this subclass which owns itself.
+    /// See [`CppSubclassSelfOwned::new_self_owned`].
+    fn new_self_owned_thread_safe(me: Self) -> Arc<Mutex<Self>>
+    where
+        Self: CppSubclassSelfOwned<CppPeer>,
+    {
+        make_owning_peer_thread_safe(
+            me,
+            |obj, holder| obj.make_peer(holder),
+            CppSubclassRustPeerHolder::OwnedThreadSafe,
+        )
+    }
+
+    /// As [`CppSubclassThreadSafe::new_cpp_owned_thread_safe`], but
+    /// constructs the C++ superclass using the constructor which accepts
+    /// `args`. See [`CppSubclass::new_cpp_owned_with`].
+    fn new_cpp_owned_thread_safe_with<Args>(me: Self, args: Args) -> UniquePtr<CppPeer>
+    where
+        Self: CppPeerConstructorWith<CppPeer, Args>,
+    {
+        make_cpp_owned_peer_thread_safe(me, |_, holder| Self::make_peer_with(holder, args))
+    }
+
+    /// As [`CppSubclassThreadSafe::new_rust_owned_thread_safe`], but
+    /// constructs the C++ superclass using the constructor which accepts
+    /// `args`. See [`CppSubclass::new_rust_owned_with`].
+    fn new_rust_owned_thread_safe_with<Args>(me: Self, args: Args) -> Arc<Mutex<Self>>
+    where
+        Self: CppPeerConstructorWith<CppPeer, Args>,
+    {
+        make_owning_peer_thread_safe(
+            me,
+            |_, holder| Self::make_peer_with(holder, args),
+            |me| CppSubclassRustPeerHolder::UnownedThreadSafe(Arc::downgrade(&me)),
+        )
+    }
+
+    /// As [`CppSubclassThreadSafe::new_self_owned_thread_safe`], but
+    /// constructs the C++ superclass using the constructor which accepts
+    /// `args`. See [`CppSubclassSelfOwned::new_self_owned_with`].
+    fn new_self_owned_thread_safe_with<Args>(me: Self, args: Args) -> Arc<Mutex<Self>>
+    where
+        Self: CppSubclassSelfOwned<CppPeer> + CppPeerConstructorWith<CppPeer, Args>,
+    {
+        make_owning_peer_thread_safe(
+            me,
+            |_, holder| Self::make_peer_with(holder, args),
+            CppSubclassRustPeerHolder::OwnedThreadSafe,
+        )
+    }
+
+    /// Creates a new instance of this subclass, owned by the returned
+    /// [`cxx::SharedPtr`]. See [`CppSubclass::new_shared_owned`].
+    fn new_shared_owned_thread_safe(me: Self) -> SharedPtr<CppPeer> {
+        CppPeer::into_shared(Self::new_cpp_owned_thread_safe(me))
+    }
+
+    /// As [`CppSubclassThreadSafe::new_shared_owned_thread_safe`], but
+    /// constructs the C++ superclass using the constructor which accepts
+    /// `args`. See [`CppSubclass::new_shared_owned_with`].
+    fn new_shared_owned_thread_safe_with<Args>(me: Self, args: Args) -> SharedPtr<CppPeer>
+    where
+        Self: CppPeerConstructorWith<CppPeer, Args>,
+    {
+        CppPeer::into_shared(Self::new_cpp_owned_thread_safe_with(me, args))
+    }
+}
+
 /// Provides default constructors for subclasses which implement `Default`.
 pub trait CppSubclassDefault<CppPeer: CppSubclassCppPeer>: CppSubclass<CppPeer> + Default {
     /// Create a Rust-owned instance of this subclass, initializing with default values. See
@@ -359,7 +812,7 @@ pub trait CppSubclassDefault<CppPeer: CppSubclassCppPeer>: CppSubclass<CppPeer>
 
 impl<T, CppPeer> CppSubclassDefault<CppPeer> for T
 where
-    T: CppSubclass<CppPeer> + Default,
+    T: CppSubclass<CppPeer> + CppSubclassNotThreadSafe<CppPeer> + Default,
     CppPeer: CppSubclassCppPeer,
 {
     fn default_rust_owned() -> Rc<RefCell<Self>> {
@@ -383,7 +836,7 @@ pub trait CppSubclassSelfOwnedDefault<CppPeer: CppSubclassCppPeer>:
 
 impl<T, CppPeer> CppSubclassSelfOwnedDefault<CppPeer> for T
 where
-    T: CppSubclassSelfOwned<CppPeer> + Default,
+    T: CppSubclassSelfOwned<CppPeer> + CppSubclassNotThreadSafe<CppPeer> + Default,
     CppPeer: CppSubclassCppPeer,
 {
     fn default_self_owned() -> Rc<RefCell<Self>> {